@@ -40,6 +40,88 @@ impl OxrExtensions {
         self.0.extx_overlay = true;
         self
     }
+    /// Enables `XR_MSFT_secondary_view_configuration` and `XR_MSFT_first_person_observer`.
+    pub fn enable_secondary_view_configuration(&mut self) -> &mut Self {
+        self.0.msft_secondary_view_configuration = true;
+        self.0.msft_first_person_observer = true;
+        self
+    }
+    pub fn disable_secondary_view_configuration(&mut self) -> &mut Self {
+        self.0.msft_secondary_view_configuration = false;
+        self.0.msft_first_person_observer = false;
+        self
+    }
+    /// Enables `XR_FB_foveation`, `XR_FB_foveation_configuration`, `XR_FB_foveation_vulkan` and `XR_FB_swapchain_update_state`.
+    pub fn enable_fb_foveation(&mut self) -> &mut Self {
+        self.0.fb_foveation = true;
+        self.0.fb_foveation_configuration = true;
+        self.0.fb_foveation_vulkan = true;
+        self.0.fb_swapchain_update_state = true;
+        self
+    }
+    pub fn disable_fb_foveation(&mut self) -> &mut Self {
+        self.0.fb_foveation = false;
+        self.0.fb_foveation_configuration = false;
+        self.0.fb_foveation_vulkan = false;
+        self.0.fb_swapchain_update_state = false;
+        self
+    }
+    pub fn enable_meta_foveation_eye_tracked(&mut self) -> &mut Self {
+        self.0.meta_foveation_eye_tracked = true;
+        self
+    }
+    pub fn disable_meta_foveation_eye_tracked(&mut self) -> &mut Self {
+        self.0.meta_foveation_eye_tracked = false;
+        self
+    }
+    pub fn enable_visibility_mask(&mut self) -> &mut Self {
+        self.0.khr_visibility_mask = true;
+        self
+    }
+    pub fn disable_visibility_mask(&mut self) -> &mut Self {
+        self.0.khr_visibility_mask = false;
+        self
+    }
+    pub fn enable_fb_display_refresh_rate(&mut self) -> &mut Self {
+        self.0.fb_display_refresh_rate = true;
+        self
+    }
+    pub fn disable_fb_display_refresh_rate(&mut self) -> &mut Self {
+        self.0.fb_display_refresh_rate = false;
+        self
+    }
+    pub fn enable_performance_settings(&mut self) -> &mut Self {
+        self.0.ext_performance_settings = true;
+        self
+    }
+    pub fn disable_performance_settings(&mut self) -> &mut Self {
+        self.0.ext_performance_settings = false;
+        self
+    }
+    pub fn enable_fb_space_warp(&mut self) -> &mut Self {
+        self.0.fb_space_warp = true;
+        self
+    }
+    pub fn disable_fb_space_warp(&mut self) -> &mut Self {
+        self.0.fb_space_warp = false;
+        self
+    }
+    pub fn enable_varjo_quad_views(&mut self) -> &mut Self {
+        self.0.varjo_quad_views = true;
+        self
+    }
+    pub fn disable_varjo_quad_views(&mut self) -> &mut Self {
+        self.0.varjo_quad_views = false;
+        self
+    }
+    pub fn enable_swapchain_usage_input_attachment(&mut self) -> &mut Self {
+        self.0.khr_swapchain_usage_input_attachment_bit = true;
+        self
+    }
+    pub fn disable_swapchain_usage_input_attachment(&mut self) -> &mut Self {
+        self.0.khr_swapchain_usage_input_attachment_bit = false;
+        self
+    }
     /// returns true if all of the extensions enabled are also available in `available_exts`
     pub fn is_available(&self, available_exts: &OxrExtensions) -> bool {
         self.0.intersection(&available_exts) == self.0
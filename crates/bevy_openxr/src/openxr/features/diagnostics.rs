@@ -0,0 +1,228 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use bevy_app::{App, Plugin};
+use bevy_diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic};
+use bevy_ecs::{
+    resource::Resource,
+    schedule::IntoScheduleConfigs as _,
+    system::{Res, ResMut},
+};
+use bevy_mod_xr::session::{XrFirst, XrHandleEvents, XrRenderSystems};
+use bevy_render::{Render, RenderApp};
+
+use crate::{
+    init::should_run_frame_loop,
+    render::{OxrWaitFrameSystem, begin_frame, end_frame, wait_image},
+    resources::OxrFrameState,
+};
+
+/// Registers [`Diagnostic`]s for the timings of the OpenXR frame loop.
+///
+/// Render world timings are measured in the render world and recorded on the next main world frame,
+/// so they show up in the [`DiagnosticsStore`](bevy_diagnostic::DiagnosticsStore) like any other diagnostic.
+pub struct OxrDiagnosticsPlugin;
+
+impl OxrDiagnosticsPlugin {
+    /// Time spent blocked in [`wait_frame`](crate::render::wait_frame), in ms.
+    pub const WAIT_FRAME: DiagnosticPath = DiagnosticPath::const_new("xr/wait_frame");
+    /// The predicted display period reported by the runtime, in ms.
+    pub const DISPLAY_PERIOD: DiagnosticPath = DiagnosticPath::const_new("xr/display_period");
+    /// Time between [`begin_frame`] and [`end_frame`], in ms.
+    pub const BEGIN_TO_END_FRAME: DiagnosticPath =
+        DiagnosticPath::const_new("xr/begin_to_end_frame");
+    /// Time spent blocked in [`wait_image`], in ms.
+    pub const WAIT_IMAGE: DiagnosticPath = DiagnosticPath::const_new("xr/wait_image");
+    /// Total number of frames the runtime told us not to render.
+    pub const SKIPPED_FRAMES: DiagnosticPath = DiagnosticPath::const_new("xr/skipped_frames");
+    /// Total number of frames where the predicted display time skipped at least one display period.
+    pub const MISSED_FRAMES: DiagnosticPath = DiagnosticPath::const_new("xr/missed_frames");
+}
+
+/// Measures [`OxrRenderTimings`] in the render world, does nothing if they are already measured.
+pub(crate) fn add_render_timings(app: &mut App) {
+    if app.world().contains_resource::<OxrRenderTimings>() {
+        return;
+    }
+    let render_timings = OxrRenderTimings::default();
+    app.insert_resource(render_timings.clone());
+    app.sub_app_mut(RenderApp)
+        .insert_resource(render_timings)
+        .add_systems(
+            Render,
+            (
+                mark_begin_frame.after(begin_frame),
+                start_wait_image_timer
+                    .after(mark_begin_frame)
+                    .before(wait_image),
+                stop_wait_image_timer.after(wait_image),
+            )
+                .in_set(XrRenderSystems::PreRender)
+                .run_if(should_run_frame_loop),
+        )
+        .add_systems(
+            Render,
+            mark_end_frame
+                .after(end_frame)
+                .in_set(XrRenderSystems::PostRender)
+                .run_if(should_run_frame_loop),
+        );
+}
+
+impl Plugin for OxrDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        add_render_timings(app);
+        app.register_diagnostic(Diagnostic::new(Self::WAIT_FRAME).with_suffix("ms"))
+            .register_diagnostic(Diagnostic::new(Self::DISPLAY_PERIOD).with_suffix("ms"))
+            .register_diagnostic(Diagnostic::new(Self::BEGIN_TO_END_FRAME).with_suffix("ms"))
+            .register_diagnostic(Diagnostic::new(Self::WAIT_IMAGE).with_suffix("ms"))
+            .register_diagnostic(
+                Diagnostic::new(Self::SKIPPED_FRAMES)
+                    .with_smoothing_factor(0.0)
+                    .with_max_history_length(0),
+            )
+            .register_diagnostic(
+                Diagnostic::new(Self::MISSED_FRAMES)
+                    .with_smoothing_factor(0.0)
+                    .with_max_history_length(0),
+            )
+            .init_resource::<OxrFrameLoopTimings>()
+            .add_systems(
+                XrFirst,
+                (
+                    start_wait_frame_timer.before(OxrWaitFrameSystem),
+                    record_frame_loop_diagnostics.after(OxrWaitFrameSystem),
+                )
+                    .in_set(XrHandleEvents::FrameLoop)
+                    .run_if(should_run_frame_loop),
+            );
+    }
+}
+
+#[derive(Resource, Default)]
+struct OxrFrameLoopTimings {
+    wait_frame_start: Option<Instant>,
+    last_display_time: Option<openxr::Time>,
+    skipped_frames: u64,
+    missed_frames: u64,
+    last_render_frame: u64,
+}
+
+/// Timings measured in the render world, shared with the main world.
+#[derive(Resource, Clone, Default)]
+pub(crate) struct OxrRenderTimings(Arc<Mutex<OxrRenderTimingsInner>>);
+
+#[derive(Default)]
+struct OxrRenderTimingsInner {
+    begin_frame: Option<Instant>,
+    wait_image_start: Option<Instant>,
+    wait_image: Duration,
+    latest: Option<OxrRenderFrameTimings>,
+}
+
+/// Timings of the last frame the render world finished.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct OxrRenderFrameTimings {
+    /// Counts up with every finished frame, used to tell whether a frame was already seen.
+    pub frame: u64,
+    pub begin_to_end_frame: Duration,
+    pub wait_image: Duration,
+}
+
+impl OxrRenderFrameTimings {
+    /// Time the render world spent working on the frame, without waiting on the swapchain image.
+    pub fn work_time(&self) -> Duration {
+        self.begin_to_end_frame.saturating_sub(self.wait_image)
+    }
+}
+
+impl OxrRenderTimings {
+    pub(crate) fn latest(&self) -> Option<OxrRenderFrameTimings> {
+        self.0.lock().unwrap().latest
+    }
+}
+
+fn start_wait_frame_timer(mut timings: ResMut<OxrFrameLoopTimings>) {
+    timings.wait_frame_start = Some(Instant::now());
+}
+
+fn record_frame_loop_diagnostics(
+    mut diagnostics: Diagnostics,
+    mut timings: ResMut<OxrFrameLoopTimings>,
+    render_timings: Res<OxrRenderTimings>,
+    frame_state: Res<OxrFrameState>,
+) {
+    if let Some(start) = timings.wait_frame_start.take() {
+        diagnostics.add_measurement(&OxrDiagnosticsPlugin::WAIT_FRAME, || {
+            start.elapsed().as_secs_f64() * 1000.0
+        });
+    }
+
+    let period = frame_state.predicted_display_period.as_nanos();
+    diagnostics.add_measurement(&OxrDiagnosticsPlugin::DISPLAY_PERIOD, || {
+        period as f64 / 1_000_000.0
+    });
+
+    if !frame_state.should_render {
+        timings.skipped_frames += 1;
+    }
+    if timings
+        .last_display_time
+        .is_some_and(|last| frame_state.missed_frame_since(last))
+    {
+        timings.missed_frames += 1;
+    }
+    timings.last_display_time = Some(frame_state.predicted_display_time);
+    let (skipped_frames, missed_frames) = (timings.skipped_frames, timings.missed_frames);
+    diagnostics.add_measurement(&OxrDiagnosticsPlugin::SKIPPED_FRAMES, || {
+        skipped_frames as f64
+    });
+    diagnostics.add_measurement(&OxrDiagnosticsPlugin::MISSED_FRAMES, || {
+        missed_frames as f64
+    });
+
+    let Some(render_timings) = render_timings
+        .latest()
+        .filter(|render_timings| render_timings.frame != timings.last_render_frame)
+    else {
+        return;
+    };
+    timings.last_render_frame = render_timings.frame;
+    diagnostics.add_measurement(&OxrDiagnosticsPlugin::BEGIN_TO_END_FRAME, || {
+        render_timings.begin_to_end_frame.as_secs_f64() * 1000.0
+    });
+    diagnostics.add_measurement(&OxrDiagnosticsPlugin::WAIT_IMAGE, || {
+        render_timings.wait_image.as_secs_f64() * 1000.0
+    });
+}
+
+fn mark_begin_frame(timings: Res<OxrRenderTimings>) {
+    let mut timings = timings.0.lock().unwrap();
+    timings.begin_frame = Some(Instant::now());
+    timings.wait_image = Duration::ZERO;
+}
+
+fn mark_end_frame(timings: Res<OxrRenderTimings>) {
+    let mut timings = timings.0.lock().unwrap();
+    if let Some(begin_frame) = timings.begin_frame.take() {
+        let frame = timings.latest.map_or(1, |latest| latest.frame + 1);
+        timings.latest = Some(OxrRenderFrameTimings {
+            frame,
+            begin_to_end_frame: begin_frame.elapsed(),
+            wait_image: timings.wait_image,
+        });
+    }
+}
+
+fn start_wait_image_timer(timings: Res<OxrRenderTimings>) {
+    timings.0.lock().unwrap().wait_image_start = Some(Instant::now());
+}
+
+fn stop_wait_image_timer(timings: Res<OxrRenderTimings>) {
+    let mut timings = timings.0.lock().unwrap();
+    if let Some(start) = timings.wait_image_start.take() {
+        timings.wait_image = start.elapsed();
+    }
+}
@@ -0,0 +1,97 @@
+use bevy_app::{App, Plugin, PreUpdate};
+use bevy_ecs::{
+    message::MessageWriter,
+    schedule::IntoScheduleConfigs as _,
+    system::{Res, ResMut},
+};
+use bevy_log::{error, info, warn};
+use bevy_mod_xr::{
+    display::{XrDisplayRefreshRate, XrDisplayRefreshRateChanged},
+    session::{XrPreDestroySession, XrSessionCreated},
+};
+use openxr::Event;
+
+use crate::{
+    exts::OxrEnabledExtensions,
+    openxr_session_running,
+    poll_events::{OxrEventHandlerExt, OxrEventIn},
+    session::OxrSession,
+};
+
+/// Exposes the display refresh rate through [`XrDisplayRefreshRate`] using `XR_FB_display_refresh_rate`.
+pub struct OxrDisplayRefreshRatePlugin;
+
+impl Plugin for OxrDisplayRefreshRatePlugin {
+    fn build(&self, app: &mut App) {
+        if !app
+            .world()
+            .get_resource::<OxrEnabledExtensions>()
+            .is_some_and(|e| e.fb_display_refresh_rate)
+        {
+            error!(
+                "XR_FB_display_refresh_rate is not enabled, the display refresh rate can't be changed"
+            );
+            return;
+        }
+        app.init_resource::<XrDisplayRefreshRate>()
+            .add_message::<XrDisplayRefreshRateChanged>()
+            .add_oxr_event_handler(handle_refresh_rate_event)
+            .add_systems(XrSessionCreated, init_refresh_rate)
+            .add_systems(XrPreDestroySession, reset_refresh_rate)
+            .add_systems(
+                PreUpdate,
+                apply_requested_refresh_rate.run_if(openxr_session_running),
+            );
+    }
+}
+
+fn handle_refresh_rate_event(
+    event: OxrEventIn,
+    mut refresh_rate: ResMut<XrDisplayRefreshRate>,
+    mut writer: MessageWriter<XrDisplayRefreshRateChanged>,
+) {
+    if let Event::DisplayRefreshRateChangedFB(event) = *event {
+        let from = event.from_display_refresh_rate();
+        let to = event.to_display_refresh_rate();
+        info!("display refresh rate changed from {from} Hz to {to} Hz");
+        refresh_rate.current = Some(to);
+        writer.write(XrDisplayRefreshRateChanged { from, to });
+    }
+}
+
+fn init_refresh_rate(session: Res<OxrSession>, mut refresh_rate: ResMut<XrDisplayRefreshRate>) {
+    match session.enumerate_display_refresh_rates() {
+        Ok(available) => refresh_rate.available = available,
+        Err(err) => error!("Failed to enumerate display refresh rates: {err}"),
+    }
+    match session.get_display_refresh_rate() {
+        Ok(current) => refresh_rate.current = Some(current),
+        Err(err) => error!("Failed to get display refresh rate: {err}"),
+    }
+}
+
+fn reset_refresh_rate(mut refresh_rate: ResMut<XrDisplayRefreshRate>) {
+    refresh_rate.available.clear();
+    refresh_rate.current = None;
+}
+
+fn apply_requested_refresh_rate(
+    session: Res<OxrSession>,
+    mut refresh_rate: ResMut<XrDisplayRefreshRate>,
+) {
+    let Some(rate) = refresh_rate.requested else {
+        return;
+    };
+    refresh_rate.requested = None;
+    // 0.0 clears the request and lets the runtime choose
+    if rate != 0.0 && !refresh_rate.available.contains(&rate) {
+        warn!(
+            "Display refresh rate {rate} Hz is not supported, available rates are {:?}",
+            refresh_rate.available
+        );
+        return;
+    }
+    if let Err(err) = session.request_display_refresh_rate(rate) {
+        error!("Failed to request display refresh rate {rate} Hz: {err}");
+    }
+}
@@ -0,0 +1,237 @@
+use std::time::{Duration, Instant};
+
+use bevy_app::{App, Plugin};
+use bevy_ecs::{
+    resource::Resource,
+    schedule::IntoScheduleConfigs as _,
+    system::{Res, ResMut},
+};
+use bevy_log::debug;
+use bevy_mod_xr::session::{XrFirst, XrHandleEvents};
+
+use crate::{
+    features::diagnostics::{OxrRenderTimings, add_render_timings},
+    init::should_run_frame_loop,
+    render::OxrWaitFrameSystem,
+    resources::{OxrFrameState, OxrResolutionScale},
+};
+
+/// Adjusts the [`OxrResolutionScale`] based on how long frames take compared to the display period.
+///
+/// Only the rendered area inside the swapchain image is changed, so the swapchain never has to be recreated.
+/// The frame time is the longer of the main world time between two calls to [`wait_frame`](crate::render::wait_frame)
+/// and the render world time between [`begin_frame`](crate::render::begin_frame) and
+/// [`end_frame`](crate::render::end_frame), not counting the wait for the swapchain image.
+/// GPU bound frames show up as missed frames, any frame the runtime reports as missed causes the scale to drop right away.
+pub struct OxrDynamicResolutionPlugin;
+
+impl Plugin for OxrDynamicResolutionPlugin {
+    fn build(&self, app: &mut App) {
+        add_render_timings(app);
+        app.init_resource::<OxrDynamicResolutionSettings>()
+            .init_resource::<OxrFrameTimer>()
+            .add_systems(
+                XrFirst,
+                (
+                    start_wait_timer.before(OxrWaitFrameSystem),
+                    update_resolution_scale.after(OxrWaitFrameSystem),
+                )
+                    .in_set(XrHandleEvents::FrameLoop)
+                    .run_if(should_run_frame_loop),
+            );
+    }
+}
+
+/// Settings for the [`OxrDynamicResolutionPlugin`].
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct OxrDynamicResolutionSettings {
+    /// The lowest resolution scale that will be used.
+    pub min_scale: f32,
+    /// The highest resolution scale that will be used.
+    pub max_scale: f32,
+    /// How much the scale changes in a single step.
+    pub step: f32,
+    /// Frame time, as a fraction of the display period, above which the scale is lowered.
+    pub decrease_threshold: f32,
+    /// Frame time, as a fraction of the display period, below which the scale is raised.
+    pub increase_threshold: f32,
+    /// Number of consecutive frames above [`decrease_threshold`](Self::decrease_threshold) before the scale is lowered.
+    pub decrease_delay: u32,
+    /// Number of consecutive frames below [`increase_threshold`](Self::increase_threshold) before the scale is raised.
+    pub increase_delay: u32,
+}
+
+impl Default for OxrDynamicResolutionSettings {
+    fn default() -> Self {
+        Self {
+            min_scale: 0.5,
+            max_scale: 1.0,
+            step: 0.05,
+            decrease_threshold: 0.9,
+            increase_threshold: 0.7,
+            decrease_delay: 3,
+            increase_delay: 30,
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+struct OxrFrameTimer {
+    last_wait_end: Option<Instant>,
+    work_time: Duration,
+    last_display_time: Option<openxr::Time>,
+    render_work_time: Duration,
+    last_render_frame: u64,
+    controller: OxrScaleController,
+}
+
+/// Counts frames over and under budget to change the scale with hysteresis.
+#[derive(Default)]
+struct OxrScaleController {
+    frames_over_budget: u32,
+    frames_under_budget: u32,
+}
+
+impl OxrScaleController {
+    /// Returns the scale to use after a frame with the given load, the fraction of the display period it took.
+    fn update(
+        &mut self,
+        settings: &OxrDynamicResolutionSettings,
+        scale: f32,
+        load: f32,
+        missed_frame: bool,
+    ) -> f32 {
+        let mut new_scale = scale;
+        if missed_frame || load > settings.decrease_threshold {
+            self.frames_under_budget = 0;
+            self.frames_over_budget += 1;
+            if missed_frame || self.frames_over_budget >= settings.decrease_delay {
+                self.frames_over_budget = 0;
+                new_scale -= settings.step;
+            }
+        } else if load < settings.increase_threshold {
+            self.frames_over_budget = 0;
+            self.frames_under_budget += 1;
+            if self.frames_under_budget >= settings.increase_delay {
+                self.frames_under_budget = 0;
+                new_scale += settings.step;
+            }
+        } else {
+            self.frames_over_budget = 0;
+            self.frames_under_budget = 0;
+        }
+        new_scale.clamp(settings.min_scale, settings.max_scale)
+    }
+}
+
+fn start_wait_timer(mut timer: ResMut<OxrFrameTimer>) {
+    if let Some(last_wait_end) = timer.last_wait_end {
+        timer.work_time = last_wait_end.elapsed();
+    }
+}
+
+fn update_resolution_scale(
+    settings: Res<OxrDynamicResolutionSettings>,
+    frame_state: Res<OxrFrameState>,
+    render_timings: Res<OxrRenderTimings>,
+    mut timer: ResMut<OxrFrameTimer>,
+    mut scale: ResMut<OxrResolutionScale>,
+) {
+    timer.last_wait_end = Some(Instant::now());
+
+    let missed_frame = timer
+        .last_display_time
+        .is_some_and(|last| frame_state.missed_frame_since(last));
+    timer.last_display_time = Some(frame_state.predicted_display_time);
+    if let Some(render_timings) = render_timings
+        .latest()
+        .filter(|render_timings| render_timings.frame != timer.last_render_frame)
+    {
+        timer.last_render_frame = render_timings.frame;
+        timer.render_work_time = render_timings.work_time();
+    }
+    let period = frame_state.predicted_display_period.as_nanos().max(1);
+    let work_time = timer.work_time.max(timer.render_work_time);
+    let load = work_time.as_nanos() as f32 / period as f32;
+
+    let new_scale = timer
+        .controller
+        .update(&settings, **scale, load, missed_frame);
+    if new_scale != **scale {
+        debug!("changing xr resolution scale to {new_scale}");
+        **scale = new_scale;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{OxrDynamicResolutionSettings, OxrScaleController};
+
+    fn assert_scale(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-5,
+            "expected scale {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn test_scale_decreases_after_delay() {
+        let settings = OxrDynamicResolutionSettings::default();
+        let mut controller = OxrScaleController::default();
+
+        for _ in 1..settings.decrease_delay {
+            assert_eq!(controller.update(&settings, 1.0, 0.95, false), 1.0);
+        }
+        assert_scale(controller.update(&settings, 1.0, 0.95, false), 0.95);
+    }
+
+    #[test]
+    fn test_missed_frame_decreases_immediately() {
+        let settings = OxrDynamicResolutionSettings::default();
+        let mut controller = OxrScaleController::default();
+
+        assert_scale(controller.update(&settings, 1.0, 0.0, true), 0.95);
+    }
+
+    #[test]
+    fn test_scale_increases_after_delay() {
+        let settings = OxrDynamicResolutionSettings::default();
+        let mut controller = OxrScaleController::default();
+
+        for _ in 1..settings.increase_delay {
+            assert_eq!(controller.update(&settings, 0.8, 0.5, false), 0.8);
+        }
+        assert_scale(controller.update(&settings, 0.8, 0.5, false), 0.85);
+    }
+
+    #[test]
+    fn test_hysteresis_resets_counters() {
+        let settings = OxrDynamicResolutionSettings::default();
+        let mut controller = OxrScaleController::default();
+
+        for _ in 1..settings.decrease_delay {
+            controller.update(&settings, 1.0, 0.95, false);
+        }
+        // a frame between the thresholds resets the count
+        assert_eq!(controller.update(&settings, 1.0, 0.8, false), 1.0);
+        assert_eq!(controller.update(&settings, 1.0, 0.95, false), 1.0);
+    }
+
+    #[test]
+    fn test_scale_is_clamped() {
+        let settings = OxrDynamicResolutionSettings {
+            increase_delay: 1,
+            ..Default::default()
+        };
+        let mut controller = OxrScaleController::default();
+
+        assert_eq!(
+            controller.update(&settings, settings.min_scale, 0.0, true),
+            settings.min_scale
+        );
+        assert_eq!(
+            controller.update(&settings, settings.max_scale, 0.0, false),
+            settings.max_scale
+        );
+    }
+}
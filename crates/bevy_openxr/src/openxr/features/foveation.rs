@@ -0,0 +1,767 @@
+use std::ptr;
+
+use bevy_app::{App, Plugin, PostUpdate};
+use bevy_camera::{
+    Camera, Camera3d, ManualTextureViewHandle, Projection, RenderTarget, visibility::RenderLayers,
+};
+use bevy_ecs::{
+    change_detection::DetectChanges as _,
+    component::Component,
+    entity::Entity,
+    query::{With, Without},
+    resource::Resource,
+    schedule::IntoScheduleConfigs as _,
+    system::{Commands, Query, Res, ResMut},
+    world::World,
+};
+use bevy_log::{debug_span, error, info};
+use bevy_math::UVec2;
+use bevy_mod_xr::{
+    camera::{Fov, XrCamera, XrProjection, calculate_projection},
+    session::{
+        XrFirst, XrHandleEvents, XrPreDestroySession, XrRenderSystems, XrSessionCreated, XrTracker,
+    },
+    spaces::XrPrimaryReferenceSpace,
+};
+use bevy_render::{
+    ExtractSchedule, MainWorld, Render, RenderApp,
+    extract_resource::{ExtractResource, ExtractResourcePlugin},
+    renderer::RenderDevice,
+    texture::{ManualTextureView, ManualTextureViews},
+    view::{Hdr, Msaa},
+};
+use bevy_transform::{TransformSystems, components::Transform};
+use openxr::{CompositionLayerFlags, Fovf, Posef, sys};
+
+use crate::{
+    exts::OxrEnabledExtensions,
+    graphics::{GraphicsBackend, GraphicsWrap},
+    helper_traits::ToTransform as _,
+    init::{create_swapchain, should_run_frame_loop},
+    layer_builder::{
+        CompositionLayer, CompositionLayerProjection, CompositionLayerProjectionView,
+        LayerProvider, SwapchainSubImage,
+    },
+    render::{
+        OxrWaitFrameSystem, XR_TEXTURE_INDEX, begin_frame, end_frame, update_views, wait_image,
+    },
+    resources::{
+        OxrCurrentSessionConfig, OxrFrameState, OxrInstance, OxrRenderLayers, OxrResolutionScale,
+        OxrSwapchain, OxrSwapchainImages, OxrViews,
+    },
+    session::OxrSession,
+};
+
+/// First texture view handle used by the focus cameras of the software fallback,
+/// placed between the handles of the primary and the secondary views.
+pub const XR_FOCUS_TEXTURE_INDEX: u32 = XR_TEXTURE_INDEX + 32;
+/// Part of each view's field of view, per axis in tangent space, rendered at full resolution by the software fallback.
+pub const FOCUS_FRACTION: f32 = 0.5;
+
+/// Applies fixed or eye tracked foveation to the XR views.
+///
+/// With [`OxrExtensions::enable_fb_foveation`](crate::exts::OxrExtensions::enable_fb_foveation) the swapchain is created foveated
+/// and the runtime lowers the shading rate in the periphery through `XR_FB_foveation`,
+/// eye tracked foveation additionally requires [`OxrExtensions::enable_meta_foveation_eye_tracked`](crate::exts::OxrExtensions::enable_meta_foveation_eye_tracked).
+/// On Vulkan the runtime foveates through a fragment density map that has to be attached to the render passes,
+/// which wgpu can't do yet, so [`OxrFoveationMode::Auto`] uses the software fallback there.
+/// Use [`OxrFoveationMode::Runtime`] for runtimes that apply the fragment density map on their own.
+///
+/// Without these extensions, or with [`OxrFoveationMode::Software`], the views are rendered at a reduced resolution
+/// and an extra projection layer with the center of each view at full resolution is submitted on top of them.
+/// This is not variable rate shading: wgpu doesn't expose shading rate tiles, so the periphery is rendered
+/// at a uniformly lower resolution and the runtime upscales it, while the focus layer costs an extra render of the center.
+/// The fallback controls [`OxrResolutionScale`], so it doesn't mix with
+/// [`OxrDynamicResolutionPlugin`](super::dynamic_resolution::OxrDynamicResolutionPlugin).
+///
+/// The foveation level can be changed at runtime through [`OxrFoveationSettings`],
+/// and is reapplied whenever the swapchain is recreated.
+#[derive(Default)]
+pub struct OxrFoveationPlugin {
+    pub mode: OxrFoveationMode,
+}
+
+/// How the [`OxrFoveationPlugin`] foveates the views.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OxrFoveationMode {
+    /// Uses the runtime's foveation if the extensions are enabled and it takes effect with the current graphics backend,
+    /// falls back to [`Software`](Self::Software) otherwise.
+    #[default]
+    Auto,
+    /// Only uses the runtime's foveation, even on Vulkan where it only takes effect if the runtime
+    /// applies the fragment density map itself.
+    Runtime,
+    /// Always renders a reduced resolution periphery with a full resolution focus layer.
+    Software,
+}
+
+impl Plugin for OxrFoveationPlugin {
+    fn build(&self, app: &mut App) {
+        let Some(exts) = app.world().get_resource::<OxrEnabledExtensions>() else {
+            return;
+        };
+        let runtime_supported =
+            exts.fb_foveation && exts.fb_foveation_configuration && exts.fb_swapchain_update_state;
+        let eye_tracking_supported = exts.meta_foveation_eye_tracked;
+        let runtime_applied = app
+            .world()
+            .get_resource::<OxrInstance>()
+            .is_some_and(|instance| runtime_foveation_applies(instance.backend()));
+
+        app.init_resource::<OxrFoveationSettings>()
+            .add_plugins(ExtractResourcePlugin::<OxrFoveationSettings>::default());
+
+        let mode = match self.mode {
+            OxrFoveationMode::Auto if runtime_supported && !runtime_applied => {
+                info!(
+                    "The runtime's foveation has no effect on this graphics backend, using software foveation"
+                );
+                OxrFoveationMode::Software
+            }
+            mode => mode,
+        };
+        match (mode, runtime_supported) {
+            (OxrFoveationMode::Runtime | OxrFoveationMode::Auto, true) => {
+                app.sub_app_mut(RenderApp)
+                    .insert_resource(OxrFoveationEyeTrackingSupported(eye_tracking_supported))
+                    .add_systems(
+                        Render,
+                        apply_foveation
+                            .before(begin_frame)
+                            .in_set(XrRenderSystems::PreRender)
+                            .run_if(should_run_frame_loop),
+                    );
+            }
+            (OxrFoveationMode::Runtime, false) => {
+                error!(
+                    "XR_FB_foveation, XR_FB_foveation_configuration and XR_FB_swapchain_update_state are required for foveated rendering"
+                );
+            }
+            (OxrFoveationMode::Auto | OxrFoveationMode::Software, _) => {
+                build_software_foveation(app);
+            }
+        }
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        if !render_app
+            .world()
+            .contains_resource::<OxrSoftwareFoveation>()
+        {
+            return;
+        }
+        match render_app.world_mut().get_resource_mut::<OxrRenderLayers>() {
+            // right above the projection layer, so quad layers stay on top of the focus views
+            Some(mut layers) => {
+                let index = layers.len().min(1);
+                layers.insert(index, Box::new(OxrFoveationFocusLayer));
+            }
+            None => error!(
+                "OxrRenderPlugin is missing, software foveation won't submit its focus layer"
+            ),
+        }
+    }
+}
+
+fn build_software_foveation(app: &mut App) {
+    app.add_plugins(ExtractResourcePlugin::<OxrFoveationFocus>::default())
+        .add_systems(XrSessionCreated, init_focus_views)
+        .add_systems(XrPreDestroySession, clean_focus_views)
+        .add_systems(
+            XrFirst,
+            (apply_periphery_scale, update_focus_cameras)
+                .run_if(should_run_frame_loop)
+                .after(OxrWaitFrameSystem)
+                .in_set(XrHandleEvents::FrameLoop),
+        )
+        .add_systems(
+            PostUpdate,
+            update_focus_views
+                .after(update_views)
+                .before(TransformSystems::Propagate)
+                .run_if(should_run_frame_loop),
+        );
+
+    app.sub_app_mut(RenderApp)
+        .insert_resource(OxrSoftwareFoveation)
+        .add_systems(ExtractSchedule, transfer_focus_swapchain)
+        .add_systems(XrPreDestroySession, clean_focus_views_render)
+        .add_systems(
+            Render,
+            acquire_focus_image
+                .after(wait_image)
+                .in_set(XrRenderSystems::PreRender)
+                .run_if(should_run_frame_loop),
+        )
+        .add_systems(
+            Render,
+            release_focus_image
+                .before(end_frame)
+                .in_set(XrRenderSystems::PostRender)
+                .run_if(should_run_frame_loop),
+        );
+}
+
+/// Foveation applied by the [`OxrFoveationPlugin`].
+#[derive(Resource, ExtractResource, Clone, Copy, Debug, PartialEq)]
+pub struct OxrFoveationSettings {
+    /// How strongly the periphery is foveated, [`NONE`](openxr::FoveationLevelFB::NONE) turns foveation off.
+    pub level: openxr::FoveationLevelFB,
+    /// Vertical offset of the foveation center, in degrees.
+    pub vertical_offset: f32,
+    /// Lets the runtime lower the level when the GPU isn't busy, ignored by the software fallback.
+    pub dynamic: bool,
+    /// Moves the foveation center with the user's gaze, if `XR_META_foveation_eye_tracked` is enabled.
+    /// Ignored by the software fallback.
+    pub eye_tracked: bool,
+}
+
+impl Default for OxrFoveationSettings {
+    fn default() -> Self {
+        Self {
+            level: openxr::FoveationLevelFB::MEDIUM,
+            vertical_offset: 0.0,
+            dynamic: false,
+            eye_tracked: false,
+        }
+    }
+}
+
+#[derive(Resource)]
+struct OxrFoveationEyeTrackingSupported(bool);
+
+fn apply_foveation(
+    session: Res<OxrSession>,
+    swapchain: Option<Res<OxrSwapchain>>,
+    settings: Res<OxrFoveationSettings>,
+    eye_tracking_supported: Res<OxrFoveationEyeTrackingSupported>,
+) {
+    let Some(swapchain) = swapchain else {
+        return;
+    };
+    if !settings.is_changed() && !swapchain.is_changed() {
+        return;
+    }
+    let eye_tracked = settings.eye_tracked && eye_tracking_supported.0;
+    if let Err(err) = update_swapchain_foveation(&session, &swapchain, &settings, eye_tracked) {
+        error!("Failed to apply foveation: {err}");
+    }
+}
+
+/// Creates a foveation profile from `settings` and applies it to `swapchain`.
+///
+/// The profile is destroyed right away, since the swapchain keeps its own copy of the state.
+fn update_swapchain_foveation(
+    session: &OxrSession,
+    swapchain: &OxrSwapchain,
+    settings: &OxrFoveationSettings,
+    eye_tracked: bool,
+) -> openxr::Result<()> {
+    let exts = session.instance().exts();
+    let (Some(foveation), Some(update_state)) = (
+        exts.fb_foveation.as_ref(),
+        exts.fb_swapchain_update_state.as_ref(),
+    ) else {
+        return Err(sys::Result::ERROR_EXTENSION_NOT_PRESENT);
+    };
+
+    let mut eye_tracked_info = sys::FoveationEyeTrackedProfileCreateInfoMETA {
+        ty: sys::FoveationEyeTrackedProfileCreateInfoMETA::TYPE,
+        next: ptr::null(),
+        flags: sys::FoveationEyeTrackedProfileCreateFlagsMETA::EMPTY,
+    };
+    let mut level_info = sys::FoveationLevelProfileCreateInfoFB {
+        ty: sys::FoveationLevelProfileCreateInfoFB::TYPE,
+        next: if eye_tracked {
+            &mut eye_tracked_info as *mut _ as *mut _
+        } else {
+            ptr::null_mut()
+        },
+        level: settings.level,
+        vertical_offset: settings.vertical_offset,
+        dynamic: if settings.dynamic {
+            sys::FoveationDynamicFB::LEVEL_ENABLED
+        } else {
+            sys::FoveationDynamicFB::DISABLED
+        },
+    };
+    let info = sys::FoveationProfileCreateInfoFB {
+        ty: sys::FoveationProfileCreateInfoFB::TYPE,
+        next: &mut level_info as *mut _ as *mut _,
+    };
+
+    let mut profile = sys::FoveationProfileFB::NULL;
+    cvt(unsafe { (foveation.create_foveation_profile)(session.as_raw(), &info, &mut profile) })?;
+    let state = sys::SwapchainStateFoveationFB {
+        ty: sys::SwapchainStateFoveationFB::TYPE,
+        next: ptr::null_mut(),
+        flags: sys::SwapchainStateFoveationFlagsFB::EMPTY,
+        profile,
+    };
+    let result = cvt(unsafe {
+        (update_state.update_swapchain)(swapchain.as_raw(), &state as *const _ as *const _)
+    });
+    unsafe { (foveation.destroy_foveation_profile)(profile) };
+    result.map(|_| ())
+}
+
+fn cvt(x: sys::Result) -> openxr::Result<sys::Result> {
+    if x.into_raw() >= 0 { Ok(x) } else { Err(x) }
+}
+
+/// Marks that the software fallback is used, so [`OxrFoveationPlugin::finish`] registers its layer.
+#[derive(Resource)]
+struct OxrSoftwareFoveation;
+
+/// Whether the runtime's foveation takes effect without the app attaching anything to its render passes.
+///
+/// On Vulkan it needs the fragment density map of the swapchain image attached, which wgpu doesn't support.
+fn runtime_foveation_applies(backend: GraphicsBackend) -> bool {
+    match backend {
+        #[cfg(feature = "vulkan")]
+        GraphicsWrap::Vulkan(()) => false,
+    }
+}
+
+/// Camera rendering the full resolution center of a view for the software fallback of [`OxrFoveationPlugin`].
+#[derive(Clone, Copy, Component, Debug)]
+#[require(Camera3d, XrTracker)]
+pub struct OxrFocusCamera(pub u32);
+
+/// The focus swapchain of the software fallback and the views its cameras last rendered.
+#[derive(Resource, ExtractResource, Clone)]
+pub struct OxrFoveationFocus {
+    /// Resolution of each focus view.
+    pub resolution: UVec2,
+    pub format: wgpu::TextureFormat,
+    pub images: OxrSwapchainImages,
+    /// Pose and field of view the focus cameras rendered with, in view order.
+    pub views: Vec<(Posef, Fovf)>,
+    /// Whether the focus cameras render this frame.
+    pub active: bool,
+}
+
+impl OxrFoveationFocus {
+    fn add_texture_view(
+        &self,
+        manual_texture_views: &mut ManualTextureViews,
+        texture: &wgpu::Texture,
+        view: u32,
+    ) -> ManualTextureViewHandle {
+        let texture_view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2),
+            array_layer_count: Some(1),
+            base_array_layer: view,
+            ..Default::default()
+        });
+        let handle = ManualTextureViewHandle(XR_FOCUS_TEXTURE_INDEX + view);
+        manual_texture_views.insert(
+            handle,
+            ManualTextureView {
+                texture_view: texture_view.into(),
+                size: self.resolution,
+                view_format: self.format,
+            },
+        );
+        handle
+    }
+}
+
+/// This is used solely to transport the focus swapchain from the main world to the render world.
+#[derive(Resource)]
+struct OxrFoveationFocusSwapchainTransfer(OxrSwapchain);
+
+#[derive(Resource)]
+struct OxrFoveationFocusSwapchain {
+    swapchain: OxrSwapchain,
+    acquired: bool,
+    /// Whether an image was acquired and released this frame, only then the layer is submitted.
+    released: bool,
+}
+
+/// Resolution scale of the periphery for the given foveation level.
+fn periphery_scale(level: openxr::FoveationLevelFB) -> f32 {
+    match level {
+        openxr::FoveationLevelFB::LOW => 0.75,
+        openxr::FoveationLevelFB::MEDIUM => 0.6,
+        openxr::FoveationLevelFB::HIGH => 0.5,
+        _ => 1.0,
+    }
+}
+
+/// Returns the center of `fov` covering `fraction` of it on each axis in tangent space,
+/// moved up by `vertical_offset` degrees and kept inside `fov`.
+fn focus_fov(fov: Fovf, fraction: f32, vertical_offset: f32) -> Fovf {
+    let (left, right) = (fov.angle_left.tan(), fov.angle_right.tan());
+    let (down, up) = (fov.angle_down.tan(), fov.angle_up.tan());
+    let half_width = (right - left) * 0.5 * fraction;
+    let half_height = (up - down) * 0.5 * fraction;
+    let center_x = (left + right) * 0.5;
+    let center_y = ((down + up) * 0.5 + vertical_offset.to_radians().tan())
+        .clamp(down + half_height, up - half_height);
+    Fovf {
+        angle_left: (center_x - half_width).atan(),
+        angle_right: (center_x + half_width).atan(),
+        angle_down: (center_y - half_height).atan(),
+        angle_up: (center_y + half_height).atan(),
+    }
+}
+
+fn init_focus_views(
+    session: Res<OxrSession>,
+    device: Res<RenderDevice>,
+    graphics_info: Res<OxrCurrentSessionConfig>,
+    mut manual_texture_views: ResMut<ManualTextureViews>,
+    mut commands: Commands,
+) {
+    let _span = debug_span!("xr_init_focus_views").entered();
+    let view_count = graphics_info.view_count();
+    let resolution = (0..view_count)
+        .map(|index| graphics_info.view_resolution(index))
+        .fold(UVec2::ONE, UVec2::max);
+    let resolution = (resolution.as_vec2() * FOCUS_FRACTION)
+        .ceil()
+        .as_uvec2()
+        .max(UVec2::ONE);
+    let (swapchain, images, _) = match create_swapchain(
+        &session,
+        device.wgpu_device(),
+        resolution,
+        graphics_info.format,
+        view_count,
+        graphics_info.swapchain_usage,
+    ) {
+        Ok(v) => v,
+        Err(err) => {
+            error!("Failed to create the foveation focus swapchain: {err}");
+            return;
+        }
+    };
+    info!("Foveation focus resolution: {resolution}");
+
+    let focus = OxrFoveationFocus {
+        resolution,
+        format: graphics_info.format,
+        images,
+        views: vec![],
+        active: false,
+    };
+    for view in 0..view_count {
+        let handle = focus.add_texture_view(&mut manual_texture_views, &focus.images[0], view);
+        let mut camera = commands.spawn((
+            RenderTarget::TextureView(handle),
+            OxrFocusCamera(view),
+            Camera {
+                is_active: false,
+                ..Default::default()
+            },
+            Projection::custom(XrProjection::default()),
+            Msaa::from_samples(graphics_info.sample_count),
+        ));
+        if graphics_info.hdr {
+            camera.insert(Hdr);
+        }
+    }
+    commands.insert_resource(focus);
+    commands.insert_resource(OxrFoveationFocusSwapchainTransfer(swapchain));
+}
+
+fn clean_focus_views(
+    mut manual_texture_views: ResMut<ManualTextureViews>,
+    cameras: Query<(Entity, &OxrFocusCamera)>,
+    mut scale: ResMut<OxrResolutionScale>,
+    mut commands: Commands,
+) {
+    for (entity, camera) in &cameras {
+        manual_texture_views.remove(&ManualTextureViewHandle(XR_FOCUS_TEXTURE_INDEX + camera.0));
+        commands.entity(entity).despawn();
+    }
+    *scale = OxrResolutionScale::default();
+    commands.remove_resource::<OxrFoveationFocus>();
+    commands.remove_resource::<OxrFoveationFocusSwapchainTransfer>();
+}
+
+fn clean_focus_views_render(mut commands: Commands) {
+    commands.remove_resource::<OxrFoveationFocusSwapchain>();
+    commands.remove_resource::<OxrFoveationFocus>();
+}
+
+/// This system transfers the focus swapchain to the render world.
+fn transfer_focus_swapchain(mut main_world: ResMut<MainWorld>, mut commands: Commands) {
+    let Some(OxrFoveationFocusSwapchainTransfer(swapchain)) = main_world.remove_resource() else {
+        return;
+    };
+    commands.insert_resource(OxrFoveationFocusSwapchain {
+        swapchain,
+        acquired: false,
+        released: false,
+    });
+}
+
+/// Renders the periphery, the full [`OxrResolutionScale`] area, at the resolution of the foveation level.
+fn apply_periphery_scale(
+    settings: Res<OxrFoveationSettings>,
+    focus: Option<Res<OxrFoveationFocus>>,
+    mut scale: ResMut<OxrResolutionScale>,
+) {
+    let Some(focus) = focus else {
+        return;
+    };
+    if !settings.is_changed() && !focus.is_added() {
+        return;
+    }
+    *scale = OxrResolutionScale(periphery_scale(settings.level));
+}
+
+fn update_focus_cameras(
+    frame_state: Res<OxrFrameState>,
+    settings: Res<OxrFoveationSettings>,
+    focus: Option<ResMut<OxrFoveationFocus>>,
+    mut cameras: Query<&mut Camera, With<OxrFocusCamera>>,
+) {
+    let Some(mut focus) = focus else {
+        return;
+    };
+    let active = frame_state.should_render && periphery_scale(settings.level) < 1.0;
+    if focus.active != active {
+        focus.active = active;
+    }
+    for mut camera in &mut cameras {
+        if camera.is_active != active {
+            camera.is_active = active;
+        }
+    }
+}
+
+/// Places the focus cameras at their view and matches what they render to the [`XrCamera`] of that view.
+fn update_focus_views(
+    views: Res<OxrViews>,
+    settings: Res<OxrFoveationSettings>,
+    focus: Option<ResMut<OxrFoveationFocus>>,
+    xr_cameras: Query<(&XrCamera, &Projection, Option<&RenderLayers>), Without<OxrFocusCamera>>,
+    mut focus_cameras: Query<(
+        Entity,
+        &mut Transform,
+        &mut Projection,
+        Option<&RenderLayers>,
+        &OxrFocusCamera,
+    )>,
+    mut commands: Commands,
+) {
+    let Some(mut focus) = focus else {
+        return;
+    };
+    if !focus.active {
+        return;
+    }
+    let mut focus_views = Vec::with_capacity(views.len());
+    for view in views.iter() {
+        focus_views.push((
+            view.pose,
+            focus_fov(view.fov, FOCUS_FRACTION, settings.vertical_offset),
+        ));
+    }
+    for (entity, mut transform, mut projection, layers, focus_camera) in &mut focus_cameras {
+        let Some((pose, fov)) = focus_views.get(focus_camera.0 as usize) else {
+            continue;
+        };
+        let Some((_, xr_projection, xr_layers)) = xr_cameras
+            .iter()
+            .find(|(camera, ..)| camera.0 == focus_camera.0)
+        else {
+            continue;
+        };
+        if layers != xr_layers {
+            match xr_layers {
+                Some(xr_layers) => commands.entity(entity).insert(xr_layers.clone()),
+                None => commands.entity(entity).remove::<RenderLayers>(),
+            };
+        }
+        let Projection::Custom(custom) = projection.as_mut() else {
+            continue;
+        };
+        let Some(projection) = custom.get_mut::<XrProjection>() else {
+            continue;
+        };
+        if let Projection::Custom(xr_custom) = xr_projection
+            && let Some(xr_projection) = xr_custom.get::<XrProjection>()
+        {
+            projection.near = xr_projection.near;
+            projection.far = xr_projection.far;
+        }
+        projection.projection_matrix = calculate_projection(
+            projection.near,
+            projection.far,
+            Fov {
+                angle_left: fov.angle_left,
+                angle_right: fov.angle_right,
+                angle_down: fov.angle_down,
+                angle_up: fov.angle_up,
+            },
+        );
+        *transform = pose.to_transform();
+    }
+    focus.views = focus_views;
+}
+
+/// # Safety
+/// The image is waited on right after it is acquired, so nothing renders to it before the compositor is done reading.
+fn acquire_focus_image(
+    focus: Option<Res<OxrFoveationFocus>>,
+    swapchain: Option<ResMut<OxrFoveationFocusSwapchain>>,
+    mut manual_texture_views: ResMut<ManualTextureViews>,
+) {
+    let (Some(focus), Some(mut swapchain)) = (focus, swapchain) else {
+        return;
+    };
+    swapchain.released = false;
+    if !focus.active {
+        return;
+    }
+    let _span = debug_span!("xr_acquire_focus_image").entered();
+    let index = match swapchain.swapchain.acquire_image() {
+        Ok(index) => index,
+        Err(err) => {
+            error!("Failed to acquire focus image: {err}");
+            return;
+        }
+    };
+    swapchain.acquired = true;
+    let image = &focus.images[index as usize];
+    for view in 0..focus.views.len() as u32 {
+        focus.add_texture_view(&mut manual_texture_views, image, view);
+    }
+    if let Err(err) = swapchain.swapchain.wait_image(openxr::Duration::INFINITE) {
+        error!("Failed to wait focus image: {err}");
+    }
+}
+
+fn release_focus_image(swapchain: Option<ResMut<OxrFoveationFocusSwapchain>>) {
+    let Some(mut swapchain) = swapchain else {
+        return;
+    };
+    if std::mem::take(&mut swapchain.acquired) {
+        let _span = debug_span!("xr_release_focus_image").entered();
+        match swapchain.swapchain.release_image() {
+            Ok(()) => swapchain.released = true,
+            Err(err) => error!("Failed to release focus image: {err}"),
+        }
+    }
+}
+
+/// Projection layer with the full resolution center of every view, submitted by the software fallback of [`OxrFoveationPlugin`].
+pub struct OxrFoveationFocusLayer;
+
+impl LayerProvider for OxrFoveationFocusLayer {
+    fn get<'a>(&self, world: &'a World) -> Option<Box<dyn CompositionLayer<'a> + 'a>> {
+        let stage = world.get_resource::<XrPrimaryReferenceSpace>()?;
+        let focus = world.get_resource::<OxrFoveationFocus>()?;
+        let swapchain = world.get_resource::<OxrFoveationFocusSwapchain>()?;
+        // nothing was rendered to the swapchain this frame
+        if !swapchain.released || focus.views.is_empty() {
+            return None;
+        }
+
+        let rect = openxr::Rect2Di {
+            offset: openxr::Offset2Di { x: 0, y: 0 },
+            extent: openxr::Extent2Di {
+                width: focus.resolution.x as _,
+                height: focus.resolution.y as _,
+            },
+        };
+        let views = focus
+            .views
+            .iter()
+            .enumerate()
+            .map(|(index, (pose, fov))| {
+                CompositionLayerProjectionView::new()
+                    .pose(*pose)
+                    .fov(*fov)
+                    .sub_image(
+                        SwapchainSubImage::new()
+                            .swapchain(&swapchain.swapchain)
+                            .image_array_index(index as u32)
+                            .image_rect(rect),
+                    )
+            })
+            .collect::<Vec<_>>();
+
+        Some(Box::new(
+            CompositionLayerProjection::new()
+                .layer_flags(CompositionLayerFlags::BLEND_TEXTURE_SOURCE_ALPHA)
+                .space(stage)
+                .views(&views),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fov(left: f32, right: f32, down: f32, up: f32) -> Fovf {
+        Fovf {
+            angle_left: left.to_radians(),
+            angle_right: right.to_radians(),
+            angle_down: down.to_radians(),
+            angle_up: up.to_radians(),
+        }
+    }
+
+    fn assert_angle(actual: f32, expected_degrees: f32) {
+        assert!(
+            (actual.to_degrees() - expected_degrees).abs() < 1e-3,
+            "{} != {expected_degrees}",
+            actual.to_degrees()
+        );
+    }
+
+    #[test]
+    fn test_focus_fov_symmetric() {
+        let focus = focus_fov(fov(-45.0, 45.0, -45.0, 45.0), 0.5, 0.0);
+        let half = 0.5f32.atan().to_degrees();
+        assert_angle(focus.angle_left, -half);
+        assert_angle(focus.angle_right, half);
+        assert_angle(focus.angle_down, -half);
+        assert_angle(focus.angle_up, half);
+    }
+
+    #[test]
+    fn test_focus_fov_follows_asymmetric_center() {
+        // tangents -1..0.5 center at -0.25 with a half width of 0.375
+        let left = -45.0;
+        let right = 0.5f32.atan().to_degrees();
+        let focus = focus_fov(fov(left, right, -45.0, 45.0), 0.5, 0.0);
+        assert_angle(focus.angle_left, (-0.625f32).atan().to_degrees());
+        assert_angle(focus.angle_right, 0.125f32.atan().to_degrees());
+    }
+
+    #[test]
+    fn test_focus_fov_vertical_offset_stays_inside() {
+        let full = fov(-45.0, 45.0, -45.0, 45.0);
+        let focus = focus_fov(full, 0.5, 10.0);
+        let offset = 10.0f32.to_radians().tan();
+        assert_angle(focus.angle_down, (offset - 0.5).atan().to_degrees());
+        assert_angle(focus.angle_up, (offset + 0.5).atan().to_degrees());
+
+        let focus = focus_fov(full, 0.5, 80.0);
+        assert_angle(focus.angle_up, 45.0);
+        assert_angle(focus.angle_down, 0.0);
+    }
+
+    #[test]
+    fn test_periphery_scale() {
+        assert_eq!(periphery_scale(openxr::FoveationLevelFB::NONE), 1.0);
+        assert!(
+            periphery_scale(openxr::FoveationLevelFB::HIGH)
+                < periphery_scale(openxr::FoveationLevelFB::MEDIUM)
+        );
+        assert!(
+            periphery_scale(openxr::FoveationLevelFB::MEDIUM)
+                < periphery_scale(openxr::FoveationLevelFB::LOW)
+        );
+    }
+}
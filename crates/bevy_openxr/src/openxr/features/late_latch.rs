@@ -0,0 +1,372 @@
+use bevy_app::{App, Plugin};
+use bevy_asset::Assets;
+use bevy_ecs::{
+    entity::{Entity, EntityHashMap},
+    hierarchy::Children,
+    query::With,
+    resource::Resource,
+    schedule::IntoScheduleConfigs as _,
+    system::{Query, Res, ResMut},
+};
+use bevy_log::warn;
+use bevy_math::{Affine3, Affine3A, Mat4};
+use bevy_mesh::skinning::{SkinnedMesh, SkinnedMeshInverseBindposes};
+use bevy_mod_xr::{
+    hands::XrHandBoneEntities,
+    session::{XrRenderSystems, XrRootTransform},
+    spaces::{XrLateLatch, XrPrimaryReferenceSpace, XrReferenceSpace, XrSpace},
+};
+use bevy_pbr::{
+    ExtractedPointLight, MAX_JOINTS, MeshInputUniform, MeshUniform, RenderMeshInstances,
+    SkinUniforms, collect_meshes_for_gpu_building, prepare_skins,
+};
+use bevy_render::{
+    Extract, ExtractSchedule, Render, RenderApp, RenderSystems,
+    batching::gpu_preprocessing::BatchedInstanceBuffers, sync_world::MainEntity,
+};
+use bevy_transform::components::{GlobalTransform, Transform};
+use openxr::sys;
+
+use crate::{
+    features::handtracking::OxrHandTracker,
+    helper_traits::ToTransform as _,
+    init::should_run_frame_loop,
+    resources::{OxrFrameState, Pipelined},
+    session::OxrSession,
+    spaces::locate_raw_hand_joints,
+};
+
+/// Locates [`XrLateLatch`] spaces and hand trackers again in the render world and moves what they carry to the new pose.
+///
+/// The correction between the pose used in the main world and the newly located pose is applied
+/// to the space, or every bone of the hand, and all of their descendants, right before rendering.
+/// This covers mesh transforms, joint matrices of skinned meshes whose joints are one of those entities
+/// and point and spot lights. Anything else extracted from their [`GlobalTransform`] keeps the main world pose.
+pub struct OxrLateLatchPlugin;
+
+impl Plugin for OxrLateLatchPlugin {
+    fn build(&self, app: &mut App) {
+        app.sub_app_mut(RenderApp)
+            .init_resource::<OxrLateLatched>()
+            .add_systems(ExtractSchedule, extract_late_latched)
+            .add_systems(
+                Render,
+                locate_late_latched
+                    .in_set(XrRenderSystems::PreRender)
+                    .run_if(should_run_frame_loop),
+            )
+            .add_systems(
+                Render,
+                patch_late_latched_meshes
+                    .in_set(RenderSystems::PrepareMeshes)
+                    .after(collect_meshes_for_gpu_building)
+                    .run_if(should_run_frame_loop),
+            )
+            .add_systems(
+                Render,
+                patch_late_latched_skins
+                    .in_set(RenderSystems::PrepareResources)
+                    .before(prepare_skins)
+                    .run_if(should_run_frame_loop),
+            );
+    }
+}
+
+/// An entity that is moved to a late latched pose, together with all of its descendants.
+struct OxrLateLatchedTree {
+    /// The transform of the root entity as used by the main world.
+    transform: GlobalTransform,
+    /// The root entity and all of its descendants, with their transforms as used by the main world.
+    entities: Vec<(MainEntity, GlobalTransform)>,
+    /// Correction from the main world pose to the late latched pose.
+    correction: Option<Affine3A>,
+}
+
+struct OxrLateLatchedSpace {
+    space: XrSpace,
+    ref_space: Option<XrReferenceSpace>,
+    tree: usize,
+}
+
+struct OxrLateLatchedHand {
+    /// Owned by the [`OxrHandTracker`] in the main world.
+    tracker: sys::HandTrackerEXT,
+    ref_space: Option<XrReferenceSpace>,
+    /// The joint index and the tree of every bone.
+    bones: Vec<(usize, usize)>,
+}
+
+/// A joint of a skinned mesh that belongs to a late latched tree.
+struct OxrLateLatchedJoint {
+    skin: MainEntity,
+    index: usize,
+    /// The joint matrix as computed from the main world transform.
+    matrix: Mat4,
+    tree: usize,
+}
+
+#[derive(Resource, Default)]
+struct OxrLateLatched {
+    trees: Vec<OxrLateLatchedTree>,
+    spaces: Vec<OxrLateLatchedSpace>,
+    hands: Vec<OxrLateLatchedHand>,
+    joints: Vec<OxrLateLatchedJoint>,
+}
+
+impl OxrLateLatched {
+    fn clear(&mut self) {
+        self.trees.clear();
+        self.spaces.clear();
+        self.hands.clear();
+        self.joints.clear();
+    }
+
+    /// Corrections of every late latched entity, keyed by main world entity.
+    fn corrections(&self) -> impl Iterator<Item = (MainEntity, Affine3A, &GlobalTransform)> {
+        self.trees.iter().flat_map(|tree| {
+            tree.correction.into_iter().flat_map(|correction| {
+                tree.entities
+                    .iter()
+                    .map(move |(entity, transform)| (*entity, correction, transform))
+            })
+        })
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn extract_late_latched(
+    mut late_latched: ResMut<OxrLateLatched>,
+    spaces: Extract<
+        Query<
+            (
+                Entity,
+                &XrSpace,
+                Option<&XrReferenceSpace>,
+                &GlobalTransform,
+            ),
+            With<XrLateLatch>,
+        >,
+    >,
+    hands: Extract<
+        Query<
+            (
+                &OxrHandTracker,
+                Option<&XrReferenceSpace>,
+                &XrHandBoneEntities,
+            ),
+            With<XrLateLatch>,
+        >,
+    >,
+    skinned_meshes: Extract<Query<(Entity, &SkinnedMesh)>>,
+    inverse_bindposes: Extract<Res<Assets<SkinnedMeshInverseBindposes>>>,
+    children: Extract<Query<&Children>>,
+    transforms: Extract<Query<&GlobalTransform>>,
+) {
+    let late_latched = late_latched.as_mut();
+    late_latched.clear();
+    let mut latched_entities = EntityHashMap::default();
+    let mut push_tree = |entity: Entity, transform: GlobalTransform| {
+        let tree = late_latched.trees.len();
+        let entities = std::iter::once(entity)
+            .chain(children.iter_descendants(entity))
+            .filter_map(|entity| {
+                latched_entities.insert(entity, tree);
+                transforms
+                    .get(entity)
+                    .ok()
+                    .map(|transform| (MainEntity::from(entity), *transform))
+            })
+            .collect();
+        late_latched.trees.push(OxrLateLatchedTree {
+            transform,
+            entities,
+            correction: None,
+        });
+        tree
+    };
+    for (entity, space, ref_space, transform) in &spaces {
+        let tree = push_tree(entity, *transform);
+        late_latched.spaces.push(OxrLateLatchedSpace {
+            space: *space,
+            ref_space: ref_space.copied(),
+            tree,
+        });
+    }
+    for (tracker, ref_space, bones) in &hands {
+        let bones = bones
+            .0
+            .iter()
+            .enumerate()
+            .filter_map(|(joint, bone)| {
+                let transform = transforms.get(*bone).ok()?;
+                Some((joint, push_tree(*bone, *transform)))
+            })
+            .collect();
+        late_latched.hands.push(OxrLateLatchedHand {
+            tracker: tracker.as_raw(),
+            ref_space: ref_space.copied(),
+            bones,
+        });
+    }
+
+    if latched_entities.is_empty() {
+        return;
+    }
+    for (entity, skin) in &skinned_meshes {
+        let Some(inverse_bindposes) = inverse_bindposes.get(&skin.inverse_bindposes) else {
+            continue;
+        };
+        for (index, (joint, inverse_bindpose)) in skin
+            .joints
+            .iter()
+            .zip(inverse_bindposes.iter())
+            .take(MAX_JOINTS)
+            .enumerate()
+        {
+            let (Some(tree), Ok(transform)) = (latched_entities.get(joint), transforms.get(*joint))
+            else {
+                continue;
+            };
+            late_latched.joints.push(OxrLateLatchedJoint {
+                skin: entity.into(),
+                index,
+                matrix: transform.affine() * *inverse_bindpose,
+                tree: *tree,
+            });
+        }
+    }
+}
+
+fn locate_late_latched(
+    session: Res<OxrSession>,
+    default_ref_space: Res<XrPrimaryReferenceSpace>,
+    frame_state: Res<OxrFrameState>,
+    root: Res<XrRootTransform>,
+    pipelined: Option<Res<Pipelined>>,
+    mut late_latched: ResMut<OxrLateLatched>,
+) {
+    let time = if pipelined.is_some() {
+        openxr::Time::from_nanos(
+            frame_state.predicted_display_time.as_nanos()
+                + frame_state.predicted_display_period.as_nanos(),
+        )
+    } else {
+        frame_state.predicted_display_time
+    };
+    let late_latched = late_latched.as_mut();
+    let mut correct = |tree: usize, pose: Transform| {
+        let tree = &mut late_latched.trees[tree];
+        let transform = root.0.mul_transform(pose);
+        tree.correction = Some(transform.affine() * tree.transform.affine().inverse());
+    };
+    for space in &late_latched.spaces {
+        let ref_space = space.ref_space.unwrap_or(**default_ref_space);
+        let location = match session.locate_space(&space.space, &ref_space, time) {
+            Ok(location) => location,
+            Err(err) => {
+                warn!("failed to late latch space: {err}");
+                continue;
+            }
+        };
+        if location.location_flags.contains(
+            openxr::SpaceLocationFlags::POSITION_VALID
+                | openxr::SpaceLocationFlags::ORIENTATION_VALID,
+        ) {
+            correct(space.tree, location.pose.to_transform());
+        }
+    }
+    for hand in &late_latched.hands {
+        let ref_space = hand.ref_space.unwrap_or(**default_ref_space);
+        let joints =
+            match locate_raw_hand_joints(session.instance(), hand.tracker, &ref_space, time) {
+                Ok(Some(joints)) => joints,
+                Ok(None) => continue,
+                Err(err) => {
+                    warn!("failed to late latch hand: {err}");
+                    continue;
+                }
+            };
+        for (joint, tree) in &hand.bones {
+            let location = &joints[*joint];
+            if location.location_flags.contains(
+                openxr::SpaceLocationFlags::POSITION_VALID
+                    | openxr::SpaceLocationFlags::ORIENTATION_VALID,
+            ) {
+                correct(*tree, location.pose.to_transform());
+            }
+        }
+    }
+}
+
+fn patch_late_latched_meshes(
+    late_latched: Res<OxrLateLatched>,
+    mut render_mesh_instances: Option<ResMut<RenderMeshInstances>>,
+    mut instance_buffers: Option<ResMut<BatchedInstanceBuffers<MeshUniform, MeshInputUniform>>>,
+    mut point_lights: Query<(&MainEntity, &mut ExtractedPointLight)>,
+) {
+    let mut lights = EntityHashMap::default();
+    for (entity, correction, transform) in late_latched.corrections() {
+        let world_from_local = correction * transform.affine();
+        lights.insert(entity.id(), world_from_local);
+        let Some(render_mesh_instances) = render_mesh_instances.as_deref_mut() else {
+            continue;
+        };
+        match render_mesh_instances {
+            RenderMeshInstances::CpuBuilding(instances) => {
+                if let Some(instance) = instances.get_mut(&entity) {
+                    instance.transforms.world_from_local = (&world_from_local).into();
+                }
+            }
+            RenderMeshInstances::GpuBuilding(instances) => {
+                let (Some(instance), Some(instance_buffers)) =
+                    (instances.get_mut(&entity), instance_buffers.as_mut())
+                else {
+                    continue;
+                };
+                instance.center = correction.transform_point3(instance.center);
+                let index = instance.current_uniform_index.get();
+                if let Some(mut input) = instance_buffers.current_input_buffer.get(index) {
+                    input.world_from_local = Affine3::from(&world_from_local).to_transpose();
+                    instance_buffers.current_input_buffer.set(index, input);
+                }
+            }
+        }
+    }
+    if lights.is_empty() {
+        return;
+    }
+    for (entity, mut light) in &mut point_lights {
+        if let Some(world_from_local) = lights.get(&entity.id()) {
+            light.transform = GlobalTransform::from(*world_from_local);
+        }
+    }
+}
+
+/// Overwrites the joint matrices of late latched joints, before they are uploaded.
+///
+/// Bevy only writes the matrices of joints that changed, so the matrix is always written from the main world one,
+/// a correction from the previous frame must not stick around.
+fn patch_late_latched_skins(
+    late_latched: Res<OxrLateLatched>,
+    skin_uniforms: Option<ResMut<SkinUniforms>>,
+) {
+    let Some(mut skin_uniforms) = skin_uniforms else {
+        return;
+    };
+    let skin_uniforms = skin_uniforms.as_mut();
+    for joint in &late_latched.joints {
+        let Some(offset) = skin_uniforms.skin_index(joint.skin) else {
+            continue;
+        };
+        let Some(matrix) = skin_uniforms
+            .current_staging_buffer
+            .get_mut(offset as usize + joint.index)
+        else {
+            continue;
+        };
+        *matrix = match late_latched.trees[joint.tree].correction {
+            Some(correction) => Mat4::from(correction) * joint.matrix,
+            None => joint.matrix,
+        };
+    }
+}
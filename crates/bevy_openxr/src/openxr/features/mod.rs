@@ -1,4 +1,18 @@
+pub mod diagnostics;
+pub mod display_refresh_rate;
+pub mod dynamic_resolution;
+pub mod foveation;
 pub mod handtracking;
+pub mod late_latch;
 #[cfg(feature = "fb_passthrough")]
 pub mod fb_passthrough;
 pub mod overlay;
+pub mod performance_settings;
+pub mod quad_layer;
+pub mod screenshot;
+pub mod secondary_view;
+pub mod space_warp;
+#[cfg(feature = "window_support")]
+pub mod spectator;
+pub mod threaded_wait_frame;
+pub mod visibility_mask;
@@ -0,0 +1,110 @@
+use bevy_app::{App, Plugin, PreUpdate};
+use bevy_ecs::{
+    message::{Message, MessageWriter},
+    resource::Resource,
+    schedule::{IntoScheduleConfigs as _, common_conditions::resource_changed},
+    system::Res,
+};
+use bevy_log::{error, warn};
+use bevy_mod_xr::session::XrPostSessionBegin;
+use openxr::{Event, sys};
+
+use crate::{
+    exts::OxrEnabledExtensions,
+    openxr_session_running,
+    poll_events::{OxrEventHandlerExt, OxrEventIn},
+    session::OxrSession,
+};
+
+/// Sets CPU and GPU performance levels and reports thermal notifications using `XR_EXT_performance_settings`.
+///
+/// Levels are taken from [`OxrPerformanceSettings`] when the session begins and whenever the resource changes,
+/// domains without a level are left to the runtime.
+/// Notifications from the runtime are sent as [`OxrPerformanceNotification`] messages.
+pub struct OxrPerformanceSettingsPlugin;
+
+impl Plugin for OxrPerformanceSettingsPlugin {
+    fn build(&self, app: &mut App) {
+        if !app
+            .world()
+            .get_resource::<OxrEnabledExtensions>()
+            .is_some_and(|e| e.ext_performance_settings)
+        {
+            error!(
+                "XR_EXT_performance_settings is not enabled, performance levels can't be changed"
+            );
+            return;
+        }
+        app.init_resource::<OxrPerformanceSettings>()
+            .add_message::<OxrPerformanceNotification>()
+            .add_oxr_event_handler(handle_perf_settings_event)
+            .add_systems(XrPostSessionBegin, apply_performance_settings)
+            .add_systems(
+                PreUpdate,
+                apply_performance_settings
+                    .run_if(openxr_session_running)
+                    .run_if(resource_changed::<OxrPerformanceSettings>),
+            );
+    }
+}
+
+/// Performance levels requested for each domain, [`None`] keeps the runtime's default level.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OxrPerformanceSettings {
+    pub cpu: Option<openxr::PerfSettingsLevelEXT>,
+    pub gpu: Option<openxr::PerfSettingsLevelEXT>,
+}
+
+/// Message sent when the runtime reports a change of the performance notification level of a subdomain.
+///
+/// A [`WARNING`](openxr::PerfSettingsNotificationLevelEXT::WARNING) level means the app should lower its workload,
+/// [`IMPAIRED`](openxr::PerfSettingsNotificationLevelEXT::IMPAIRED) means the runtime is already throttling.
+#[derive(Message, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OxrPerformanceNotification {
+    pub domain: openxr::PerfSettingsDomainEXT,
+    pub sub_domain: openxr::PerfSettingsSubDomainEXT,
+    pub from_level: openxr::PerfSettingsNotificationLevelEXT,
+    pub to_level: openxr::PerfSettingsNotificationLevelEXT,
+}
+
+fn handle_perf_settings_event(
+    event: OxrEventIn,
+    mut writer: MessageWriter<OxrPerformanceNotification>,
+) {
+    if let Event::PerfSettingsEXT(event) = *event {
+        let notification = OxrPerformanceNotification {
+            domain: event.domain(),
+            sub_domain: event.sub_domain(),
+            from_level: event.from_level(),
+            to_level: event.to_level(),
+        };
+        if notification.to_level != openxr::PerfSettingsNotificationLevelEXT::NORMAL {
+            warn!("performance notification: {notification:?}");
+        }
+        writer.write(notification);
+    }
+}
+
+fn apply_performance_settings(session: Res<OxrSession>, settings: Res<OxrPerformanceSettings>) {
+    let Some(perf_settings) = session.instance().exts().ext_performance_settings.as_ref() else {
+        return;
+    };
+    for (domain, level) in [
+        (openxr::PerfSettingsDomainEXT::CPU, settings.cpu),
+        (openxr::PerfSettingsDomainEXT::GPU, settings.gpu),
+    ] {
+        let Some(level) = level else {
+            continue;
+        };
+        let result = unsafe {
+            (perf_settings.perf_settings_set_performance_level)(session.as_raw(), domain, level)
+        };
+        if let Err(err) = cvt(result) {
+            error!("Failed to set {domain:?} performance level to {level:?}: {err}");
+        }
+    }
+}
+
+fn cvt(x: sys::Result) -> openxr::Result<sys::Result> {
+    if x.into_raw() >= 0 { Ok(x) } else { Err(x) }
+}
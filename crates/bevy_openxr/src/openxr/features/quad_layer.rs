@@ -0,0 +1,414 @@
+use bevy_app::{App, Plugin, PostUpdate};
+use bevy_camera::{
+    ManualTextureViewHandle, visibility::InheritedVisibility, visibility::Visibility,
+};
+use bevy_ecs::{
+    change_detection::Ref,
+    component::Component,
+    entity::Entity,
+    lifecycle::RemovedComponents,
+    query::{Changed, Or, With, Without},
+    resource::Resource,
+    schedule::IntoScheduleConfigs as _,
+    system::{Commands, Query, Res, ResMut},
+    world::World,
+};
+use bevy_log::{debug_span, error};
+use bevy_math::{UVec2, Vec2};
+use bevy_mod_xr::{
+    session::{XrPreDestroySession, XrRenderSystems, XrRootTransform},
+    spaces::XrPrimaryReferenceSpace,
+};
+use bevy_platform::collections::{HashMap, HashSet};
+use bevy_render::{
+    ExtractSchedule, MainWorld, Render, RenderApp,
+    renderer::RenderDevice,
+    texture::{ManualTextureView, ManualTextureViews},
+};
+use bevy_transform::components::{GlobalTransform, Transform};
+use openxr::CompositionLayerFlags;
+
+use crate::{
+    features::secondary_view::XR_SECONDARY_TEXTURE_INDEX,
+    helper_traits::ToPosef as _,
+    init::{create_swapchain, should_run_frame_loop},
+    layer_builder::{CompositionLayer, CompositionLayerQuad, LayerProvider, SwapchainSubImage},
+    render::{OxrRetiredSwapchains, end_frame, wait_image},
+    resources::*,
+    session::OxrSession,
+};
+
+/// First texture view handle used by quad layers, placed well after the handles of the secondary views.
+pub const XR_QUAD_TEXTURE_INDEX: u32 = XR_SECONDARY_TEXTURE_INDEX + 1024;
+
+/// Submits every [`OxrQuadLayer`] as a quad composition layer.
+///
+/// The runtime composites quad layers on top of the projection layer and samples them directly,
+/// so flat content like text stays sharp without being resampled into the eye buffers.
+pub struct OxrQuadLayerPlugin;
+
+impl Plugin for OxrQuadLayerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<OxrQuadLayerTransfer>()
+            .init_resource::<OxrQuadLayerHandles>()
+            .add_systems(XrPreDestroySession, clean_quad_layers)
+            .add_systems(
+                PostUpdate,
+                (remove_quad_layers, create_quad_layers)
+                    .chain()
+                    .run_if(should_run_frame_loop),
+            );
+
+        app.sub_app_mut(RenderApp)
+            .init_resource::<OxrQuadLayers>()
+            .init_resource::<OxrRetiredSwapchains>()
+            .add_systems(ExtractSchedule, extract_quad_layers)
+            .add_systems(XrPreDestroySession, clean_quad_layers_render)
+            .add_systems(
+                Render,
+                acquire_quad_images
+                    .after(wait_image)
+                    .in_set(XrRenderSystems::PreRender)
+                    .run_if(should_run_frame_loop),
+            )
+            .add_systems(
+                Render,
+                release_quad_images
+                    .before(end_frame)
+                    .in_set(XrRenderSystems::PostRender)
+                    .run_if(should_run_frame_loop),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        match render_app.world_mut().get_resource_mut::<OxrRenderLayers>() {
+            Some(mut layers) => layers.push(Box::new(OxrQuadLayerProvider)),
+            None => error!("OxrRenderPlugin is missing, quad layers won't be submitted"),
+        }
+    }
+}
+
+/// A flat quad composited by the runtime, placed at the [`GlobalTransform`] of the entity.
+///
+/// Once the swapchain of the quad is created an [`OxrQuadLayerTarget`] is inserted,
+/// render into the quad by using it as the target of a camera.
+/// The quad faces the +Z axis of the entity and is scaled by the X and Y scale of the entity.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+#[require(Transform, Visibility)]
+pub struct OxrQuadLayer {
+    /// Resolution of the swapchain in pixels.
+    pub resolution: UVec2,
+    /// Size of the quad in meters.
+    pub size: Vec2,
+}
+
+/// Texture view of the swapchain of an [`OxrQuadLayer`], only present while the session is running.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct OxrQuadLayerTarget(pub ManualTextureViewHandle);
+
+struct OxrQuadSwapchain {
+    swapchain: OxrSwapchain,
+    images: OxrSwapchainImages,
+    handle: ManualTextureViewHandle,
+    resolution: UVec2,
+    format: wgpu::TextureFormat,
+}
+
+impl OxrQuadSwapchain {
+    fn add_texture_view(&self, manual_texture_views: &mut ManualTextureViews, index: usize) {
+        let texture_view = self.images[index].create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2),
+            array_layer_count: Some(1),
+            ..Default::default()
+        });
+        manual_texture_views.insert(
+            self.handle,
+            ManualTextureView {
+                texture_view: texture_view.into(),
+                size: self.resolution,
+                view_format: self.format,
+            },
+        );
+    }
+}
+
+/// This is used solely to transport quad swapchains from the main world to the render world.
+#[derive(Resource, Default)]
+struct OxrQuadLayerTransfer {
+    created: Vec<(Entity, OxrQuadSwapchain)>,
+    removed: Vec<Entity>,
+}
+
+/// Texture view handles of the quads in the main world.
+#[derive(Resource, Default)]
+struct OxrQuadLayerHandles {
+    handles: HashMap<Entity, ManualTextureViewHandle>,
+    /// Quads whose swapchain couldn't be created, they are only retried once they change.
+    failed: HashSet<Entity>,
+    next: u32,
+}
+
+struct OxrQuadLayerState {
+    swapchain: OxrQuadSwapchain,
+    transform: GlobalTransform,
+    size: Vec2,
+    visible: bool,
+    /// Whether an image was acquired this frame, stays set after it is released for submitting the layer.
+    acquired: bool,
+}
+
+/// The quad layers of the current session in the render world.
+#[derive(Resource, Default)]
+struct OxrQuadLayers(HashMap<Entity, OxrQuadLayerState>);
+
+fn create_quad_layers(
+    session: Res<OxrSession>,
+    device: Res<RenderDevice>,
+    graphics_info: Res<OxrCurrentSessionConfig>,
+    quads: Query<
+        (Entity, Ref<OxrQuadLayer>),
+        Or<(Changed<OxrQuadLayer>, Without<OxrQuadLayerTarget>)>,
+    >,
+    mut handles: ResMut<OxrQuadLayerHandles>,
+    mut transfer: ResMut<OxrQuadLayerTransfer>,
+    mut manual_texture_views: ResMut<ManualTextureViews>,
+    mut commands: Commands,
+) {
+    for (entity, quad) in &quads {
+        if !quad.is_changed() && handles.failed.contains(&entity) {
+            continue;
+        }
+        let _span = debug_span!("xr_create_quad_layer").entered();
+        if let Some(handle) = handles.handles.remove(&entity) {
+            manual_texture_views.remove(&handle);
+            transfer.removed.push(entity);
+        }
+        let resolution = quad.resolution.max(UVec2::ONE);
+        let (swapchain, images, _) = match create_swapchain(
+            &session,
+            device.wgpu_device(),
+            resolution,
+            graphics_info.format,
+            1,
+            graphics_info.swapchain_usage,
+        ) {
+            Ok(v) => v,
+            Err(err) => {
+                error!("Failed to create swapchain for quad layer: {err}");
+                handles.failed.insert(entity);
+                continue;
+            }
+        };
+        handles.failed.remove(&entity);
+        let handle = ManualTextureViewHandle(XR_QUAD_TEXTURE_INDEX.wrapping_add(handles.next));
+        handles.next = handles.next.wrapping_add(1);
+        handles.handles.insert(entity, handle);
+
+        let swapchain = OxrQuadSwapchain {
+            swapchain,
+            images,
+            handle,
+            resolution,
+            format: graphics_info.format,
+        };
+        swapchain.add_texture_view(&mut manual_texture_views, 0);
+        transfer.created.push((entity, swapchain));
+        commands.entity(entity).insert(OxrQuadLayerTarget(handle));
+    }
+}
+
+fn remove_quad_layers(
+    mut removed: RemovedComponents<OxrQuadLayer>,
+    mut handles: ResMut<OxrQuadLayerHandles>,
+    mut transfer: ResMut<OxrQuadLayerTransfer>,
+    mut manual_texture_views: ResMut<ManualTextureViews>,
+    mut commands: Commands,
+) {
+    for entity in removed.read() {
+        handles.failed.remove(&entity);
+        let Some(handle) = handles.handles.remove(&entity) else {
+            continue;
+        };
+        manual_texture_views.remove(&handle);
+        transfer.removed.push(entity);
+        if let Ok(mut entity) = commands.get_entity(entity) {
+            entity.try_remove::<OxrQuadLayerTarget>();
+        }
+    }
+}
+
+fn clean_quad_layers(
+    mut handles: ResMut<OxrQuadLayerHandles>,
+    mut transfer: ResMut<OxrQuadLayerTransfer>,
+    mut manual_texture_views: ResMut<ManualTextureViews>,
+    targets: Query<Entity, With<OxrQuadLayerTarget>>,
+    mut commands: Commands,
+) {
+    for (_, handle) in handles.handles.drain() {
+        manual_texture_views.remove(&handle);
+    }
+    handles.failed.clear();
+    transfer.created.clear();
+    transfer.removed.clear();
+    for entity in &targets {
+        commands.entity(entity).remove::<OxrQuadLayerTarget>();
+    }
+}
+
+fn clean_quad_layers_render(mut layers: ResMut<OxrQuadLayers>) {
+    layers.0.clear();
+}
+
+/// Transfers new quad swapchains to the render world and extracts the placement of every quad.
+fn extract_quad_layers(
+    mut main_world: ResMut<MainWorld>,
+    mut layers: ResMut<OxrQuadLayers>,
+    mut retired: ResMut<OxrRetiredSwapchains>,
+) {
+    if let Some(mut transfer) = main_world.get_resource_mut::<OxrQuadLayerTransfer>() {
+        for entity in transfer.removed.drain(..) {
+            // frames still in flight may use the images of the removed swapchain
+            if let Some(OxrQuadLayerState { swapchain, .. }) = layers.0.remove(&entity) {
+                retired.retire(swapchain.images, swapchain.swapchain);
+            }
+        }
+        for (entity, swapchain) in transfer.created.drain(..) {
+            layers.0.insert(
+                entity,
+                OxrQuadLayerState {
+                    swapchain,
+                    transform: GlobalTransform::IDENTITY,
+                    size: Vec2::ZERO,
+                    visible: false,
+                    acquired: false,
+                },
+            );
+        }
+    }
+    if layers.0.is_empty() {
+        return;
+    }
+    let world: &mut World = &mut main_world;
+    let mut quads = world.query::<(
+        Entity,
+        &OxrQuadLayer,
+        &GlobalTransform,
+        Option<&InheritedVisibility>,
+    )>();
+    for (entity, quad, transform, visibility) in quads.iter(world) {
+        if let Some(layer) = layers.0.get_mut(&entity) {
+            layer.transform = *transform;
+            layer.size = quad.size;
+            layer.visible = visibility.is_none_or(InheritedVisibility::get);
+        }
+    }
+}
+
+/// Acquires the next image of every quad, images are waited on right after they are acquired
+/// so nothing renders to them before the compositor is done reading.
+fn acquire_quad_images(
+    frame_state: Res<OxrFrameState>,
+    mut layers: ResMut<OxrQuadLayers>,
+    mut manual_texture_views: ResMut<ManualTextureViews>,
+) {
+    for layer in layers.0.values_mut() {
+        layer.acquired = false;
+        if !frame_state.should_render {
+            continue;
+        }
+        let _span = debug_span!("xr_acquire_quad_image").entered();
+        let quad = &mut layer.swapchain;
+        let index = match quad.swapchain.acquire_image() {
+            Ok(index) => index,
+            Err(err) => {
+                error!("Failed to acquire quad layer image: {err}");
+                continue;
+            }
+        };
+        layer.acquired = true;
+        quad.add_texture_view(&mut manual_texture_views, index as usize);
+        if let Err(err) = quad.swapchain.wait_image(openxr::Duration::INFINITE) {
+            error!("Failed to wait quad layer image: {err}");
+        }
+    }
+}
+
+fn release_quad_images(mut layers: ResMut<OxrQuadLayers>) {
+    for layer in layers.0.values_mut().filter(|layer| layer.acquired) {
+        let _span = debug_span!("xr_release_quad_image").entered();
+        if let Err(err) = layer.swapchain.swapchain.release_image() {
+            error!("Failed to release quad layer image: {err}");
+            layer.acquired = false;
+        }
+    }
+}
+
+/// Submits a quad composition layer for every visible quad rendered this frame.
+struct OxrQuadLayerProvider;
+
+impl LayerProvider for OxrQuadLayerProvider {
+    fn get<'a>(&'a self, _world: &'a World) -> Option<Box<dyn CompositionLayer<'a> + 'a>> {
+        // the quads are submitted by get_all
+        None
+    }
+
+    fn get_all<'a>(&'a self, world: &'a World) -> Vec<Box<dyn CompositionLayer<'a> + 'a>> {
+        quad_layers(world)
+    }
+}
+
+/// Returns a quad composition layer for every visible quad rendered this frame, ordered by entity
+/// so overlapping quads are composited in the same order every frame.
+fn quad_layers(world: &World) -> Vec<Box<dyn CompositionLayer<'_> + '_>> {
+    let (Some(stage), Some(layers)) = (
+        world.get_resource::<XrPrimaryReferenceSpace>(),
+        world.get_resource::<OxrQuadLayers>(),
+    ) else {
+        return vec![];
+    };
+    let root = world
+        .get_resource::<XrRootTransform>()
+        .map_or(GlobalTransform::IDENTITY, |root| root.0);
+    let root_inverse = root.affine().inverse();
+    let mut quads = layers
+        .0
+        .iter()
+        .filter(|(_, layer)| layer.acquired && layer.visible)
+        .collect::<Vec<_>>();
+    quads.sort_unstable_by_key(|(entity, _)| **entity);
+    quads
+        .into_iter()
+        .map(|(_, layer)| {
+            let quad = &layer.swapchain;
+            // the quad is placed relative to the reference space, which is the tracking root
+            let transform =
+                GlobalTransform::from(root_inverse * layer.transform.affine()).compute_transform();
+            let size = layer.size * transform.scale.truncate();
+            Box::new(
+                CompositionLayerQuad::new()
+                    .layer_flags(CompositionLayerFlags::BLEND_TEXTURE_SOURCE_ALPHA)
+                    .space(stage)
+                    .eye_visibility(openxr::sys::EyeVisibility::BOTH)
+                    .sub_image(
+                        SwapchainSubImage::new()
+                            .swapchain(&quad.swapchain)
+                            .image_rect(openxr::Rect2Di {
+                                offset: openxr::Offset2Di { x: 0, y: 0 },
+                                extent: openxr::Extent2Di {
+                                    width: quad.resolution.x as _,
+                                    height: quad.resolution.y as _,
+                                },
+                            }),
+                    )
+                    .pose(transform.to_posef())
+                    .size(openxr::sys::Extent2Df {
+                        width: size.x,
+                        height: size.y,
+                    }),
+            ) as Box<dyn CompositionLayer<'_> + '_>
+        })
+        .collect()
+}
@@ -0,0 +1,298 @@
+use std::sync::{
+    Arc, Mutex, OnceLock,
+    mpsc::{self, Receiver, Sender},
+};
+
+use bevy_app::{App, Plugin, PostUpdate, Update};
+use bevy_asset::RenderAssetUsages;
+use bevy_ecs::{
+    entity::Entity,
+    message::MessageReader,
+    resource::Resource,
+    schedule::IntoScheduleConfigs as _,
+    system::{Commands, Res, ResMut},
+};
+use bevy_image::Image;
+use bevy_log::{debug_span, error, warn};
+use bevy_math::UVec2;
+use bevy_mod_xr::{
+    screenshot::{XrScreenshot, XrScreenshotViews},
+    session::XrRenderSystems,
+};
+use bevy_render::{
+    ExtractSchedule, MainWorld, Render, RenderApp,
+    renderer::{RenderDevice, RenderQueue},
+    view::screenshot::{Captured, Capturing, ScreenshotCaptured, save_to_disk},
+};
+
+use crate::{
+    init::should_run_frame_loop, render::release_image, resources::*, types::SwapchainUsageFlags,
+};
+
+/// Handles [`XrScreenshot`] messages by copying the views out of the swapchain image.
+///
+/// The copy is recorded right before the image is released, the captured image is handed to bevy's
+/// screenshot machinery by triggering [`ScreenshotCaptured`] a few frames later.
+pub struct OxrScreenshotPlugin;
+
+impl Plugin for OxrScreenshotPlugin {
+    fn build(&self, app: &mut App) {
+        let (sender, receiver) = mpsc::channel();
+        app.add_message::<XrScreenshot>()
+            .init_resource::<OxrPendingScreenshots>()
+            .insert_resource(OxrCapturedScreenshots(Mutex::new(receiver)))
+            .add_systems(PostUpdate, queue_screenshots)
+            .add_systems(Update, trigger_screenshots);
+
+        app.sub_app_mut(RenderApp)
+            .init_resource::<OxrScreenshotRequests>()
+            .init_resource::<OxrScreenshotReadbacks>()
+            .insert_resource(OxrScreenshotSender(sender))
+            .add_systems(ExtractSchedule, extract_screenshots)
+            .add_systems(
+                Render,
+                (collect_screenshots, copy_screenshots.before(release_image))
+                    .chain()
+                    .in_set(XrRenderSystems::PostRender)
+                    .run_if(should_run_frame_loop),
+            );
+    }
+}
+
+/// Screenshots requested in the main world that haven't been extracted yet.
+#[derive(Resource, Default)]
+struct OxrPendingScreenshots(Vec<(Entity, XrScreenshotViews)>);
+
+#[derive(Resource)]
+struct OxrCapturedScreenshots(Mutex<Receiver<(Entity, Image)>>);
+
+#[derive(Resource)]
+struct OxrScreenshotSender(Sender<(Entity, Image)>);
+
+#[derive(Resource, Default)]
+struct OxrScreenshotRequests(Vec<(Entity, XrScreenshotViews)>);
+
+struct OxrScreenshotReadback {
+    entity: Entity,
+    buffer: wgpu::Buffer,
+    format: wgpu::TextureFormat,
+    /// Size of every captured view, the views are stored one after another in the buffer.
+    sizes: Vec<UVec2>,
+    mapped: Arc<OnceLock<bool>>,
+}
+
+#[derive(Resource, Default)]
+struct OxrScreenshotReadbacks(Vec<OxrScreenshotReadback>);
+
+fn queue_screenshots(
+    mut messages: MessageReader<XrScreenshot>,
+    mut pending: ResMut<OxrPendingScreenshots>,
+    mut commands: Commands,
+) {
+    for screenshot in messages.read() {
+        let mut entity = commands.spawn(Capturing);
+        if let Some(path) = &screenshot.path {
+            entity.observe(save_to_disk(path));
+        }
+        pending.0.push((entity.id(), screenshot.views));
+    }
+}
+
+fn trigger_screenshots(captured: Res<OxrCapturedScreenshots>, mut commands: Commands) {
+    let captured = captured.0.lock().unwrap();
+    while let Ok((entity, image)) = captured.try_recv() {
+        // bevy's screenshot plugin despawns captured screenshots
+        commands.entity(entity).insert(Captured);
+        commands.trigger(ScreenshotCaptured { entity, image });
+    }
+}
+
+fn extract_screenshots(
+    mut main_world: ResMut<MainWorld>,
+    mut requests: ResMut<OxrScreenshotRequests>,
+) {
+    if let Some(mut pending) = main_world.get_resource_mut::<OxrPendingScreenshots>() {
+        requests.0.append(&mut pending.0);
+    }
+}
+
+fn padded_bytes_per_row(width: u32, format: wgpu::TextureFormat) -> u32 {
+    let bytes_per_row = width * format.block_copy_size(None).unwrap_or(4);
+    bytes_per_row.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT) * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT
+}
+
+fn copy_screenshots(
+    mut requests: ResMut<OxrScreenshotRequests>,
+    mut readbacks: ResMut<OxrScreenshotReadbacks>,
+    frame_state: Res<OxrFrameState>,
+    graphics_info: Res<OxrCurrentSessionConfig>,
+    resolution_scale: Res<OxrResolutionScale>,
+    swapchain_images: Res<OxrSwapchainImages>,
+    image_index: Res<OxrSwapchainImageIndex>,
+    device: Res<RenderDevice>,
+    queue: Res<RenderQueue>,
+) {
+    // nothing was rendered this frame, keep the requests for the next one
+    if requests.0.is_empty() || !frame_state.should_render {
+        return;
+    }
+    let _span = debug_span!("xr_copy_screenshots").entered();
+    let format = graphics_info.format;
+    if !graphics_info
+        .swapchain_usage
+        .contains(SwapchainUsageFlags::TRANSFER_SRC)
+    {
+        error!("The XR swapchain wasn't created with TRANSFER_SRC, screenshots can't be captured");
+        requests.0.clear();
+        return;
+    }
+    if format.block_copy_size(None).is_none() {
+        error!("Can't capture screenshots of swapchain format {format:?}");
+        requests.0.clear();
+        return;
+    }
+    let source = &swapchain_images[**image_index as usize];
+    let mut encoder =
+        device
+            .wgpu_device()
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("xr_screenshot_copy"),
+            });
+    let first_new = readbacks.0.len();
+    for (entity, views) in requests.0.drain(..) {
+        let views = match views {
+            XrScreenshotViews::View(view) if view < graphics_info.view_count() => view..view + 1,
+            XrScreenshotViews::View(view) => {
+                warn!(
+                    "Can't capture view {view}, there are only {} views",
+                    graphics_info.view_count()
+                );
+                continue;
+            }
+            XrScreenshotViews::SideBySide => 0..graphics_info.view_count(),
+        };
+        let sizes = views
+            .clone()
+            .map(|view| resolution_scale.apply(graphics_info.view_resolution(view)))
+            .collect::<Vec<_>>();
+        let buffer_size = sizes
+            .iter()
+            .map(|size| (padded_bytes_per_row(size.x, format) * size.y) as u64)
+            .sum();
+        let buffer = device.wgpu_device().create_buffer(&wgpu::BufferDescriptor {
+            label: Some("xr_screenshot_buffer"),
+            size: buffer_size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let mut offset = 0;
+        for (view, size) in views.zip(&sizes) {
+            let bytes_per_row = padded_bytes_per_row(size.x, format);
+            encoder.copy_texture_to_buffer(
+                wgpu::TexelCopyTextureInfo {
+                    texture: source,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d {
+                        x: 0,
+                        y: 0,
+                        z: view,
+                    },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                wgpu::TexelCopyBufferInfo {
+                    buffer: &buffer,
+                    layout: wgpu::TexelCopyBufferLayout {
+                        offset,
+                        bytes_per_row: Some(bytes_per_row),
+                        rows_per_image: Some(size.y),
+                    },
+                },
+                wgpu::Extent3d {
+                    width: size.x,
+                    height: size.y,
+                    depth_or_array_layers: 1,
+                },
+            );
+            offset += (bytes_per_row * size.y) as u64;
+        }
+        readbacks.0.push(OxrScreenshotReadback {
+            entity,
+            buffer,
+            format,
+            sizes,
+            mapped: Arc::default(),
+        });
+    }
+    queue.submit([encoder.finish()]);
+
+    for readback in &readbacks.0[first_new..] {
+        let mapped = readback.mapped.clone();
+        readback
+            .buffer
+            .map_async(wgpu::MapMode::Read, .., move |result| {
+                let _ = mapped.set(result.is_ok());
+            });
+    }
+}
+
+fn collect_screenshots(
+    mut readbacks: ResMut<OxrScreenshotReadbacks>,
+    sender: Res<OxrScreenshotSender>,
+    device: Res<RenderDevice>,
+) {
+    if readbacks.0.is_empty() {
+        return;
+    }
+    let _ = device.wgpu_device().poll(wgpu::PollType::Poll);
+    readbacks.0.retain(|readback| {
+        let Some(&mapped) = readback.mapped.get() else {
+            return true;
+        };
+        if !mapped {
+            error!("Failed to read back XR screenshot");
+            return false;
+        }
+        let image = read_screenshot(readback);
+        readback.buffer.unmap();
+        if sender.0.send((readback.entity, image)).is_err() {
+            error!("Failed to send XR screenshot");
+        }
+        false
+    });
+}
+
+/// Places the captured views next to each other and removes the row padding.
+fn read_screenshot(readback: &OxrScreenshotReadback) -> Image {
+    let pixel_size = readback.format.block_copy_size(None).unwrap_or(4) as usize;
+    let width = readback.sizes.iter().map(|size| size.x).sum::<u32>();
+    let height = readback.sizes.iter().map(|size| size.y).max().unwrap_or(0);
+    let row_size = width as usize * pixel_size;
+    let mut data = vec![0; row_size * height as usize];
+
+    let mapped = readback.buffer.get_mapped_range(..);
+    let (mut view_offset, mut x) = (0, 0);
+    for size in &readback.sizes {
+        let padded_row = padded_bytes_per_row(size.x, readback.format) as usize;
+        let view_row = size.x as usize * pixel_size;
+        for y in 0..size.y as usize {
+            let source = view_offset + y * padded_row;
+            let target = y * row_size + x;
+            data[target..target + view_row].copy_from_slice(&mapped[source..source + view_row]);
+        }
+        view_offset += padded_row * size.y as usize;
+        x += view_row;
+    }
+    drop(mapped);
+
+    Image::new(
+        wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        wgpu::TextureDimension::D2,
+        data,
+        readback.format,
+        RenderAssetUsages::RENDER_WORLD,
+    )
+}
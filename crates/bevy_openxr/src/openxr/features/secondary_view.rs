@@ -0,0 +1,575 @@
+use bevy_app::{App, Plugin, PostUpdate};
+use bevy_camera::{Camera, Camera3d, ManualTextureViewHandle, Projection, RenderTarget};
+use bevy_derive::{Deref, DerefMut};
+use bevy_ecs::{
+    component::Component,
+    entity::Entity,
+    query::With,
+    resource::Resource,
+    schedule::IntoScheduleConfigs as _,
+    system::{Commands, Query, Res, ResMut},
+    world::World,
+};
+use bevy_log::{debug_span, error, info, warn};
+use bevy_math::UVec2;
+use bevy_mod_xr::{
+    camera::{Fov, XrProjection, calculate_projection},
+    session::{
+        XrFirst, XrHandleEvents, XrPreDestroySession, XrRenderSystems, XrSessionCreated, XrTracker,
+    },
+    spaces::XrPrimaryReferenceSpace,
+};
+use bevy_render::{
+    ExtractSchedule, MainWorld, Render, RenderApp,
+    extract_resource::{ExtractResource, ExtractResourcePlugin},
+    renderer::RenderDevice,
+    texture::{ManualTextureView, ManualTextureViews},
+    view::{Hdr, Msaa},
+};
+use bevy_transform::{TransformSystems, components::Transform};
+use openxr::{CompositionLayerFlags, ViewStateFlags};
+
+use crate::{
+    exts::OxrEnabledExtensions,
+    helper_traits::ToTransform as _,
+    init::{create_swapchain, should_run_frame_loop},
+    layer_builder::{
+        CompositionLayer, CompositionLayerProjection, CompositionLayerProjectionView,
+        LayerProvider, SwapchainSubImage,
+    },
+    render::{OxrWaitFrameSystem, XR_TEXTURE_INDEX, end_frame, wait_image},
+    resources::*,
+    session::OxrSession,
+};
+
+/// First texture view handle used by secondary views, placed well after the handles of the primary views.
+pub const XR_SECONDARY_TEXTURE_INDEX: u32 = XR_TEXTURE_INDEX + 64;
+/// Number of texture view handles reserved for each secondary view configuration.
+const MAX_SECONDARY_VIEWS: u32 = 8;
+
+/// Renders secondary view configurations, such as the first person observer view used for mixed reality capture.
+///
+/// Requires `XR_MSFT_secondary_view_configuration` to be enabled,
+/// see [`OxrExtensions::enable_secondary_view_configuration`](crate::exts::OxrExtensions::enable_secondary_view_configuration).
+/// View configurations the runtime doesn't support are skipped.
+/// Each secondary view gets its own swapchain and an [`OxrSecondaryCamera`],
+/// which is only active while the runtime reports the view configuration as active.
+pub struct OxrSecondaryViewPlugin {
+    /// Secondary view configurations to enable, if the runtime supports them.
+    pub view_configurations: Vec<openxr::ViewConfigurationType>,
+}
+
+impl Default for OxrSecondaryViewPlugin {
+    fn default() -> Self {
+        Self {
+            view_configurations: vec![
+                openxr::ViewConfigurationType::SECONDARY_MONO_FIRST_PERSON_OBSERVER_MSFT,
+            ],
+        }
+    }
+}
+
+impl Plugin for OxrSecondaryViewPlugin {
+    fn build(&self, app: &mut App) {
+        if !app
+            .world()
+            .get_resource::<OxrEnabledExtensions>()
+            .is_some_and(|e| e.msft_secondary_view_configuration)
+        {
+            error!(
+                "XR_MSFT_secondary_view_configuration is not enabled, secondary views will not be rendered"
+            );
+            return;
+        }
+
+        app.insert_resource(OxrWantedSecondaryViewConfigurations(
+            self.view_configurations.clone(),
+        ))
+        .add_plugins((
+            ExtractResourcePlugin::<OxrSecondaryViewStates>::default(),
+            ExtractResourcePlugin::<OxrSecondaryViews>::default(),
+        ))
+        .add_systems(XrSessionCreated, init_secondary_views)
+        .add_systems(XrPreDestroySession, clean_secondary_views)
+        .add_systems(
+            XrFirst,
+            update_secondary_cameras
+                .run_if(should_run_frame_loop)
+                .after(OxrWaitFrameSystem)
+                .in_set(XrHandleEvents::FrameLoop),
+        )
+        .add_systems(
+            PostUpdate,
+            (locate_secondary_views, update_secondary_views)
+                .before(TransformSystems::Propagate)
+                .chain()
+                .run_if(should_run_frame_loop),
+        );
+
+        app.sub_app_mut(RenderApp)
+            .init_resource::<OxrSecondaryRenderLayers>()
+            .add_systems(ExtractSchedule, transfer_secondary_swapchains)
+            .add_systems(XrPreDestroySession, clean_secondary_views_render)
+            .add_systems(
+                Render,
+                acquire_secondary_images
+                    .after(wait_image)
+                    .in_set(XrRenderSystems::PreRender)
+                    .run_if(should_run_frame_loop),
+            )
+            .add_systems(
+                Render,
+                release_secondary_images
+                    .before(end_frame)
+                    .in_set(XrRenderSystems::PostRender)
+                    .run_if(should_run_frame_loop),
+            );
+    }
+}
+
+/// Camera rendering a single view of a secondary view configuration.
+#[derive(Clone, Copy, Component, Debug)]
+#[require(Camera3d, XrTracker)]
+pub struct OxrSecondaryCamera {
+    pub view_configuration: openxr::ViewConfigurationType,
+    pub view: u32,
+}
+
+/// The view configurations requested through [`OxrSecondaryViewPlugin`].
+#[derive(Resource, Deref, Clone)]
+struct OxrWantedSecondaryViewConfigurations(Vec<openxr::ViewConfigurationType>);
+
+/// A secondary view configuration enabled for the current session.
+#[derive(Clone)]
+pub struct OxrSecondaryView {
+    pub view_configuration: openxr::ViewConfigurationType,
+    pub resolution: UVec2,
+    pub format: wgpu::TextureFormat,
+    pub images: OxrSwapchainImages,
+    /// The latest located views of this view configuration.
+    pub views: Vec<openxr::View>,
+    /// Number of views in this view configuration.
+    pub view_count: u32,
+    texture_index: u32,
+}
+
+impl OxrSecondaryView {
+    fn texture_handle(&self, view: u32) -> ManualTextureViewHandle {
+        ManualTextureViewHandle(self.texture_index + view)
+    }
+
+    fn add_texture_view(
+        &self,
+        manual_texture_views: &mut ManualTextureViews,
+        texture: &wgpu::Texture,
+        view: u32,
+    ) -> ManualTextureViewHandle {
+        let texture_view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2),
+            array_layer_count: Some(1),
+            base_array_layer: view,
+            ..Default::default()
+        });
+        let handle = self.texture_handle(view);
+        manual_texture_views.insert(
+            handle,
+            ManualTextureView {
+                texture_view: texture_view.into(),
+                size: self.resolution,
+                view_format: self.format,
+            },
+        );
+        handle
+    }
+}
+
+/// All secondary view configurations enabled for the current session.
+#[derive(Resource, ExtractResource, Deref, DerefMut, Clone, Default)]
+pub struct OxrSecondaryViews(pub Vec<OxrSecondaryView>);
+
+/// This is used solely to transport the secondary swapchains from the main world to the render world.
+#[derive(Resource)]
+struct OxrSecondarySwapchainsTransfer(Vec<OxrSwapchain>);
+
+/// Swapchains of the secondary view configurations, in the same order as [`OxrSecondaryViews`].
+#[derive(Resource)]
+pub struct OxrSecondarySwapchains {
+    pub swapchains: Vec<OxrSwapchain>,
+    acquired: Vec<bool>,
+    /// Whether an image was acquired and released this frame, only then the layer is submitted.
+    released: Vec<bool>,
+}
+
+fn init_secondary_views(
+    instance: Res<OxrInstance>,
+    system_id: Res<OxrSystemId>,
+    session: Res<OxrSession>,
+    device: Res<RenderDevice>,
+    graphics_info: Res<OxrCurrentSessionConfig>,
+    wanted: Res<OxrWantedSecondaryViewConfigurations>,
+    mut manual_texture_views: ResMut<ManualTextureViews>,
+    mut commands: Commands,
+) {
+    let available = match instance.enumerate_view_configurations(**system_id) {
+        Ok(available) => available,
+        Err(err) => {
+            error!("Failed to enumerate view configurations: {err}");
+            return;
+        }
+    };
+
+    let mut secondary_views = vec![];
+    let mut swapchains = vec![];
+    for ty in wanted.iter().copied() {
+        if !available.contains(&ty) {
+            warn!("Secondary view configuration {ty:?} is not supported by the runtime");
+            continue;
+        }
+        let _span = debug_span!("xr_init_secondary_view").entered();
+        let config_views = match instance.enumerate_view_configuration_views(**system_id, ty) {
+            Ok(views) => views,
+            Err(err) => {
+                error!("Failed to enumerate views of {ty:?}: {err}");
+                continue;
+            }
+        };
+        let resolution = config_views
+            .iter()
+            .map(|view| {
+                UVec2::new(
+                    view.recommended_image_rect_width,
+                    view.recommended_image_rect_height,
+                )
+            })
+            .fold(UVec2::ONE, UVec2::max);
+        let view_count = (config_views.len() as u32).min(MAX_SECONDARY_VIEWS);
+        let (swapchain, images, _) = match create_swapchain(
+            &session,
+            device.wgpu_device(),
+            resolution,
+            graphics_info.format,
+            view_count,
+            graphics_info.swapchain_usage,
+        ) {
+            Ok(v) => v,
+            Err(err) => {
+                error!("Failed to create swapchain for {ty:?}: {err}");
+                continue;
+            }
+        };
+        info!("Secondary view configuration {ty:?} resolution: {resolution}");
+
+        let secondary_view = OxrSecondaryView {
+            view_configuration: ty,
+            resolution,
+            format: graphics_info.format,
+            images,
+            views: vec![],
+            view_count,
+            texture_index: XR_SECONDARY_TEXTURE_INDEX
+                + secondary_views.len() as u32 * MAX_SECONDARY_VIEWS,
+        };
+        for view in 0..view_count {
+            let handle = secondary_view.add_texture_view(
+                &mut manual_texture_views,
+                &secondary_view.images[0],
+                view,
+            );
+            let mut camera = commands.spawn((
+                RenderTarget::TextureView(handle),
+                OxrSecondaryCamera {
+                    view_configuration: ty,
+                    view,
+                },
+                Camera {
+                    is_active: false,
+                    ..Default::default()
+                },
+                Projection::custom(XrProjection::default()),
+                Msaa::from_samples(graphics_info.sample_count),
+            ));
+            if graphics_info.hdr {
+                camera.insert(Hdr);
+            }
+        }
+        secondary_views.push(secondary_view);
+        swapchains.push(swapchain);
+    }
+
+    if secondary_views.is_empty() {
+        return;
+    }
+    commands.insert_resource(OxrSecondaryViewStates(
+        secondary_views
+            .iter()
+            .map(|view| OxrSecondaryViewState {
+                view_configuration: view.view_configuration,
+                active: false,
+            })
+            .collect(),
+    ));
+    commands.insert_resource(OxrSecondaryViews(secondary_views));
+    commands.insert_resource(OxrSecondarySwapchainsTransfer(swapchains));
+}
+
+fn clean_secondary_views(
+    secondary_views: Option<Res<OxrSecondaryViews>>,
+    mut manual_texture_views: ResMut<ManualTextureViews>,
+    cameras: Query<Entity, With<OxrSecondaryCamera>>,
+    mut commands: Commands,
+) {
+    if let Some(secondary_views) = secondary_views {
+        for secondary_view in secondary_views.iter() {
+            for view in 0..secondary_view.view_count {
+                manual_texture_views.remove(&secondary_view.texture_handle(view));
+            }
+        }
+    }
+    for entity in &cameras {
+        commands.entity(entity).despawn();
+    }
+    commands.remove_resource::<OxrSecondaryViews>();
+    commands.remove_resource::<OxrSecondaryViewStates>();
+    commands.remove_resource::<OxrSecondarySwapchainsTransfer>();
+}
+
+fn clean_secondary_views_render(
+    mut layers: ResMut<OxrSecondaryRenderLayers>,
+    mut commands: Commands,
+) {
+    layers.clear();
+    commands.remove_resource::<OxrSecondarySwapchains>();
+    commands.remove_resource::<OxrSecondaryViews>();
+    commands.remove_resource::<OxrSecondaryViewStates>();
+}
+
+/// This system transfers the secondary swapchains to the render world and registers their composition layers.
+fn transfer_secondary_swapchains(
+    mut main_world: ResMut<MainWorld>,
+    mut layers: ResMut<OxrSecondaryRenderLayers>,
+    mut commands: Commands,
+) {
+    let Some(OxrSecondarySwapchainsTransfer(swapchains)) = main_world.remove_resource() else {
+        return;
+    };
+    let Some(secondary_views) = main_world.get_resource::<OxrSecondaryViews>() else {
+        return;
+    };
+    layers.clear();
+    for (index, secondary_view) in secondary_views.iter().enumerate() {
+        layers.push((
+            secondary_view.view_configuration,
+            Box::new(OxrSecondaryProjectionLayer(index)),
+        ));
+    }
+    commands.insert_resource(OxrSecondarySwapchains {
+        acquired: vec![false; swapchains.len()],
+        released: vec![false; swapchains.len()],
+        swapchains,
+    });
+}
+
+fn update_secondary_cameras(
+    frame_state: Res<OxrFrameState>,
+    states: Option<Res<OxrSecondaryViewStates>>,
+    mut cameras: Query<(&mut Camera, &OxrSecondaryCamera)>,
+) {
+    for (mut camera, secondary_camera) in &mut cameras {
+        let active = frame_state.should_render
+            && states
+                .as_ref()
+                .is_some_and(|states| states.is_active(secondary_camera.view_configuration));
+        if camera.is_active != active {
+            camera.is_active = active;
+        }
+    }
+}
+
+fn locate_secondary_views(
+    session: Res<OxrSession>,
+    ref_space: Res<XrPrimaryReferenceSpace>,
+    frame_state: Res<OxrFrameState>,
+    states: Option<Res<OxrSecondaryViewStates>>,
+    secondary_views: Option<ResMut<OxrSecondaryViews>>,
+) {
+    let (Some(states), Some(mut secondary_views)) = (states, secondary_views) else {
+        return;
+    };
+    for secondary_view in secondary_views.iter_mut() {
+        if !states.is_active(secondary_view.view_configuration) {
+            continue;
+        }
+        let Ok((flags, views)) = session
+            .locate_views(
+                secondary_view.view_configuration,
+                frame_state.predicted_display_time,
+                &ref_space,
+            )
+            .inspect_err(|err| warn!("failed to locate secondary views: {err}"))
+        else {
+            continue;
+        };
+        if flags.contains(ViewStateFlags::ORIENTATION_VALID | ViewStateFlags::POSITION_VALID) {
+            secondary_view.views = views;
+        }
+    }
+}
+
+fn update_secondary_views(
+    secondary_views: Option<Res<OxrSecondaryViews>>,
+    mut query: Query<(&mut Transform, &mut Projection, &OxrSecondaryCamera)>,
+) {
+    let Some(secondary_views) = secondary_views else {
+        return;
+    };
+    for (mut transform, mut projection, camera) in &mut query {
+        let Some(view) = secondary_views
+            .iter()
+            .find(|view| view.view_configuration == camera.view_configuration)
+            .and_then(|view| view.views.get(camera.view as usize))
+        else {
+            continue;
+        };
+        let Projection::Custom(custom) = projection.as_mut() else {
+            continue;
+        };
+        let Some(projection) = custom.get_mut::<XrProjection>() else {
+            continue;
+        };
+        projection.projection_matrix = calculate_projection(
+            projection.near,
+            projection.far,
+            Fov {
+                angle_left: view.fov.angle_left,
+                angle_right: view.fov.angle_right,
+                angle_down: view.fov.angle_down,
+                angle_up: view.fov.angle_up,
+            },
+        );
+        *transform = view.pose.to_transform();
+    }
+}
+
+/// # Safety
+/// Images are waited on right after they are acquired, so nothing renders to them before the compositor is done reading.
+fn acquire_secondary_images(
+    frame_state: Res<OxrFrameState>,
+    states: Option<Res<OxrSecondaryViewStates>>,
+    secondary_views: Option<Res<OxrSecondaryViews>>,
+    swapchains: Option<ResMut<OxrSecondarySwapchains>>,
+    mut manual_texture_views: ResMut<ManualTextureViews>,
+) {
+    let (Some(states), Some(secondary_views), Some(mut swapchains)) =
+        (states, secondary_views, swapchains)
+    else {
+        return;
+    };
+    let OxrSecondarySwapchains {
+        swapchains,
+        acquired,
+        released,
+    } = &mut *swapchains;
+    released.fill(false);
+    for ((secondary_view, swapchain), acquired) in secondary_views
+        .iter()
+        .zip(swapchains.iter_mut())
+        .zip(acquired.iter_mut())
+    {
+        if !frame_state.should_render || !states.is_active(secondary_view.view_configuration) {
+            continue;
+        }
+        let _span = debug_span!("xr_acquire_secondary_image").entered();
+        let index = match swapchain.acquire_image() {
+            Ok(index) => index,
+            Err(err) => {
+                error!("Failed to acquire secondary image: {err}");
+                continue;
+            }
+        };
+        *acquired = true;
+        let image = &secondary_view.images[index as usize];
+        for view in 0..secondary_view.view_count {
+            secondary_view.add_texture_view(&mut manual_texture_views, image, view);
+        }
+        if let Err(err) = swapchain.wait_image(openxr::Duration::INFINITE) {
+            error!("Failed to wait secondary image: {err}");
+        }
+    }
+}
+
+fn release_secondary_images(swapchains: Option<ResMut<OxrSecondarySwapchains>>) {
+    let Some(mut swapchains) = swapchains else {
+        return;
+    };
+    let OxrSecondarySwapchains {
+        swapchains,
+        acquired,
+        released,
+    } = &mut *swapchains;
+    for ((swapchain, acquired), released) in swapchains
+        .iter_mut()
+        .zip(acquired.iter_mut())
+        .zip(released.iter_mut())
+    {
+        if std::mem::take(acquired) {
+            let _span = debug_span!("xr_release_secondary_image").entered();
+            match swapchain.release_image() {
+                Ok(()) => *released = true,
+                Err(err) => error!("Failed to release secondary image: {err}"),
+            }
+        }
+    }
+}
+
+/// Projection layer of the secondary view configuration at the given index of [`OxrSecondaryViews`].
+pub struct OxrSecondaryProjectionLayer(pub usize);
+
+impl LayerProvider for OxrSecondaryProjectionLayer {
+    fn get<'a>(&self, world: &'a World) -> Option<Box<dyn CompositionLayer<'a> + 'a>> {
+        let stage = world.get_resource::<XrPrimaryReferenceSpace>()?;
+        let secondary_view = world.get_resource::<OxrSecondaryViews>()?.get(self.0)?;
+        let swapchains = world.get_resource::<OxrSecondarySwapchains>()?;
+        // nothing was rendered to the swapchain this frame
+        let rendered = world.get_resource::<OxrRenderedTextureViews>()?;
+        if !swapchains.released.get(self.0).copied().unwrap_or(false)
+            || !(0..secondary_view.view_count)
+                .any(|view| rendered.contains(&secondary_view.texture_handle(view)))
+        {
+            return None;
+        }
+        let swapchain = swapchains.swapchains.get(self.0)?;
+
+        if secondary_view.views.len() < secondary_view.view_count as usize {
+            return None;
+        }
+
+        let rect = openxr::Rect2Di {
+            offset: openxr::Offset2Di { x: 0, y: 0 },
+            extent: openxr::Extent2Di {
+                width: secondary_view.resolution.x as _,
+                height: secondary_view.resolution.y as _,
+            },
+        };
+        let views = (0..secondary_view.view_count)
+            .map(|index| {
+                let view = &secondary_view.views[index as usize];
+                CompositionLayerProjectionView::new()
+                    .pose(view.pose)
+                    .fov(view.fov)
+                    .sub_image(
+                        SwapchainSubImage::new()
+                            .swapchain(swapchain)
+                            .image_array_index(index)
+                            .image_rect(rect),
+                    )
+            })
+            .collect::<Vec<_>>();
+
+        Some(Box::new(
+            CompositionLayerProjection::new()
+                .layer_flags(CompositionLayerFlags::BLEND_TEXTURE_SOURCE_ALPHA)
+                .space(stage)
+                .views(&views),
+        ))
+    }
+}
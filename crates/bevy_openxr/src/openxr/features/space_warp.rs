@@ -0,0 +1,799 @@
+use std::borrow::Cow;
+
+use bevy_app::{App, Plugin, PreUpdate};
+use bevy_core_pipeline::prepass::{
+    DepthPrepass, MotionVectorPrepass, PreviousViewData, ViewPrepassTextures,
+};
+use bevy_ecs::{
+    entity::Entity,
+    query::{Has, With, Without},
+    resource::Resource,
+    schedule::IntoScheduleConfigs as _,
+    system::{Commands, Query, Res, ResMut},
+    world::World,
+};
+use bevy_log::{debug_span, error, info};
+use bevy_math::{Mat4, UVec2, Vec4};
+use bevy_mod_xr::{
+    camera::{XrAdditionalCamera, XrCamera, XrClipPlanes},
+    session::{XrPreDestroySession, XrRenderSystems, XrRootTransform, XrSessionCreated},
+};
+use bevy_render::{
+    ExtractSchedule, MainWorld, Render, RenderApp,
+    extract_resource::{ExtractResource, ExtractResourcePlugin},
+    render_resource::TextureViewId,
+    renderer::{RenderDevice, RenderQueue},
+    view::ExtractedView,
+};
+use bevy_transform::components::GlobalTransform;
+use openxr::sys;
+
+use crate::{
+    exts::OxrEnabledExtensions,
+    helper_traits::ToPosef as _,
+    init::should_run_frame_loop,
+    layer_builder::{
+        CompositionLayerProjection, CompositionLayerSpaceWarpInfoFB, ProjectionLayerExtension,
+        SwapchainSubImage,
+    },
+    render::{end_frame, release_image, update_views_render_world, wait_image},
+    resources::*,
+    session::OxrSession,
+    types::{Result as OxrResult, SwapchainCreateFlags, SwapchainCreateInfo, SwapchainUsageFlags},
+};
+
+/// Format of the motion vector swapchain required by `XR_FB_space_warp`.
+const MOTION_VECTOR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+/// Depth formats for the depth swapchain, in order of preference.
+const DEPTH_FORMATS: [wgpu::TextureFormat; 2] = [
+    wgpu::TextureFormat::Depth32Float,
+    wgpu::TextureFormat::Depth16Unorm,
+];
+
+/// Submits motion vectors and depth with every projection view using `XR_FB_space_warp`,
+/// so the runtime can synthesize every other frame.
+///
+/// Requires [`OxrExtensions::enable_fb_space_warp`](crate::exts::OxrExtensions::enable_fb_space_warp).
+/// Space warp is off until [`OxrSpaceWarpSettings::enabled`] is set.
+/// While it is set, every [`XrCamera`] gets a [`DepthPrepass`] and a [`MotionVectorPrepass`],
+/// and the runtime is expected to throttle the app to half the display refresh rate.
+/// The prepass textures are downsampled into swapchains at the resolution recommended by the runtime after rendering.
+///
+/// Head motion is left out of the motion vectors, since the runtime reprojects it on its own,
+/// so the previous view of XR cameras is the current view while space warp is enabled.
+pub struct OxrSpaceWarpPlugin;
+
+impl Plugin for OxrSpaceWarpPlugin {
+    fn build(&self, app: &mut App) {
+        if !app
+            .world()
+            .get_resource::<OxrEnabledExtensions>()
+            .is_some_and(|e| e.fb_space_warp)
+        {
+            error!("XR_FB_space_warp is not enabled, space warp will not be used");
+            return;
+        }
+
+        app.init_resource::<OxrSpaceWarpSettings>()
+            .add_plugins((
+                ExtractResourcePlugin::<OxrSpaceWarpSettings>::default(),
+                ExtractResourcePlugin::<OxrSpaceWarpImages>::default(),
+            ))
+            .add_systems(XrSessionCreated, init_space_warp)
+            .add_systems(XrPreDestroySession, clean_space_warp)
+            .add_systems(
+                PreUpdate,
+                update_space_warp_prepasses.run_if(should_run_frame_loop),
+            );
+
+        app.sub_app_mut(RenderApp)
+            .add_systems(ExtractSchedule, transfer_space_warp_swapchains)
+            .add_systems(XrPreDestroySession, clean_space_warp_render)
+            .add_systems(
+                Render,
+                (
+                    remove_head_motion.after(update_views_render_world),
+                    acquire_space_warp_images.after(wait_image),
+                )
+                    .in_set(XrRenderSystems::PreRender)
+                    .run_if(should_run_frame_loop),
+            )
+            .add_systems(
+                Render,
+                (resolve_space_warp_images, release_space_warp_images)
+                    .chain()
+                    .before(release_image)
+                    .before(end_frame)
+                    .in_set(XrRenderSystems::PostRender)
+                    .run_if(should_run_frame_loop),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        // not initialized if the extension is missing
+        if !app.world().contains_resource::<OxrSpaceWarpSettings>() {
+            return;
+        }
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        match render_app
+            .world_mut()
+            .get_resource_mut::<OxrProjectionLayerExtensions>()
+        {
+            Some(mut extensions) => extensions.push(Box::new(OxrSpaceWarpLayerExtension)),
+            None => error!("OxrRenderPlugin is missing, space warp infos won't be submitted"),
+        }
+    }
+}
+
+/// Controls the [`OxrSpaceWarpPlugin`].
+#[derive(Resource, ExtractResource, Clone, Copy, Debug, Default, PartialEq)]
+pub struct OxrSpaceWarpSettings {
+    /// Submits motion vectors and depth, which lets the runtime render at half the display refresh rate.
+    pub enabled: bool,
+    /// Tells the runtime not to use the motion vectors of the current frame,
+    /// e.g. after a teleport or a scene cut.
+    pub skip_frame: bool,
+}
+
+/// The motion vector and depth swapchain images used by the [`OxrSpaceWarpPlugin`].
+#[derive(Resource, ExtractResource, Clone)]
+pub struct OxrSpaceWarpImages {
+    /// Resolution of the motion vector and depth swapchains, as recommended by the runtime.
+    pub resolution: UVec2,
+    pub depth_format: wgpu::TextureFormat,
+    pub motion_vectors: OxrSwapchainImages,
+    pub depth: OxrSwapchainImages,
+}
+
+/// This is used solely to transport the space warp swapchains from the main world to the render world.
+#[derive(Resource)]
+struct OxrSpaceWarpSwapchainsTransfer {
+    motion_vectors: OxrSwapchain,
+    depth: OxrSwapchain,
+}
+
+/// The motion vector and depth swapchains used by the [`OxrSpaceWarpPlugin`].
+#[derive(Resource)]
+pub struct OxrSpaceWarpSwapchains {
+    pub motion_vectors: OxrSwapchain,
+    pub depth: OxrSwapchain,
+    /// The acquired motion vector and depth image indices, until they are released.
+    acquired: Option<(u32, u32)>,
+    /// Whether every view was written this frame, so the space warp info can be submitted.
+    written: bool,
+    /// Pose of the current app space in the previous app space.
+    app_space_delta: openxr::Posef,
+    previous_root: Option<GlobalTransform>,
+    /// Clip planes of each view, used to interpret the depth images.
+    clip_planes: Vec<XrClipPlanes>,
+    pipelines: OxrSpaceWarpPipelines,
+    /// Resolve bindings of each view, kept as long as the prepass textures of the view don't change.
+    resolve_views: Vec<Option<OxrSpaceWarpResolveView>>,
+}
+
+struct OxrSpaceWarpResolveView {
+    /// The motion vector and depth prepass textures bound to [`Self::bind_group`].
+    textures: (TextureViewId, TextureViewId),
+    viewport: Vec4,
+    viewport_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+fn init_space_warp(
+    instance: Res<OxrInstance>,
+    system_id: Res<OxrSystemId>,
+    session: Res<OxrSession>,
+    device: Res<RenderDevice>,
+    graphics_info: Res<OxrCurrentSessionConfig>,
+    mut commands: Commands,
+) {
+    let resolution = match get_recommended_motion_vector_resolution(&instance, *system_id) {
+        Ok(resolution) => resolution,
+        Err(err) => {
+            error!("Failed to get space warp properties: {err}");
+            return;
+        }
+    };
+    let depth_format = match session.enumerate_swapchain_formats() {
+        Ok(formats) => DEPTH_FORMATS
+            .into_iter()
+            .find(|format| formats.contains(format)),
+        Err(err) => {
+            error!("Failed to enumerate swapchain formats: {err}");
+            return;
+        }
+    };
+    let Some(depth_format) = depth_format else {
+        error!("None of the depth formats {DEPTH_FORMATS:?} are supported by the runtime");
+        return;
+    };
+
+    let view_count = graphics_info.view_count();
+    let create = |format, usage_flags| {
+        let info = SwapchainCreateInfo {
+            create_flags: SwapchainCreateFlags::EMPTY,
+            usage_flags,
+            format,
+            sample_count: 1,
+            width: resolution.x,
+            height: resolution.y,
+            face_count: 1,
+            array_size: view_count,
+            mip_count: 1,
+        };
+        let swapchain = session.create_swapchain(info)?;
+        let images = swapchain.enumerate_images(device.wgpu_device(), info)?;
+        OxrResult::Ok((swapchain, images))
+    };
+    let swapchains = create(
+        MOTION_VECTOR_FORMAT,
+        SwapchainUsageFlags::COLOR_ATTACHMENT | SwapchainUsageFlags::SAMPLED,
+    )
+    .and_then(|motion_vectors| {
+        create(
+            depth_format,
+            SwapchainUsageFlags::DEPTH_STENCIL_ATTACHMENT | SwapchainUsageFlags::SAMPLED,
+        )
+        .map(|depth| (motion_vectors, depth))
+    });
+    let ((motion_vectors, motion_vector_images), (depth, depth_images)) = match swapchains {
+        Ok(swapchains) => swapchains,
+        Err(err) => {
+            error!("Failed to create space warp swapchains: {err}");
+            return;
+        }
+    };
+    info!("Space warp resolution: {resolution}, depth format: {depth_format:?}");
+
+    commands.insert_resource(OxrSpaceWarpImages {
+        resolution,
+        depth_format,
+        motion_vectors: motion_vector_images,
+        depth: depth_images,
+    });
+    commands.insert_resource(OxrSpaceWarpSwapchainsTransfer {
+        motion_vectors,
+        depth,
+    });
+}
+
+fn clean_space_warp(mut commands: Commands) {
+    commands.remove_resource::<OxrSpaceWarpImages>();
+    commands.remove_resource::<OxrSpaceWarpSwapchainsTransfer>();
+}
+
+fn clean_space_warp_render(mut commands: Commands) {
+    commands.remove_resource::<OxrSpaceWarpSwapchains>();
+    commands.remove_resource::<OxrSpaceWarpImages>();
+}
+
+/// Adds or removes the prepasses writing motion vectors and depth on the [`XrCamera`] of every view.
+fn update_space_warp_prepasses(
+    settings: Res<OxrSpaceWarpSettings>,
+    cameras: Query<
+        (Entity, Has<MotionVectorPrepass>),
+        (With<XrCamera>, Without<XrAdditionalCamera>),
+    >,
+    mut commands: Commands,
+) {
+    for (entity, has_prepass) in &cameras {
+        if settings.enabled && !has_prepass {
+            commands
+                .entity(entity)
+                .insert((DepthPrepass, MotionVectorPrepass));
+        } else if !settings.enabled && has_prepass {
+            commands
+                .entity(entity)
+                .remove::<(DepthPrepass, MotionVectorPrepass)>();
+        }
+    }
+}
+
+/// This system transfers the space warp swapchains to the render world.
+fn transfer_space_warp_swapchains(
+    mut main_world: ResMut<MainWorld>,
+    device: Res<RenderDevice>,
+    mut commands: Commands,
+) {
+    let Some(OxrSpaceWarpSwapchainsTransfer {
+        motion_vectors,
+        depth,
+    }) = main_world.remove_resource()
+    else {
+        return;
+    };
+    let Some(images) = main_world.get_resource::<OxrSpaceWarpImages>() else {
+        return;
+    };
+    commands.insert_resource(OxrSpaceWarpSwapchains {
+        motion_vectors,
+        depth,
+        acquired: None,
+        written: false,
+        app_space_delta: openxr::Posef::IDENTITY,
+        previous_root: None,
+        clip_planes: vec![],
+        pipelines: OxrSpaceWarpPipelines::new(device.wgpu_device(), images.depth_format),
+        resolve_views: vec![],
+    });
+}
+
+/// Makes the previous view of every XR camera its current view, so only object motion ends up in the motion vectors.
+///
+/// Also keeps track of how the app space moved since the last frame.
+fn remove_head_motion(
+    settings: Res<OxrSpaceWarpSettings>,
+    root: Res<XrRootTransform>,
+    swapchains: Option<ResMut<OxrSpaceWarpSwapchains>>,
+    mut views: Query<(&ExtractedView, &mut PreviousViewData), With<XrCamera>>,
+) {
+    let Some(mut swapchains) = swapchains else {
+        return;
+    };
+    swapchains.app_space_delta =
+        swapchains
+            .previous_root
+            .replace(root.0)
+            .map_or(openxr::Posef::IDENTITY, |previous| {
+                GlobalTransform::from(previous.affine().inverse() * root.0.affine())
+                    .compute_transform()
+                    .to_posef()
+            });
+    if !settings.enabled {
+        return;
+    }
+    for (view, mut previous_view) in &mut views {
+        let world_from_view = view.world_from_view.affine();
+        let view_from_world = Mat4::from(world_from_view.inverse());
+        let view_from_clip = view.clip_from_view.inverse();
+        *previous_view = PreviousViewData {
+            view_from_world,
+            clip_from_world: view.clip_from_view * view_from_world,
+            clip_from_view: view.clip_from_view,
+            world_from_clip: Mat4::from(world_from_view) * view_from_clip,
+            view_from_clip,
+        };
+    }
+}
+
+/// # Safety
+/// Images are waited on right after they are acquired, so nothing renders to them before the compositor is done reading.
+fn acquire_space_warp_images(
+    settings: Res<OxrSpaceWarpSettings>,
+    frame_state: Res<OxrFrameState>,
+    swapchains: Option<ResMut<OxrSpaceWarpSwapchains>>,
+) {
+    let Some(mut swapchains) = swapchains else {
+        return;
+    };
+    swapchains.written = false;
+    if !settings.enabled || !frame_state.should_render {
+        return;
+    }
+    let _span = debug_span!("xr_acquire_space_warp_images").entered();
+    let acquire = |swapchain: &mut OxrSwapchain| {
+        let index = swapchain.acquire_image()?;
+        swapchain.wait_image(openxr::Duration::INFINITE)?;
+        OxrResult::Ok(index)
+    };
+    let motion_vectors = match acquire(&mut swapchains.motion_vectors) {
+        Ok(index) => index,
+        Err(err) => {
+            error!("Failed to acquire space warp motion vector image: {err}");
+            return;
+        }
+    };
+    match acquire(&mut swapchains.depth) {
+        Ok(depth) => swapchains.acquired = Some((motion_vectors, depth)),
+        Err(err) => {
+            error!("Failed to acquire space warp depth image: {err}");
+            if let Err(err) = swapchains.motion_vectors.release_image() {
+                error!("Failed to release space warp motion vector image: {err}");
+            }
+        }
+    }
+}
+
+/// Downsamples the motion vector and depth prepass textures of every XR camera into the acquired swapchain images.
+fn resolve_space_warp_images(
+    device: Res<RenderDevice>,
+    queue: Res<RenderQueue>,
+    images: Option<Res<OxrSpaceWarpImages>>,
+    graphics_info: Res<OxrCurrentSessionConfig>,
+    swapchains: Option<ResMut<OxrSpaceWarpSwapchains>>,
+    views: Query<
+        (&XrCamera, &XrClipPlanes, &ExtractedView, &ViewPrepassTextures),
+        Without<XrAdditionalCamera>,
+    >,
+) {
+    let (Some(images), Some(mut swapchains)) = (images, swapchains) else {
+        return;
+    };
+    let Some((motion_vector_index, depth_index)) = swapchains.acquired else {
+        return;
+    };
+    let _span = debug_span!("xr_resolve_space_warp_images").entered();
+    let device = device.wgpu_device();
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("xr_space_warp_resolve"),
+    });
+    let mut written = 0;
+    let swapchains = swapchains.as_mut();
+    let view_count = graphics_info.view_count() as usize;
+    swapchains
+        .clip_planes
+        .resize(view_count, XrClipPlanes::default());
+    swapchains.resolve_views.resize_with(view_count, || None);
+    for (camera, clip_planes, view, prepass) in &views {
+        let (Some(motion_vectors), Some(depth)) = (&prepass.motion_vectors, &prepass.depth) else {
+            continue;
+        };
+        if camera.0 >= graphics_info.view_count() {
+            continue;
+        }
+        let pipeline = if motion_vectors.texture.texture.sample_count() > 1 {
+            &swapchains.pipelines.multisampled
+        } else {
+            &swapchains.pipelines.single_sampled
+        };
+        let textures = (
+            motion_vectors.texture.default_view.id(),
+            depth.texture.default_view.id(),
+        );
+        let resolve_view = &mut swapchains.resolve_views[camera.0 as usize];
+        if resolve_view
+            .as_ref()
+            .is_none_or(|resolve_view| resolve_view.textures != textures)
+        {
+            let viewport_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("xr_space_warp_viewport"),
+                size: size_of::<Vec4>() as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("xr_space_warp_resolve"),
+                layout: &pipeline.layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(
+                            &motion_vectors.texture.default_view,
+                        ),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(&depth.texture.default_view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: viewport_buffer.as_entire_binding(),
+                    },
+                ],
+            });
+            *resolve_view = Some(OxrSpaceWarpResolveView {
+                textures,
+                // never a valid viewport, so it is written below
+                viewport: Vec4::NAN,
+                viewport_buffer,
+                bind_group,
+            });
+        }
+        let Some(resolve_view) = resolve_view else {
+            continue;
+        };
+        let viewport = view.viewport.as_vec4();
+        if resolve_view.viewport != viewport {
+            queue.write_buffer(
+                &resolve_view.viewport_buffer,
+                0,
+                &viewport
+                    .to_array()
+                    .into_iter()
+                    .flat_map(f32::to_ne_bytes)
+                    .collect::<Vec<_>>(),
+            );
+            resolve_view.viewport = viewport;
+        }
+        let layer_view = |texture: &wgpu::Texture| {
+            texture.create_view(&wgpu::TextureViewDescriptor {
+                dimension: Some(wgpu::TextureViewDimension::D2),
+                array_layer_count: Some(1),
+                base_array_layer: camera.0,
+                ..Default::default()
+            })
+        };
+        let motion_vector_target = layer_view(&images.motion_vectors[motion_vector_index as usize]);
+        let depth_target = layer_view(&images.depth[depth_index as usize]);
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("xr_space_warp_resolve"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &motion_vector_target,
+                    depth_slice: None,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &depth_target,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(0.0),
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+                multiview_mask: None,
+            });
+            pass.set_pipeline(&pipeline.pipeline);
+            pass.set_bind_group(0, &resolve_view.bind_group, &[]);
+            pass.draw(0..3, 0..1);
+        }
+        swapchains.clip_planes[camera.0 as usize] = *clip_planes;
+        written += 1;
+    }
+    queue.submit([encoder.finish()]);
+    swapchains.written = written == graphics_info.view_count();
+}
+
+fn release_space_warp_images(swapchains: Option<ResMut<OxrSpaceWarpSwapchains>>) {
+    let Some(mut swapchains) = swapchains else {
+        return;
+    };
+    if swapchains.acquired.take().is_none() {
+        return;
+    }
+    let _span = debug_span!("xr_release_space_warp_images").entered();
+    if let Err(err) = swapchains
+        .motion_vectors
+        .release_image()
+        .and_then(|_| swapchains.depth.release_image())
+    {
+        error!("Failed to release space warp images: {err}");
+        swapchains.written = false;
+    }
+}
+
+/// Chains the space warp infos onto the views of the [`ProjectionLayer`](crate::layer_builder::ProjectionLayer).
+struct OxrSpaceWarpLayerExtension;
+
+impl ProjectionLayerExtension for OxrSpaceWarpLayerExtension {
+    fn extend<'a>(
+        &'a self,
+        world: &'a World,
+        layer: CompositionLayerProjection<'a>,
+    ) -> CompositionLayerProjection<'a> {
+        match space_warp_infos(world) {
+            Some(space_warp_infos) => layer.space_warp_infos(&space_warp_infos),
+            None => layer,
+        }
+    }
+}
+
+/// Returns the space warp info for each view of the projection layer,
+/// if the motion vector and depth images were written this frame.
+fn space_warp_infos(world: &World) -> Option<Vec<CompositionLayerSpaceWarpInfoFB<'_>>> {
+    let swapchains = world.get_resource::<OxrSpaceWarpSwapchains>()?;
+    let images = world.get_resource::<OxrSpaceWarpImages>()?;
+    let settings = world.get_resource::<OxrSpaceWarpSettings>()?;
+    if !settings.enabled || !swapchains.written {
+        return None;
+    }
+    let rect = openxr::Rect2Di {
+        offset: openxr::Offset2Di { x: 0, y: 0 },
+        extent: openxr::Extent2Di {
+            width: images.resolution.x as _,
+            height: images.resolution.y as _,
+        },
+    };
+    let flags = if settings.skip_frame {
+        sys::CompositionLayerSpaceWarpInfoFlagsFB::FRAME_SKIP
+    } else {
+        sys::CompositionLayerSpaceWarpInfoFlagsFB::EMPTY
+    };
+    Some(
+        swapchains
+            .clip_planes
+            .iter()
+            .enumerate()
+            .map(|(index, clip_planes)| {
+                CompositionLayerSpaceWarpInfoFB::new()
+                    .layer_flags(flags)
+                    .motion_vector_sub_image(
+                        SwapchainSubImage::new()
+                            .swapchain(&swapchains.motion_vectors)
+                            .image_array_index(index as u32)
+                            .image_rect(rect),
+                    )
+                    .app_space_delta_pose(swapchains.app_space_delta)
+                    .depth_sub_image(
+                        SwapchainSubImage::new()
+                            .swapchain(&swapchains.depth)
+                            .image_array_index(index as u32)
+                            .image_rect(rect),
+                    )
+                    .min_depth(0.0)
+                    .max_depth(1.0)
+                    // reversed depth: 0 is at the far plane or infinity and 1 at the near plane
+                    .near_z(clip_planes.far.unwrap_or(f32::INFINITY))
+                    .far_z(clip_planes.near)
+            })
+            .collect(),
+    )
+}
+
+/// Gets the motion vector resolution recommended by `XR_FB_space_warp`.
+fn get_recommended_motion_vector_resolution(
+    instance: &OxrInstance,
+    system_id: OxrSystemId,
+) -> openxr::Result<UVec2> {
+    let mut space_warp_properties = sys::SystemSpaceWarpPropertiesFB {
+        ty: sys::SystemSpaceWarpPropertiesFB::TYPE,
+        next: std::ptr::null_mut(),
+        recommended_motion_vector_image_rect_width: 0,
+        recommended_motion_vector_image_rect_height: 0,
+    };
+    let mut properties = sys::SystemProperties::out(&mut space_warp_properties as *mut _ as _);
+    unsafe {
+        cvt((instance.fp().get_system_properties)(
+            instance.as_raw(),
+            system_id.0,
+            properties.as_mut_ptr(),
+        ))?;
+    }
+    Ok(UVec2::new(
+        space_warp_properties.recommended_motion_vector_image_rect_width,
+        space_warp_properties.recommended_motion_vector_image_rect_height,
+    ))
+}
+
+struct OxrSpaceWarpPipeline {
+    layout: wgpu::BindGroupLayout,
+    pipeline: wgpu::RenderPipeline,
+}
+
+struct OxrSpaceWarpPipelines {
+    single_sampled: OxrSpaceWarpPipeline,
+    multisampled: OxrSpaceWarpPipeline,
+}
+
+impl OxrSpaceWarpPipelines {
+    fn new(device: &wgpu::Device, depth_format: wgpu::TextureFormat) -> Self {
+        Self {
+            single_sampled: OxrSpaceWarpPipeline::new(device, depth_format, false),
+            multisampled: OxrSpaceWarpPipeline::new(device, depth_format, true),
+        }
+    }
+}
+
+impl OxrSpaceWarpPipeline {
+    fn new(device: &wgpu::Device, depth_format: wgpu::TextureFormat, multisampled: bool) -> Self {
+        let source = if multisampled {
+            RESOLVE_SHADER
+                .replace("texture_2d<f32>", "texture_multisampled_2d<f32>")
+                .replace("texture_depth_2d", "texture_depth_multisampled_2d")
+        } else {
+            RESOLVE_SHADER.to_string()
+        };
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("xr_space_warp_resolve"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(source)),
+        });
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("xr_space_warp_resolve"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Depth,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("xr_space_warp_resolve"),
+            bind_group_layouts: &[Some(&layout)],
+            immediate_size: 0,
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("xr_space_warp_resolve"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vertex"),
+                compilation_options: Default::default(),
+                buffers: &[],
+            },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: depth_format,
+                depth_write_enabled: Some(true),
+                depth_compare: Some(wgpu::CompareFunction::Always),
+                stencil: Default::default(),
+                bias: Default::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fragment"),
+                compilation_options: Default::default(),
+                targets: &[Some(MOTION_VECTOR_FORMAT.into())],
+            }),
+            multiview_mask: None,
+            cache: None,
+        });
+        Self { layout, pipeline }
+    }
+}
+
+/// Copies the viewport of the prepass textures into the space warp swapchains.
+///
+/// Bevy stores motion vectors as offsets in uv space, while space warp expects them in NDC.
+const RESOLVE_SHADER: &str = r"
+@group(0) @binding(0) var motion_vectors: texture_2d<f32>;
+@group(0) @binding(1) var depth: texture_depth_2d;
+// x, y, width and height of the viewport in the prepass textures
+@group(0) @binding(2) var<uniform> viewport: vec4<f32>;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vertex(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32(index >> 1u), f32(index & 1u)) * 2.0;
+    var out: VertexOutput;
+    out.position = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+struct FragmentOutput {
+    @location(0) motion_vector: vec4<f32>,
+    @builtin(frag_depth) depth: f32,
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> FragmentOutput {
+    let coord = vec2<i32>(viewport.xy + in.uv * viewport.zw);
+    var out: FragmentOutput;
+    out.motion_vector = vec4<f32>(textureLoad(motion_vectors, coord, 0).xy * vec2<f32>(2.0, -2.0), 0.0, 0.0);
+    out.depth = textureLoad(depth, coord, 0);
+    return out;
+}
+";
+
+fn cvt(x: sys::Result) -> openxr::Result<sys::Result> {
+    if x.into_raw() >= 0 { Ok(x) } else { Err(x) }
+}
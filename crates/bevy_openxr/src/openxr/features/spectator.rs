@@ -0,0 +1,391 @@
+use bevy_app::{App, Plugin, PostUpdate};
+use bevy_asset::{Assets, Handle, RenderAssetUsages};
+use bevy_camera::{Camera, Camera2d, Camera3d};
+use bevy_derive::Deref;
+use bevy_ecs::{
+    change_detection::DetectChanges as _,
+    component::Component,
+    entity::Entity,
+    query::{Or, With, Without},
+    resource::Resource,
+    schedule::IntoScheduleConfigs as _,
+    system::{Commands, Query, Res, ResMut},
+};
+use bevy_image::Image;
+use bevy_log::{debug_span, warn_once};
+use bevy_math::{Rect, Vec3};
+use bevy_mod_xr::{
+    camera::{XrAdditionalCamera, XrCamera},
+    session::{XrPreDestroySession, XrRenderSystems, XrSessionCreated},
+};
+use bevy_render::{
+    Render, RenderApp,
+    extract_resource::{ExtractResource, ExtractResourcePlugin},
+    render_asset::RenderAssets,
+    renderer::{RenderDevice, RenderQueue},
+    texture::GpuImage,
+};
+use bevy_time::Time;
+use bevy_transform::{
+    TransformSystems,
+    components::{GlobalTransform, Transform},
+};
+use bevy_ui::{AlignItems, Display, JustifyContent, Node, UiTargetCamera, Val, widget::ImageNode};
+
+use crate::{
+    init::should_run_frame_loop, render::release_image, resources::*, types::SwapchainUsageFlags,
+};
+
+/// Shows the XR views, or a separate spectator camera, in the primary window.
+///
+/// Mirrored views are copied out of the swapchain right before the image is released,
+/// so the window shows the views of the previous XR frame.
+/// The mode can be changed at runtime through [`OxrSpectatorMode`].
+pub struct OxrSpectatorPlugin;
+
+impl Plugin for OxrSpectatorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<OxrSpectatorMode>()
+            .add_plugins((
+                ExtractResourcePlugin::<OxrSpectatorMode>::default(),
+                ExtractResourcePlugin::<OxrSpectatorImage>::default(),
+            ))
+            .add_systems(XrSessionCreated, spawn_spectator)
+            .add_systems(XrPreDestroySession, despawn_spectator)
+            .add_systems(
+                PostUpdate,
+                (update_spectator_mirror, follow_head)
+                    .before(TransformSystems::Propagate)
+                    .run_if(should_run_frame_loop),
+            );
+
+        app.sub_app_mut(RenderApp)
+            .add_systems(XrPreDestroySession, clean_spectator_render)
+            .add_systems(
+                Render,
+                copy_spectator_views
+                    .before(release_image)
+                    .in_set(XrRenderSystems::PostRender)
+                    .run_if(should_run_frame_loop),
+            );
+    }
+}
+
+/// What the [`OxrSpectatorPlugin`] shows in the primary window.
+#[derive(Resource, ExtractResource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OxrSpectatorMode {
+    #[default]
+    LeftEye,
+    RightEye,
+    SideBySide,
+    /// Renders the scene again from the [`OxrSpectatorCamera`].
+    ThirdPerson,
+}
+
+impl OxrSpectatorMode {
+    /// The views copied into the [`OxrSpectatorImage`] in this mode.
+    pub fn mirrored_views(&self) -> &'static [u32] {
+        match self {
+            Self::LeftEye => &[0],
+            Self::RightEye => &[1],
+            Self::SideBySide => &[0, 1],
+            Self::ThirdPerson => &[],
+        }
+    }
+}
+
+/// Camera rendering to the primary window while in [`OxrSpectatorMode::ThirdPerson`].
+///
+/// It follows the head unless `follow` is disabled, in which case the app is free to move it.
+#[derive(Component, Clone, Copy, Debug)]
+#[require(Camera3d)]
+pub struct OxrSpectatorCamera {
+    pub follow: bool,
+    /// Offset from the head, in the head's space.
+    pub offset: Transform,
+    /// Time in seconds it takes the camera to cover most of the distance to the head, 0 snaps to the head.
+    pub smoothing: f32,
+}
+
+impl Default for OxrSpectatorCamera {
+    fn default() -> Self {
+        Self {
+            follow: true,
+            offset: Transform::from_xyz(0.0, 0.3, 1.5),
+            smoothing: 0.3,
+        }
+    }
+}
+
+/// The image the mirrored views are copied into, with the views placed next to each other.
+#[derive(Resource, ExtractResource, Deref, Clone)]
+pub struct OxrSpectatorImage(pub Handle<Image>);
+
+/// Camera drawing the mirrored views to the primary window.
+#[derive(Component)]
+struct OxrSpectatorMirrorCamera;
+
+/// Node showing a single mirrored view.
+#[derive(Component)]
+struct OxrSpectatorView(u32);
+
+/// Root node of the mirrored views.
+#[derive(Component)]
+struct OxrSpectatorRoot;
+
+fn create_spectator_image(graphics_info: &OxrCurrentSessionConfig) -> Image {
+    Image::new_uninit(
+        wgpu::Extent3d {
+            width: graphics_info.resolution.x * graphics_info.view_count(),
+            height: graphics_info.resolution.y,
+            depth_or_array_layers: 1,
+        },
+        wgpu::TextureDimension::D2,
+        graphics_info.format,
+        RenderAssetUsages::RENDER_WORLD,
+    )
+}
+
+fn spawn_spectator(
+    graphics_info: Res<OxrCurrentSessionConfig>,
+    mode: Res<OxrSpectatorMode>,
+    mut images: ResMut<Assets<Image>>,
+    mut commands: Commands,
+) {
+    let image = images.add(create_spectator_image(&graphics_info));
+    let mirror = commands
+        .spawn((
+            OxrSpectatorMirrorCamera,
+            Camera2d,
+            Camera {
+                is_active: *mode != OxrSpectatorMode::ThirdPerson,
+                ..Default::default()
+            },
+        ))
+        .id();
+    commands
+        .spawn((
+            OxrSpectatorRoot,
+            UiTargetCamera(mirror),
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+        ))
+        .with_children(|parent| {
+            for view in 0..graphics_info.view_count() {
+                parent.spawn((
+                    OxrSpectatorView(view),
+                    ImageNode::new(image.clone()),
+                    Node::default(),
+                ));
+            }
+        });
+    commands.spawn((
+        OxrSpectatorCamera::default(),
+        Camera {
+            is_active: *mode == OxrSpectatorMode::ThirdPerson,
+            ..Default::default()
+        },
+    ));
+    commands.insert_resource(OxrSpectatorImage(image));
+}
+
+fn despawn_spectator(
+    entities: Query<
+        Entity,
+        Or<(
+            With<OxrSpectatorMirrorCamera>,
+            With<OxrSpectatorRoot>,
+            With<OxrSpectatorCamera>,
+        )>,
+    >,
+    mut commands: Commands,
+) {
+    for entity in &entities {
+        commands.entity(entity).despawn();
+    }
+    commands.remove_resource::<OxrSpectatorImage>();
+}
+
+fn clean_spectator_render(mut commands: Commands) {
+    commands.remove_resource::<OxrSpectatorImage>();
+}
+
+/// Switches between the mirror and the spectator camera, and shows the part of the image each mirrored view was copied to.
+fn update_spectator_mirror(
+    mode: Res<OxrSpectatorMode>,
+    graphics_info: Res<OxrCurrentSessionConfig>,
+    resolution_scale: Res<OxrResolutionScale>,
+    spectator_image: Option<ResMut<OxrSpectatorImage>>,
+    mut images: ResMut<Assets<Image>>,
+    mut mirror_cameras: Query<&mut Camera, With<OxrSpectatorMirrorCamera>>,
+    mut spectator_cameras: Query<
+        &mut Camera,
+        (With<OxrSpectatorCamera>, Without<OxrSpectatorMirrorCamera>),
+    >,
+    mut views: Query<(&mut ImageNode, &mut Node, &OxrSpectatorView)>,
+) {
+    let Some(mut spectator_image) = spectator_image else {
+        return;
+    };
+    if graphics_info.is_changed() && !graphics_info.is_added() {
+        // the swapchain was recreated, so the views no longer fit the image
+        spectator_image.0 = images.add(create_spectator_image(&graphics_info));
+    }
+
+    let third_person = *mode == OxrSpectatorMode::ThirdPerson;
+    for mut camera in &mut mirror_cameras {
+        if camera.is_active == third_person {
+            camera.is_active = !third_person;
+        }
+    }
+    for mut camera in &mut spectator_cameras {
+        if camera.is_active != third_person {
+            camera.is_active = third_person;
+        }
+    }
+
+    let mirrored_views = mode.mirrored_views();
+    for (mut image_node, mut node, view) in &mut views {
+        let size = resolution_scale
+            .apply(graphics_info.view_resolution(view.0))
+            .as_vec2();
+        let offset = (view.0 * graphics_info.resolution.x) as f32;
+        let rect = Rect::new(offset, 0.0, offset + size.x, size.y);
+        if image_node.image != spectator_image.0 || image_node.rect != Some(rect) {
+            image_node.image = spectator_image.0.clone();
+            image_node.rect = Some(rect);
+        }
+        let display = if mirrored_views.contains(&view.0) {
+            Display::Flex
+        } else {
+            Display::None
+        };
+        let visible_views = mirrored_views.len().max(1) as f32;
+        let max_width = Val::Percent(100.0 / visible_views);
+        let aspect_ratio = Some(size.x / size.y);
+        if node.display != display
+            || node.max_width != max_width
+            || node.aspect_ratio != aspect_ratio
+        {
+            node.display = display;
+            node.height = Val::Percent(100.0);
+            node.max_width = max_width;
+            node.aspect_ratio = aspect_ratio;
+        }
+    }
+}
+
+/// Moves every [`OxrSpectatorCamera`] that follows the head towards its offset from the head.
+fn follow_head(
+    time: Res<Time>,
+    xr_cameras: Query<(&XrCamera, &GlobalTransform), Without<XrAdditionalCamera>>,
+    mut spectator_cameras: Query<(&mut Transform, &OxrSpectatorCamera)>,
+) {
+    let mut count = 0;
+    let mut position = Vec3::ZERO;
+    let mut rotation = None;
+    for (camera, transform) in &xr_cameras {
+        count += 1;
+        position += transform.translation();
+        if camera.0 == 0 {
+            rotation = Some(transform.rotation());
+        }
+    }
+    let Some(rotation) = rotation else {
+        return;
+    };
+    let head = Transform::from_translation(position / count as f32).with_rotation(rotation);
+    let delta = time.delta_secs();
+
+    for (mut transform, spectator) in &mut spectator_cameras {
+        if !spectator.follow {
+            continue;
+        }
+        let target = head * spectator.offset;
+        let t = if spectator.smoothing > 0.0 {
+            1.0 - (-delta / spectator.smoothing).exp()
+        } else {
+            1.0
+        };
+        transform.translation = transform.translation.lerp(target.translation, t);
+        transform.rotation = transform.rotation.slerp(target.rotation, t);
+    }
+}
+
+/// Copies the mirrored views from the current swapchain image into the [`OxrSpectatorImage`].
+fn copy_spectator_views(
+    mode: Res<OxrSpectatorMode>,
+    spectator_image: Option<Res<OxrSpectatorImage>>,
+    frame_state: Res<OxrFrameState>,
+    graphics_info: Res<OxrCurrentSessionConfig>,
+    resolution_scale: Res<OxrResolutionScale>,
+    swapchain_images: Res<OxrSwapchainImages>,
+    image_index: Res<OxrSwapchainImageIndex>,
+    gpu_images: Res<RenderAssets<GpuImage>>,
+    device: Res<RenderDevice>,
+    queue: Res<RenderQueue>,
+) {
+    let Some(spectator_image) = spectator_image else {
+        return;
+    };
+    let Some(gpu_image) = gpu_images.get(&spectator_image.0) else {
+        return;
+    };
+    if !frame_state.should_render || mode.mirrored_views().is_empty() {
+        return;
+    }
+    if !graphics_info
+        .swapchain_usage
+        .contains(SwapchainUsageFlags::TRANSFER_SRC)
+    {
+        warn_once!("The XR swapchain wasn't created with TRANSFER_SRC, views can't be mirrored");
+        return;
+    }
+    let _span = debug_span!("xr_copy_spectator_views").entered();
+    let source = &swapchain_images[**image_index as usize];
+    let mut encoder =
+        device
+            .wgpu_device()
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("xr_spectator_copy"),
+            });
+    for &view in mode.mirrored_views() {
+        if view >= graphics_info.view_count() {
+            continue;
+        }
+        let size = resolution_scale.apply(graphics_info.view_resolution(view));
+        encoder.copy_texture_to_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: source,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: 0,
+                    y: 0,
+                    z: view,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::TexelCopyTextureInfo {
+                texture: &gpu_image.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: view * graphics_info.resolution.x,
+                    y: 0,
+                    z: 0,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::Extent3d {
+                width: size.x.min(gpu_image.size.width),
+                height: size.y.min(gpu_image.size.height),
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+    queue.submit([encoder.finish()]);
+}
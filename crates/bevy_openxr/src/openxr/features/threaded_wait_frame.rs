@@ -0,0 +1,263 @@
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver},
+    },
+    thread::{self, JoinHandle},
+};
+
+use bevy_app::{App, Plugin};
+use bevy_ecs::{
+    resource::Resource,
+    schedule::IntoScheduleConfigs as _,
+    system::{Commands, Res, ResMut},
+    world::World,
+};
+use bevy_log::{debug_span, error};
+use bevy_mod_xr::session::{XrFirst, XrHandleEvents, XrPreDestroySession, XrRenderSystems};
+use bevy_render::{ExtractSchedule, MainWorld, Render, RenderApp};
+
+use crate::{
+    environment_blend_mode::OxrEnvironmentBlendModes,
+    init::should_run_frame_loop,
+    render::{OxrExternalWaitFrame, OxrWaitFrameSystem, begin_frame},
+    resources::{
+        OxrFrameState, OxrFrameStream, OxrFrameWaiter, OxrSecondaryViewState,
+        OxrSecondaryViewStates,
+    },
+    session::OxrSession,
+};
+
+/// Calls `xrWaitFrame` on a dedicated thread instead of blocking [`XrFirst`].
+///
+/// The thread owns the [`OxrFrameWaiter`] and waits for the next frame while the app updates the current one.
+/// [`XrFirst`] never blocks, it predicts the [`OxrFrameState`] of the frame from the latest state the thread returned.
+/// The render world receives the actual state right before `xrBeginFrame`, which is the only place that has to wait for it.
+/// A wrong prediction never reaches the swapchain: XR cameras are skipped if the actual state doesn't render,
+/// and the projection layer is left out if the actual state renders but no XR camera was active.
+/// The thread is never more than one frame ahead, so this works with and without pipelined rendering.
+///
+/// The default wait of [`OxrRenderPlugin`](crate::render::OxrRenderPlugin) is disabled while this plugin is added.
+pub struct OxrThreadedWaitFramePlugin;
+
+impl Plugin for OxrThreadedWaitFramePlugin {
+    fn build(&self, app: &mut App) {
+        let latest = OxrLatestFrameState::default();
+        app.insert_resource(OxrExternalWaitFrame)
+            .insert_resource(latest.clone())
+            .add_systems(
+                XrFirst,
+                predict_frame_state
+                    .run_if(should_run_frame_loop)
+                    .in_set(OxrWaitFrameSystem)
+                    .in_set(XrHandleEvents::FrameLoop),
+            );
+        app.sub_app_mut(RenderApp)
+            .insert_resource(latest)
+            .add_systems(ExtractSchedule, transfer_frame_waiter)
+            .add_systems(
+                Render,
+                receive_frame_state
+                    .before(begin_frame)
+                    .in_set(XrRenderSystems::PreRender)
+                    .run_if(should_run_frame_loop),
+            )
+            .add_systems(XrPreDestroySession, stop_frame_waiter_thread);
+    }
+}
+
+type WaitState = (openxr::FrameState, Vec<OxrSecondaryViewState>);
+type WaitResult = openxr::Result<WaitState>;
+
+/// The latest frame state the render world received from the thread, shared with the main world.
+#[derive(Resource, Clone, Default)]
+struct OxrLatestFrameState(Arc<Mutex<Option<WaitState>>>);
+
+#[derive(Resource)]
+struct OxrFrameWaiterThread {
+    receiver: Mutex<Receiver<WaitResult>>,
+    stop: Arc<AtomicBool>,
+    /// Returns the frame waiter once the thread stops.
+    handle: JoinHandle<OxrFrameWaiter>,
+}
+
+impl OxrFrameWaiterThread {
+    /// Waits for the thread to stop and returns its frame waiter.
+    ///
+    /// Only call this once the thread stopped sending, otherwise it may be blocked forever.
+    fn join(self) -> Option<OxrFrameWaiter> {
+        drop(self.receiver);
+        self.handle
+            .join()
+            .inspect_err(|_| error!("Frame waiter thread panicked"))
+            .ok()
+    }
+}
+
+fn spawn_frame_waiter_thread(
+    mut frame_waiter: OxrFrameWaiter,
+    session: OxrSession,
+    secondary_view_configurations: Vec<openxr::ViewConfigurationType>,
+) -> std::io::Result<OxrFrameWaiterThread> {
+    // a rendezvous channel keeps the thread from waiting more than one frame ahead
+    let (sender, receiver) = mpsc::sync_channel(0);
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread_stop = stop.clone();
+    let handle = thread::Builder::new()
+        .name("xr_wait_frame".into())
+        .spawn(move || {
+            while !thread_stop.load(Ordering::Acquire) {
+                let result = if secondary_view_configurations.is_empty() {
+                    frame_waiter.wait().map(|state| (state, vec![]))
+                } else {
+                    frame_waiter.wait_secondary(&session, &secondary_view_configurations)
+                };
+                let failed = result.is_err();
+                if sender.send(result).is_err() || failed {
+                    break;
+                }
+            }
+            frame_waiter
+        })?;
+    Ok(OxrFrameWaiterThread {
+        receiver: Mutex::new(receiver),
+        stop,
+        handle,
+    })
+}
+
+/// Uses the latest received frame state, moved one display period ahead, for the main world.
+fn predict_frame_state(
+    latest: Res<OxrLatestFrameState>,
+    frame_state: Option<ResMut<OxrFrameState>>,
+    secondary_states: Option<ResMut<OxrSecondaryViewStates>>,
+    mut commands: Commands,
+) {
+    let Some((latest, latest_secondary)) = latest.0.lock().unwrap().clone() else {
+        // nothing was rendered yet, the first frame is predicted as not rendering
+        if frame_state.is_none() {
+            commands.insert_resource(OxrFrameState(openxr::FrameState {
+                predicted_display_time: openxr::Time::from_nanos(0),
+                predicted_display_period: openxr::Duration::from_nanos(0),
+                should_render: false,
+            }));
+        }
+        return;
+    };
+    let period = latest.predicted_display_period.as_nanos();
+    let mut predicted = latest;
+    predicted.predicted_display_time =
+        openxr::Time::from_nanos(latest.predicted_display_time.as_nanos() + period);
+    match frame_state {
+        Some(mut frame_state) => {
+            // keep moving forward if the render world hasn't received a new state since the last frame
+            let previous = frame_state.predicted_display_time.as_nanos() + period;
+            if previous > predicted.predicted_display_time.as_nanos() {
+                predicted.predicted_display_time = openxr::Time::from_nanos(previous);
+            }
+            frame_state.0 = predicted;
+        }
+        None => commands.insert_resource(OxrFrameState(predicted)),
+    }
+    if let Some(mut secondary_states) = secondary_states
+        && !latest_secondary.is_empty()
+    {
+        secondary_states.0 = latest_secondary;
+    }
+}
+
+/// Moves the frame waiter of a new session to the render world, which spawns the thread.
+fn transfer_frame_waiter(mut main_world: ResMut<MainWorld>, mut commands: Commands) {
+    if let Some(frame_waiter) = main_world.remove_resource::<OxrFrameWaiter>() {
+        commands.insert_resource(frame_waiter);
+    }
+}
+
+fn receive_frame_state(world: &mut World) {
+    if !world.contains_resource::<OxrFrameWaiterThread>() {
+        let Some(frame_waiter) = world.remove_resource::<OxrFrameWaiter>() else {
+            return;
+        };
+        let session = world.resource::<OxrSession>().clone();
+        let secondary_view_configurations = world
+            .get_resource::<OxrSecondaryViewStates>()
+            .map(|states| states.view_configurations())
+            .unwrap_or_default();
+        match spawn_frame_waiter_thread(frame_waiter, session, secondary_view_configurations) {
+            Ok(thread) => world.insert_resource(thread),
+            Err(err) => {
+                error!("Failed to spawn frame waiter thread: {err}");
+                return;
+            }
+        }
+    }
+
+    let _span = debug_span!("xr_wait_frame").entered();
+    let result = world
+        .resource::<OxrFrameWaiterThread>()
+        .receiver
+        .lock()
+        .unwrap()
+        .recv();
+    match result {
+        Ok(Ok((state, secondary_states))) => {
+            *world.resource::<OxrLatestFrameState>().0.lock().unwrap() =
+                Some((state, secondary_states.clone()));
+            world.insert_resource(OxrFrameState(state));
+            if !secondary_states.is_empty()
+                && let Some(mut states) = world.get_resource_mut::<OxrSecondaryViewStates>()
+            {
+                states.0 = secondary_states;
+            }
+        }
+        Ok(Err(err)) => {
+            error!("Failed to wait frame: {err}");
+            // the thread stops after a failed wait, a new one is spawned with the same waiter next frame
+            restore_frame_waiter(world);
+        }
+        Err(_) => {
+            error!("Frame waiter thread stopped");
+            restore_frame_waiter(world);
+        }
+    }
+}
+
+fn restore_frame_waiter(world: &mut World) {
+    let Some(thread) = world.remove_resource::<OxrFrameWaiterThread>() else {
+        return;
+    };
+    if let Some(frame_waiter) = thread.join() {
+        world.insert_resource(frame_waiter);
+    }
+}
+
+fn stop_frame_waiter_thread(world: &mut World) {
+    *world.resource::<OxrLatestFrameState>().0.lock().unwrap() = None;
+    world.remove_resource::<OxrFrameWaiter>();
+    let Some(thread) = world.remove_resource::<OxrFrameWaiterThread>() else {
+        return;
+    };
+    thread.stop.store(true, Ordering::Release);
+    // The thread may be blocked in xrWaitFrame, which only returns once the previous frame began.
+    // Every frame it still hands over is begun and ended without layers, until it sees the stop flag.
+    let blend_mode = world
+        .get_resource::<OxrEnvironmentBlendModes>()
+        .map(OxrEnvironmentBlendModes::blend_mode);
+    loop {
+        let result = thread.receiver.lock().unwrap().recv();
+        let Ok(result) = result else {
+            break;
+        };
+        let (Ok((state, _)), Some(blend_mode)) = (result, blend_mode) else {
+            continue;
+        };
+        if let Some(mut frame_stream) = world.get_resource_mut::<OxrFrameStream>()
+            && frame_stream.begin().is_ok()
+            && let Err(err) = frame_stream.end(state.predicted_display_time, blend_mode, &[])
+        {
+            error!("Failed to skip frame while stopping the frame waiter thread: {err}");
+        }
+    }
+    thread.join();
+}
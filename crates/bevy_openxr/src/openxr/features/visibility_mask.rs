@@ -0,0 +1,447 @@
+use std::{borrow::Cow, ptr};
+
+use bevy_app::{App, Plugin, PreUpdate};
+use bevy_core_pipeline::core_3d::graph::{Core3d, Node3d};
+use bevy_ecs::{
+    change_detection::DetectChanges as _,
+    entity::{Entity, EntityHashMap},
+    query::{QueryItem, With},
+    resource::Resource,
+    schedule::IntoScheduleConfigs as _,
+    system::{Query, Res, ResMut},
+    world::World,
+};
+use bevy_log::{debug, error};
+use bevy_math::Vec2;
+use bevy_mod_xr::{camera::XrCamera, session::XrSessionCreated};
+use bevy_platform::collections::HashMap;
+use bevy_render::{
+    Render, RenderApp, RenderSystems,
+    extract_resource::{ExtractResource, ExtractResourcePlugin},
+    render_graph::{
+        NodeRunError, RenderGraphContext, RenderGraphExt as _, RenderLabel, ViewNode,
+        ViewNodeRunner,
+    },
+    renderer::{RenderContext, RenderDevice, RenderQueue},
+    view::{ExtractedView, ViewDepthTexture},
+};
+use openxr::{Event, sys};
+use wgpu::util::DeviceExt as _;
+
+use crate::{
+    exts::OxrEnabledExtensions,
+    init::should_run_frame_loop,
+    poll_events::{OxrEventHandlerExt, OxrEventIn},
+    resources::OxrCurrentSessionConfig,
+    session::OxrSession,
+};
+
+/// Covers the parts of each view that are hidden by the lenses using `XR_KHR_visibility_mask`.
+///
+/// Before the prepasses and the main opaque pass of every [`XrCamera`], a depth only pass writes the hidden area
+/// of its view into the depth buffer at the near plane, so the GPU skips shading anything behind it.
+/// The masks are fetched again when the runtime reports that a mask changed.
+pub struct OxrVisibilityMaskPlugin;
+
+impl Plugin for OxrVisibilityMaskPlugin {
+    fn build(&self, app: &mut App) {
+        if !app
+            .world()
+            .get_resource::<OxrEnabledExtensions>()
+            .is_some_and(|e| e.khr_visibility_mask)
+        {
+            error!("XR_KHR_visibility_mask is not enabled, visibility masks will not be used");
+            return;
+        }
+        app.init_resource::<OxrVisibilityMaskState>()
+            .init_resource::<OxrVisibilityMasks>()
+            .add_plugins(ExtractResourcePlugin::<OxrVisibilityMasks>::default())
+            .add_oxr_event_handler(handle_visibility_mask_event)
+            .add_systems(XrSessionCreated, mark_visibility_masks_outdated)
+            .add_systems(
+                PreUpdate,
+                update_visibility_masks.run_if(should_run_frame_loop),
+            );
+
+        app.sub_app_mut(RenderApp)
+            .init_resource::<OxrVisibilityMaskBuffers>()
+            .add_systems(
+                Render,
+                prepare_visibility_masks.in_set(RenderSystems::PrepareBindGroups),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        if !app.world().contains_resource::<OxrVisibilityMasks>() {
+            return;
+        }
+        // the core 3d graph only exists once the core pipeline plugin is built
+        app.sub_app_mut(RenderApp)
+            .add_render_graph_node::<ViewNodeRunner<OxrVisibilityMaskNode>>(
+                Core3d,
+                OxrVisibilityMaskPass,
+            )
+            .add_render_graph_edges(Core3d, (OxrVisibilityMaskPass, Node3d::EarlyPrepass));
+    }
+}
+
+/// Hidden area of a single view, in tangent space on the z = -1 plane.
+#[derive(Clone, Debug, Default)]
+pub struct OxrVisibilityMask {
+    pub vertices: Vec<Vec2>,
+    pub indices: Vec<u32>,
+}
+
+/// The hidden area of every view, indexed by view.
+#[derive(Resource, ExtractResource, Clone, Debug, Default)]
+pub struct OxrVisibilityMasks(pub Vec<OxrVisibilityMask>);
+
+#[derive(Resource, Default)]
+struct OxrVisibilityMaskState {
+    outdated: bool,
+}
+
+fn handle_visibility_mask_event(event: OxrEventIn, mut state: ResMut<OxrVisibilityMaskState>) {
+    if let Event::VisibilityMaskChangedKHR(event) = *event {
+        debug!("visibility mask of view {} changed", event.view_index());
+        state.outdated = true;
+    }
+}
+
+fn mark_visibility_masks_outdated(mut state: ResMut<OxrVisibilityMaskState>) {
+    state.outdated = true;
+}
+
+fn update_visibility_masks(
+    session: Res<OxrSession>,
+    graphics_info: Res<OxrCurrentSessionConfig>,
+    mut state: ResMut<OxrVisibilityMaskState>,
+    mut masks: ResMut<OxrVisibilityMasks>,
+) {
+    if !state.outdated {
+        return;
+    }
+    state.outdated = false;
+    masks.0 = (0..graphics_info.view_count())
+        .map(|view| {
+            match get_visibility_mask(
+                &session,
+                graphics_info.view_configuration,
+                view,
+                openxr::VisibilityMaskTypeKHR::HIDDEN_TRIANGLE_MESH,
+            ) {
+                Ok((vertices, indices)) => OxrVisibilityMask {
+                    vertices: vertices.iter().map(|v| Vec2::new(v.x, v.y)).collect(),
+                    indices,
+                },
+                Err(err) => {
+                    error!("Failed to get visibility mask of view {view}: {err}");
+                    OxrVisibilityMask::default()
+                }
+            }
+        })
+        .collect();
+}
+
+/// GPU buffers of a single view's mask.
+struct OxrVisibilityMaskMesh {
+    vertices: wgpu::Buffer,
+    indices: wgpu::Buffer,
+    index_count: u32,
+}
+
+/// Buffer holding the `clip_from_view` matrix of a camera, and the bind group using it.
+struct OxrVisibilityMaskView {
+    uniform: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+/// Everything the [`OxrVisibilityMaskNode`] needs, kept across frames and only rebuilt when the masks change.
+#[derive(Resource, Default)]
+struct OxrVisibilityMaskBuffers {
+    layout: Option<wgpu::BindGroupLayout>,
+    shader: Option<wgpu::ShaderModule>,
+    /// Pipelines by depth format and sample count.
+    pipelines: HashMap<(wgpu::TextureFormat, u32), wgpu::RenderPipeline>,
+    meshes: Vec<Option<OxrVisibilityMaskMesh>>,
+    views: EntityHashMap<OxrVisibilityMaskView>,
+}
+
+fn prepare_visibility_masks(
+    device: Res<RenderDevice>,
+    queue: Res<RenderQueue>,
+    masks: Option<Res<OxrVisibilityMasks>>,
+    mut buffers: ResMut<OxrVisibilityMaskBuffers>,
+    views: Query<(Entity, &ExtractedView, &ViewDepthTexture), With<XrCamera>>,
+) {
+    let Some(masks) = masks else {
+        return;
+    };
+    let device = device.wgpu_device();
+    let buffers = &mut *buffers;
+    if masks.is_changed() {
+        buffers.meshes = masks
+            .0
+            .iter()
+            .map(|mask| {
+                if mask.indices.is_empty() {
+                    return None;
+                }
+                Some(OxrVisibilityMaskMesh {
+                    vertices: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                        label: Some("xr_visibility_mask_vertices"),
+                        contents: &mask
+                            .vertices
+                            .iter()
+                            .flat_map(|v| v.to_array())
+                            .flat_map(f32::to_ne_bytes)
+                            .collect::<Vec<_>>(),
+                        usage: wgpu::BufferUsages::VERTEX,
+                    }),
+                    indices: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                        label: Some("xr_visibility_mask_indices"),
+                        contents: &mask
+                            .indices
+                            .iter()
+                            .copied()
+                            .flat_map(u32::to_ne_bytes)
+                            .collect::<Vec<_>>(),
+                        usage: wgpu::BufferUsages::INDEX,
+                    }),
+                    index_count: mask.indices.len() as u32,
+                })
+            })
+            .collect();
+    }
+
+    let layout = buffers
+        .layout
+        .get_or_insert_with(|| {
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("xr_visibility_mask"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+            })
+        })
+        .clone();
+    let shader = buffers
+        .shader
+        .get_or_insert_with(|| {
+            device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("xr_visibility_mask"),
+                source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(VISIBILITY_MASK_SHADER)),
+            })
+        })
+        .clone();
+
+    buffers.views.retain(|entity, _| views.contains(*entity));
+    for (entity, view, depth) in &views {
+        let key = (depth.texture.format(), depth.texture.sample_count());
+        buffers
+            .pipelines
+            .entry(key)
+            .or_insert_with(|| create_pipeline(device, &layout, &shader, key.0, key.1));
+        let clip_from_view = view
+            .clip_from_view
+            .to_cols_array()
+            .into_iter()
+            .flat_map(f32::to_ne_bytes)
+            .collect::<Vec<_>>();
+        let view = buffers.views.entry(entity).or_insert_with(|| {
+            let uniform = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("xr_visibility_mask_clip_from_view"),
+                size: clip_from_view.len() as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("xr_visibility_mask"),
+                layout: &layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform.as_entire_binding(),
+                }],
+            });
+            OxrVisibilityMaskView {
+                uniform,
+                bind_group,
+            }
+        });
+        queue.write_buffer(&view.uniform, 0, &clip_from_view);
+    }
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    shader: &wgpu::ShaderModule,
+    depth_format: wgpu::TextureFormat,
+    sample_count: u32,
+) -> wgpu::RenderPipeline {
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("xr_visibility_mask"),
+        bind_group_layouts: &[Some(layout)],
+        immediate_size: 0,
+    });
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("xr_visibility_mask"),
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: Some("vertex"),
+            compilation_options: Default::default(),
+            buffers: &[wgpu::VertexBufferLayout {
+                array_stride: size_of::<Vec2>() as u64,
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes: &wgpu::vertex_attr_array![0 => Float32x2],
+            }],
+        },
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: depth_format,
+            depth_write_enabled: Some(true),
+            depth_compare: Some(wgpu::CompareFunction::Always),
+            stencil: Default::default(),
+            bias: Default::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            ..Default::default()
+        },
+        fragment: None,
+        multiview_mask: None,
+        cache: None,
+    })
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+pub struct OxrVisibilityMaskPass;
+
+/// Writes the visibility mask of the view into its depth texture, clearing it first.
+///
+/// Passes that run later load the depth texture instead of clearing it, since [`ViewDepthTexture`] only clears on first use.
+#[derive(Default)]
+struct OxrVisibilityMaskNode;
+
+impl ViewNode for OxrVisibilityMaskNode {
+    type ViewQuery = (
+        Entity,
+        &'static XrCamera,
+        &'static ExtractedView,
+        &'static ViewDepthTexture,
+    );
+
+    fn run<'w>(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext<'w>,
+        (entity, camera, view, depth): QueryItem<'w, '_, Self::ViewQuery>,
+        world: &'w World,
+    ) -> Result<(), NodeRunError> {
+        let Some(buffers) = world.get_resource::<OxrVisibilityMaskBuffers>() else {
+            return Ok(());
+        };
+        let (Some(Some(mesh)), Some(mask_view), Some(pipeline)) = (
+            buffers.meshes.get(camera.0 as usize),
+            buffers.views.get(&entity),
+            buffers
+                .pipelines
+                .get(&(depth.texture.format(), depth.texture.sample_count())),
+        ) else {
+            return Ok(());
+        };
+
+        let mut pass =
+            render_context
+                .command_encoder()
+                .begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("xr_visibility_mask"),
+                    color_attachments: &[],
+                    depth_stencil_attachment: Some(depth.get_attachment(wgpu::StoreOp::Store)),
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                    multiview_mask: None,
+                });
+        let viewport = view.viewport.as_vec4();
+        pass.set_viewport(viewport.x, viewport.y, viewport.z, viewport.w, 0.0, 1.0);
+        pass.set_pipeline(pipeline);
+        pass.set_bind_group(0, &mask_view.bind_group, &[]);
+        pass.set_vertex_buffer(0, mesh.vertices.slice(..));
+        pass.set_index_buffer(mesh.indices.slice(..), wgpu::IndexFormat::Uint32);
+        pass.draw_indexed(0..mesh.index_count, 0, 0..1);
+        Ok(())
+    }
+}
+
+/// Projects the mask from the z = -1 plane and moves it onto the near plane,
+/// which is at depth 1 with bevy's reversed depth.
+const VISIBILITY_MASK_SHADER: &str = r"
+@group(0) @binding(0) var<uniform> clip_from_view: mat4x4<f32>;
+
+@vertex
+fn vertex(@location(0) position: vec2<f32>) -> @builtin(position) vec4<f32> {
+    let clip = clip_from_view * vec4<f32>(position, -1.0, 1.0);
+    return vec4<f32>(clip.xy, clip.w, clip.w);
+}
+";
+
+/// Calls [`xrGetVisibilityMaskKHR`](https://www.khronos.org/registry/OpenXR/specs/1.0/html/xrspec.html#xrGetVisibilityMaskKHR) and returns the vertices and indices of the mask.
+fn get_visibility_mask(
+    session: &OxrSession,
+    view_configuration: openxr::ViewConfigurationType,
+    view_index: u32,
+    ty: openxr::VisibilityMaskTypeKHR,
+) -> openxr::Result<(Vec<openxr::Vector2f>, Vec<u32>)> {
+    let Some(visibility_mask) = session.instance().exts().khr_visibility_mask.as_ref() else {
+        return Err(sys::Result::ERROR_EXTENSION_NOT_PRESENT);
+    };
+    let mut mask = sys::VisibilityMaskKHR {
+        ty: sys::VisibilityMaskKHR::TYPE,
+        next: ptr::null_mut(),
+        vertex_capacity_input: 0,
+        vertex_count_output: 0,
+        vertices: ptr::null_mut(),
+        index_capacity_input: 0,
+        index_count_output: 0,
+        indices: ptr::null_mut(),
+    };
+    unsafe {
+        cvt((visibility_mask.get_visibility_mask)(
+            session.as_raw(),
+            view_configuration,
+            view_index,
+            ty,
+            &mut mask,
+        ))?;
+    }
+    let mut vertices = Vec::with_capacity(mask.vertex_count_output as usize);
+    let mut indices = Vec::with_capacity(mask.index_count_output as usize);
+    mask.vertex_capacity_input = vertices.capacity() as u32;
+    mask.vertices = vertices.as_mut_ptr();
+    mask.index_capacity_input = indices.capacity() as u32;
+    mask.indices = indices.as_mut_ptr();
+    unsafe {
+        cvt((visibility_mask.get_visibility_mask)(
+            session.as_raw(),
+            view_configuration,
+            view_index,
+            ty,
+            &mut mask,
+        ))?;
+        vertices.set_len(mask.vertex_count_output as usize);
+        indices.set_len(mask.index_count_output as usize);
+    }
+    Ok((vertices, indices))
+}
+
+fn cvt(x: sys::Result) -> openxr::Result<sys::Result> {
+    if x.into_raw() >= 0 { Ok(x) } else { Err(x) }
+}
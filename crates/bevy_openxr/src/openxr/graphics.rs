@@ -6,12 +6,11 @@ pub mod vulkan;
 use std::{any::TypeId, ffi::CStr};
 
 use bevy_ecs::resource::Resource;
-use bevy_math::UVec2;
 use openxr::{FrameStream, FrameWaiter, Session};
 
 use crate::{
     session::OxrSessionCreateNextChain,
-    types::{AppInfo, OxrExtensions, Result, WgpuGraphics},
+    types::{AppInfo, OxrExtensions, Result, SwapchainCreateInfo, WgpuGraphics},
 };
 
 /// This is an extension trait to the [`Graphics`](openxr::Graphics) trait and is how the graphics API should be interacted with.
@@ -32,8 +31,7 @@ pub unsafe trait GraphicsExt: openxr::Graphics {
     unsafe fn to_wgpu_img(
         image: Self::SwapchainImage,
         device: &wgpu::Device,
-        format: wgpu::TextureFormat,
-        resolution: UVec2,
+        info: SwapchainCreateInfo,
     ) -> Result<wgpu::Texture>;
     /// Initialize graphics for this backend and return a [`WgpuGraphics`] for bevy and an API specific [Self::SessionCreateInfo] for openxr
     fn init_graphics(
@@ -3,17 +3,16 @@ use std::str::FromStr;
 
 use ash::vk::{self, Handle};
 use bevy_log::{debug, error};
-use bevy_math::UVec2;
 use openxr::sys::Handle as _;
 use openxr::{Version, sys};
-use wgpu::{ExperimentalFeatures, InstanceFlags, Limits, MemoryBudgetThresholds, TextureUses};
+use wgpu::{ExperimentalFeatures, InstanceFlags, Limits, MemoryBudgetThresholds};
 use wgpu_hal::Api;
 use wgpu_hal::api::Vulkan;
 
 use super::{GraphicsExt, GraphicsType, GraphicsWrap, OxrManualGraphicsConfig};
 use crate::error::OxrError;
 use crate::session::OxrSessionCreateNextChain;
-use crate::types::{AppInfo, OxrExtensions, Result, WgpuGraphics};
+use crate::types::{AppInfo, OxrExtensions, Result, SwapchainCreateInfo, WgpuGraphics};
 
 #[cfg(not(target_os = "android"))]
 const VK_TARGET_VERSION: Version = Version::new(1, 2, 0);
@@ -49,10 +48,10 @@ unsafe impl GraphicsExt for openxr::Vulkan {
     unsafe fn to_wgpu_img(
         color_image: Self::SwapchainImage,
         device: &wgpu::Device,
-        format: wgpu::TextureFormat,
-        resolution: UVec2,
+        info: SwapchainCreateInfo,
     ) -> Result<wgpu::Texture> {
         let color_image = vk::Image::from_raw(color_image);
+        let (hal_usage, usage) = info.texture_usages();
         let wgpu_hal_texture = unsafe {
             let hal_dev = device.as_hal::<wgpu_hal::vulkan::Api>().ok_or(
                 OxrError::GraphicsBackendMismatch {
@@ -66,15 +65,15 @@ unsafe impl GraphicsExt for openxr::Vulkan {
                 &wgpu_hal::TextureDescriptor {
                     label: Some("VR Swapchain"),
                     size: wgpu::Extent3d {
-                        width: resolution.x,
-                        height: resolution.y,
-                        depth_or_array_layers: 2,
+                        width: info.width,
+                        height: info.height,
+                        depth_or_array_layers: info.array_size,
                     },
-                    mip_level_count: 1,
-                    sample_count: 1,
+                    mip_level_count: info.mip_count,
+                    sample_count: info.sample_count,
                     dimension: wgpu::TextureDimension::D2,
-                    format,
-                    usage: TextureUses::COLOR_TARGET | TextureUses::COPY_DST,
+                    format: info.format,
+                    usage: hal_usage,
                     memory_flags: wgpu_hal::MemoryFlags::empty(),
                     view_formats: vec![],
                 },
@@ -88,15 +87,15 @@ unsafe impl GraphicsExt for openxr::Vulkan {
                 &wgpu::TextureDescriptor {
                     label: Some("VR Swapchain"),
                     size: wgpu::Extent3d {
-                        width: resolution.x,
-                        height: resolution.y,
-                        depth_or_array_layers: 2,
+                        width: info.width,
+                        height: info.height,
+                        depth_or_array_layers: info.array_size,
                     },
-                    mip_level_count: 1,
-                    sample_count: 1,
+                    mip_level_count: info.mip_count,
+                    sample_count: info.sample_count,
                     dimension: wgpu::TextureDimension::D2,
-                    format,
-                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_DST,
+                    format: info.format,
+                    usage,
                     view_formats: &[],
                 },
             )
@@ -852,3 +851,55 @@ fn wgpu_to_vulkan(format: wgpu::TextureFormat) -> Option<vk::Format> {
         },
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use ash::vk::Format as F;
+
+    use super::{vulkan_to_wgpu, wgpu_to_vulkan};
+    use crate::resources::OxrSessionConfig;
+
+    /// Color formats runtimes commonly offer for Vulkan swapchains.
+    const SWAPCHAIN_FORMATS: [F; 8] = [
+        F::R8G8B8A8_SRGB,
+        F::B8G8R8A8_SRGB,
+        F::R8G8B8A8_UNORM,
+        F::B8G8R8A8_UNORM,
+        F::A2B10G10R10_UNORM_PACK32,
+        F::B10G11R11_UFLOAT_PACK32,
+        F::R16G16B16A16_SFLOAT,
+        F::R32G32B32A32_SFLOAT,
+    ];
+
+    #[test]
+    fn test_swapchain_formats_round_trip() {
+        for format in SWAPCHAIN_FORMATS {
+            let wgpu_format = vulkan_to_wgpu(format).unwrap();
+            assert_eq!(wgpu_to_vulkan(wgpu_format), Some(format));
+        }
+        let config = OxrSessionConfig::default().with_hdr();
+        for format in config.formats.unwrap() {
+            assert_eq!(vulkan_to_wgpu(wgpu_to_vulkan(format).unwrap()), Some(format));
+        }
+    }
+
+    #[test]
+    fn test_swapchain_format_encoding() {
+        // bevy's blit relies on sRGB formats encoding on write, every other format receives linear color
+        for format in SWAPCHAIN_FORMATS {
+            let wgpu_format = vulkan_to_wgpu(format).unwrap();
+            assert_eq!(
+                wgpu_format.is_srgb(),
+                format!("{format:?}").ends_with("_SRGB"),
+                "{format:?}"
+            );
+            if wgpu_format.is_srgb() {
+                let linear = wgpu_to_vulkan(wgpu_format.remove_srgb_suffix()).unwrap();
+                assert_eq!(
+                    format!("{linear:?}"),
+                    format!("{format:?}").replace("_SRGB", "_UNORM")
+                );
+            }
+        }
+    }
+}
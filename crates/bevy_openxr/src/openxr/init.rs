@@ -78,6 +78,8 @@ pub struct OxrInitPlugin {
     pub exts: OxrExtensions,
     /// List of backends the openxr session can use. If [None], pick the first available backend.
     pub backends: Option<Vec<GraphicsBackend>>,
+    /// The form factor of the XR system, e.g. [`HANDHELD_DISPLAY`](openxr::FormFactor::HANDHELD_DISPLAY) for handheld AR.
+    pub form_factor: openxr::FormFactor,
     /// Passed into the render plugin when added to the app.
     pub synchronous_pipeline_compilation: bool,
     pub render_debug_flags: RenderDebugFlags,
@@ -92,6 +94,7 @@ impl Default for OxrInitPlugin {
                 exts
             },
             backends: Default::default(),
+            form_factor: openxr::FormFactor::HEAD_MOUNTED_DISPLAY,
             synchronous_pipeline_compilation: false,
             render_debug_flags: Default::default(),
         }
@@ -304,7 +307,7 @@ impl OxrInitPlugin {
             instance_props.runtime_name, instance_props.runtime_version
         );
 
-        let system_id = instance.system(openxr::FormFactor::HEAD_MOUNTED_DISPLAY)?;
+        let system_id = instance.system(self.form_factor)?;
         let system_props = instance.system_properties(system_id)?;
 
         info!(
@@ -373,10 +376,16 @@ fn init_xr_session(
     chain: &mut OxrSessionCreateNextChain,
     OxrSessionConfig {
         blend_mode_preference,
+        view_configuration_preference,
         formats,
         resolutions,
+        render_scale,
+        sample_count,
+        hdr,
+        swapchain_usage,
     }: OxrSessionConfig,
     graphics_info: SessionGraphicsCreateInfo,
+    input_attachment_supported: bool,
 ) -> OxrResult<(
     OxrSession,
     OxrFrameWaiter,
@@ -389,84 +398,50 @@ fn init_xr_session(
     let (session, frame_waiter, frame_stream) =
         unsafe { instance.create_session(system_id, graphics_info, chain)? };
 
-    // TODO!() support other view configurations
     let available_view_configurations = instance.enumerate_view_configurations(system_id)?;
-    if !available_view_configurations.contains(&openxr::ViewConfigurationType::PRIMARY_STEREO) {
-        return Err(OxrError::NoAvailableViewConfiguration);
-    }
 
-    let view_configuration_type = openxr::ViewConfigurationType::PRIMARY_STEREO;
+    // view configuration selection
+    let view_configuration_type = view_configuration_preference
+        .iter()
+        .find(|ty| available_view_configurations.contains(ty))
+        .copied()
+        .ok_or(OxrError::NoAvailableViewConfiguration)?;
 
     let view_configuration_views =
         instance.enumerate_view_configuration_views(system_id, view_configuration_type)?;
 
-    let (resolution, _view) = if let Some(resolutions) = &resolutions {
-        let mut preferred = None;
-        for resolution in resolutions {
-            for view_config in view_configuration_views.iter() {
-                if view_config.recommended_image_rect_height == resolution.y
-                    && view_config.recommended_image_rect_width == resolution.x
-                {
-                    preferred = Some((*resolution, *view_config));
-                }
-            }
-        }
-
-        if preferred.is_none() {
-            for resolution in resolutions {
-                for view_config in view_configuration_views.iter() {
-                    if view_config.max_image_rect_height >= resolution.y
-                        && view_config.max_image_rect_width >= resolution.x
-                    {
-                        preferred = Some((*resolution, *view_config));
-                    }
-                }
-            }
-        }
-
-        preferred
-    } else {
-        view_configuration_views.first().map(|config| {
-            (
-                UVec2::new(
-                    config.recommended_image_rect_width,
-                    config.recommended_image_rect_height,
-                ),
-                *config,
-            )
-        })
-    }
-    .ok_or(OxrError::NoAvailableViewConfiguration)?;
+    let (resolution, view_resolutions, resolution_reason) =
+        select_resolution(resolutions.as_deref(), render_scale, &view_configuration_views)
+            .ok_or(OxrError::NoAvailableViewConfiguration)?;
+    info!("Selected XR swapchain resolution {resolution}: {resolution_reason:?}");
 
     let available_formats = session.enumerate_swapchain_formats()?;
 
-    let format = if let Some(formats) = &formats {
-        let mut format = None;
-        for wanted_format in formats {
-            if available_formats.contains(wanted_format) {
-                format = Some(*wanted_format);
-            }
-        }
-        format
-    } else {
-        available_formats.first().copied()
-    }
-    .ok_or(OxrError::NoAvailableFormat)?;
+    let format = select_format(formats.as_deref(), &available_formats)
+        .ok_or(OxrError::NoAvailableFormat)?;
+    info!("Selected XR swapchain format {format:?}");
 
-    let swapchain = session.create_swapchain(SwapchainCreateInfo {
-        create_flags: SwapchainCreateFlags::EMPTY,
-        usage_flags: SwapchainUsageFlags::COLOR_ATTACHMENT | SwapchainUsageFlags::SAMPLED,
-        format,
-        // TODO() add support for multisampling
-        sample_count: 1,
-        width: resolution.x,
-        height: resolution.y,
-        face_count: 1,
-        array_size: 2,
-        mip_count: 1,
-    })?;
+    let max_sample_count = view_configuration_views
+        .iter()
+        .map(|view| view.max_swapchain_sample_count)
+        .min()
+        .unwrap_or(1);
+    let sample_count = select_sample_count(sample_count, max_sample_count);
 
-    let images = swapchain.enumerate_images(device, format, resolution)?;
+    let swapchain_usage = select_swapchain_usage(
+        swapchain_usage,
+        format,
+        device.features(),
+        input_attachment_supported,
+    );
+    let (swapchain, images, swapchain_usage) = create_swapchain(
+        &session,
+        device,
+        resolution,
+        format,
+        view_configuration_views.len() as u32,
+        swapchain_usage,
+    )?;
 
     let available_blend_modes =
         instance.enumerate_environment_blend_modes(system_id, view_configuration_type)?;
@@ -475,7 +450,16 @@ fn init_xr_session(
     let blend_modes = OxrEnvironmentBlendModes::new(available_blend_modes, &blend_mode_preference)
         .ok_or(OxrError::NoAvailableBlendMode)?;
 
-    let graphics_info = OxrCurrentSessionConfig { resolution, format };
+    let graphics_info = OxrCurrentSessionConfig {
+        view_configuration: view_configuration_type,
+        resolution,
+        view_resolutions,
+        resolution_reason,
+        format,
+        sample_count,
+        hdr,
+        swapchain_usage,
+    };
 
     Ok((
         session,
@@ -488,6 +472,180 @@ fn init_xr_session(
     ))
 }
 
+/// Picks the swapchain resolution and the resolution of every view.
+///
+/// Resolutions from `resolutions` are preferred if they match the recommended resolution of a view or otherwise fit within its maximum.
+/// Without any wanted resolutions, the recommended resolution of every view is scaled by `render_scale` and clamped to its maximum.
+/// The swapchain resolution is large enough to fit every view.
+fn select_resolution(
+    resolutions: Option<&[UVec2]>,
+    render_scale: f32,
+    views: &[openxr::ViewConfigurationView],
+) -> Option<(UVec2, Vec<UVec2>, OxrResolutionReason)> {
+    let recommended = |view: &openxr::ViewConfigurationView| {
+        UVec2::new(
+            view.recommended_image_rect_width,
+            view.recommended_image_rect_height,
+        )
+    };
+    let max = |view: &openxr::ViewConfigurationView| {
+        UVec2::new(view.max_image_rect_width, view.max_image_rect_height)
+    };
+
+    if let Some(resolutions) = resolutions {
+        let (resolution, reason) = resolutions
+            .iter()
+            .find(|resolution| views.iter().any(|view| recommended(view) == **resolution))
+            .map(|resolution| (*resolution, OxrResolutionReason::MatchedRecommended))
+            .or_else(|| {
+                resolutions
+                    .iter()
+                    .find(|resolution| views.iter().all(|view| max(view).cmpge(**resolution).all()))
+                    .map(|resolution| (*resolution, OxrResolutionReason::FitsMax))
+            })?;
+        return Some((resolution, vec![resolution; views.len()], reason));
+    }
+
+    let mut reason = OxrResolutionReason::RenderScale(render_scale);
+    let view_resolutions = views
+        .iter()
+        .map(|view| {
+            let scaled = (recommended(view).as_vec2() * render_scale)
+                .round()
+                .as_uvec2()
+                .max(UVec2::ONE);
+            if scaled.cmpgt(max(view)).any() {
+                reason = OxrResolutionReason::ClampedToMax;
+            }
+            scaled.min(max(view))
+        })
+        .collect::<Vec<_>>();
+    let resolution = view_resolutions.iter().copied().reduce(UVec2::max)?;
+    Some((resolution, view_resolutions, reason))
+}
+
+/// Picks the first format in `formats` that the runtime supports, or the runtime's preferred format without any wanted formats.
+fn select_format(
+    formats: Option<&[wgpu::TextureFormat]>,
+    available: &[wgpu::TextureFormat],
+) -> Option<wgpu::TextureFormat> {
+    match formats {
+        Some(formats) => formats
+            .iter()
+            .find(|format| available.contains(format))
+            .copied(),
+        None => available.first().copied(),
+    }
+}
+
+/// Removes the usage flags that can't be used with `format`, see [`OxrSessionConfig::swapchain_usage`].
+fn select_swapchain_usage(
+    wanted: SwapchainUsageFlags,
+    format: wgpu::TextureFormat,
+    features: wgpu::Features,
+    input_attachment_supported: bool,
+) -> SwapchainUsageFlags {
+    let remove = |usage: SwapchainUsageFlags, flag: SwapchainUsageFlags| {
+        SwapchainUsageFlags::from_raw(usage.into_raw() & !flag.into_raw())
+    };
+    let mut usage = wanted | SwapchainUsageFlags::COLOR_ATTACHMENT;
+    let allowed_usages = format.guaranteed_format_features(features).allowed_usages;
+    if usage.contains(SwapchainUsageFlags::UNORDERED_ACCESS)
+        && !allowed_usages.contains(wgpu::TextureUsages::STORAGE_BINDING)
+    {
+        warn!(
+            "Swapchain format {format:?} can't be used as a storage texture, removing UNORDERED_ACCESS"
+        );
+        usage = remove(usage, SwapchainUsageFlags::UNORDERED_ACCESS);
+    }
+    if usage.contains(SwapchainUsageFlags::INPUT_ATTACHMENT) && !input_attachment_supported {
+        warn!(
+            "XR_KHR_swapchain_usage_input_attachment_bit is not enabled, removing INPUT_ATTACHMENT"
+        );
+        usage = remove(usage, SwapchainUsageFlags::INPUT_ATTACHMENT);
+    }
+    usage
+}
+
+/// Usage flags used if the runtime doesn't support the wanted ones.
+const FALLBACK_SWAPCHAIN_USAGE: SwapchainUsageFlags = SwapchainUsageFlags::from_raw(
+    SwapchainUsageFlags::COLOR_ATTACHMENT.into_raw()
+        | SwapchainUsageFlags::SAMPLED.into_raw()
+        | SwapchainUsageFlags::TRANSFER_DST.into_raw(),
+);
+
+/// Creates the swapchain used by the XR views along with its images. Every view renders to its own array layer.
+///
+/// Returns the usage flags the swapchain was actually created with.
+pub(crate) fn create_swapchain(
+    session: &OxrSession,
+    device: &wgpu::Device,
+    resolution: UVec2,
+    format: wgpu::TextureFormat,
+    view_count: u32,
+    usage: SwapchainUsageFlags,
+) -> OxrResult<(OxrSwapchain, OxrSwapchainImages, SwapchainUsageFlags)> {
+    let mut swapchain_info = SwapchainCreateInfo {
+        create_flags: SwapchainCreateFlags::EMPTY,
+        usage_flags: usage,
+        format,
+        // bevy resolves its multisampled view target into the swapchain image,
+        // so the swapchain itself is always single sampled.
+        sample_count: 1,
+        width: resolution.x,
+        height: resolution.y,
+        face_count: 1,
+        array_size: view_count,
+        mip_count: 1,
+    };
+
+    // with XR_FB_foveation enabled the swapchain has to opt into foveation when it is created,
+    // otherwise the foveation profiles applied later are ignored
+    let exts = session.instance().exts();
+    let create = |info| {
+        if exts.fb_foveation.is_none() {
+            return session.create_swapchain(info);
+        }
+        let flags = if exts.fb_foveation_vulkan.is_some() {
+            openxr::sys::SwapchainCreateFoveationFlagsFB::FRAGMENT_DENSITY_MAP
+        } else {
+            openxr::sys::SwapchainCreateFoveationFlagsFB::EMPTY
+        };
+        session.create_foveated_swapchain(info, flags)
+    };
+
+    let swapchain = match create(swapchain_info) {
+        Err(OxrError::OpenXrError(openxr::sys::Result::ERROR_FEATURE_UNSUPPORTED))
+            if usage != FALLBACK_SWAPCHAIN_USAGE =>
+        {
+            warn!(
+                "Runtime doesn't support swapchain usage {usage:?}, falling back to {FALLBACK_SWAPCHAIN_USAGE:?}"
+            );
+            swapchain_info.usage_flags = FALLBACK_SWAPCHAIN_USAGE;
+            create(swapchain_info)?
+        }
+        swapchain => swapchain?,
+    };
+
+    let images = swapchain.enumerate_images(device, swapchain_info)?;
+
+    Ok((swapchain, images, swapchain_info.usage_flags))
+}
+
+/// Picks the highest MSAA sample count supported by bevy that is no larger than both the wanted and the maximum sample count.
+pub(crate) fn select_sample_count(wanted: u32, max: u32) -> u32 {
+    let sample_count = [8, 4, 2, 1]
+        .into_iter()
+        .find(|count| *count <= wanted.min(max))
+        .unwrap_or(1);
+    if sample_count != wanted {
+        warn!(
+            "Requested MSAA sample count {wanted} is not supported (runtime maximum is {max}), using {sample_count} instead"
+        );
+    }
+    sample_count
+}
+
 pub fn create_xr_session(world: &mut World) {
     let mut chain = world
         .remove_non_send::<OxrSessionCreateNextChain>()
@@ -497,6 +655,9 @@ pub fn create_xr_session(world: &mut World) {
     let session_config = world.resource::<OxrSessionConfig>();
     let session_create_info = world.non_send::<SessionGraphicsCreateInfo>();
     let system_id = world.resource::<OxrSystemId>();
+    let input_attachment_supported = world
+        .get_resource::<OxrEnabledExtensions>()
+        .is_some_and(|exts| exts.khr_swapchain_usage_input_attachment_bit);
     match init_xr_session(
         device.wgpu_device(),
         instance,
@@ -504,6 +665,7 @@ pub fn create_xr_session(world: &mut World) {
         &mut chain,
         session_config.clone(),
         session_create_info.clone(),
+        input_attachment_supported,
     ) {
         Ok((
             session,
@@ -516,8 +678,8 @@ pub fn create_xr_session(world: &mut World) {
         )) => {
             world.insert_resource(session.clone());
             world.insert_resource(frame_waiter);
-            world.insert_resource(images);
-            world.insert_resource(graphics_info);
+            world.insert_resource(images.clone());
+            world.insert_resource(graphics_info.clone());
             world.insert_resource(OxrRenderResources {
                 session,
                 frame_stream,
@@ -551,11 +713,20 @@ pub fn destroy_xr_session(world: &mut World) {
 
 pub fn begin_xr_session(world: &mut World) {
     let _span = debug_span!("xr_begin_session").entered();
-    world
-        .get_resource::<OxrSession>()
-        .unwrap()
-        .begin(openxr::ViewConfigurationType::PRIMARY_STEREO)
-        .expect("Failed to begin session");
+    let view_configuration = world
+        .resource::<OxrCurrentSessionConfig>()
+        .view_configuration;
+    let secondary_view_configurations = world
+        .get_resource::<OxrSecondaryViewStates>()
+        .map(|states| states.view_configurations())
+        .unwrap_or_default();
+    let session = world.get_resource::<OxrSession>().unwrap();
+    if secondary_view_configurations.is_empty() {
+        session.begin(view_configuration)
+    } else {
+        session.begin_with_secondary(view_configuration, &secondary_view_configurations)
+    }
+    .expect("Failed to begin session");
     drop(_span);
     world.get_resource_mut::<OxrSessionStarted>().unwrap().0 = true;
     world.run_schedule(XrPostSessionBegin);
@@ -608,3 +779,155 @@ pub fn transfer_xr_resources(mut commands: Commands, mut world: ResMut<MainWorld
     commands.insert_resource(graphics_info);
     commands.insert_resource(session_destroy_flag);
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy_math::UVec2;
+
+    use super::{
+        FALLBACK_SWAPCHAIN_USAGE, select_format, select_resolution, select_swapchain_usage,
+    };
+    use crate::resources::{OxrResolutionReason, OxrSessionConfig};
+    use crate::types::{SwapchainCreateFlags, SwapchainCreateInfo};
+
+    fn view(recommended: UVec2, max: UVec2) -> openxr::ViewConfigurationView {
+        openxr::ViewConfigurationView {
+            recommended_image_rect_width: recommended.x,
+            max_image_rect_width: max.x,
+            recommended_image_rect_height: recommended.y,
+            max_image_rect_height: max.y,
+            recommended_swapchain_sample_count: 1,
+            max_swapchain_sample_count: 4,
+        }
+    }
+
+    #[test]
+    fn test_render_scale_clamped_to_max() {
+        let views = [view(UVec2::new(1000, 1000), UVec2::new(1200, 1500)); 2];
+
+        let (resolution, view_resolutions, reason) = select_resolution(None, 1.0, &views).unwrap();
+        assert_eq!(resolution, UVec2::new(1000, 1000));
+        assert_eq!(view_resolutions, vec![UVec2::new(1000, 1000); 2]);
+        assert_eq!(reason, OxrResolutionReason::RenderScale(1.0));
+
+        let (resolution, _, reason) = select_resolution(None, 1.4, &views).unwrap();
+        assert_eq!(resolution, UVec2::new(1200, 1400));
+        assert_eq!(reason, OxrResolutionReason::ClampedToMax);
+    }
+
+    #[test]
+    fn test_per_view_resolutions() {
+        let views = [
+            view(UVec2::new(1000, 800), UVec2::new(2000, 2000)),
+            view(UVec2::new(900, 1000), UVec2::new(2000, 2000)),
+        ];
+
+        let (resolution, view_resolutions, _) = select_resolution(None, 1.0, &views).unwrap();
+        assert_eq!(resolution, UVec2::new(1000, 1000));
+        assert_eq!(
+            view_resolutions,
+            vec![UVec2::new(1000, 800), UVec2::new(900, 1000)]
+        );
+    }
+
+    #[test]
+    fn test_wanted_resolutions() {
+        let views = [view(UVec2::new(1000, 1000), UVec2::new(1500, 1500)); 2];
+
+        let (resolution, _, reason) = select_resolution(
+            Some(&[UVec2::new(2000, 2000), UVec2::new(1000, 1000)]),
+            1.0,
+            &views,
+        )
+        .unwrap();
+        assert_eq!(resolution, UVec2::new(1000, 1000));
+        assert_eq!(reason, OxrResolutionReason::MatchedRecommended);
+
+        let (resolution, _, reason) = select_resolution(
+            Some(&[UVec2::new(2000, 2000), UVec2::new(1200, 1200)]),
+            1.0,
+            &views,
+        )
+        .unwrap();
+        assert_eq!(resolution, UVec2::new(1200, 1200));
+        assert_eq!(reason, OxrResolutionReason::FitsMax);
+
+        assert!(select_resolution(Some(&[UVec2::new(2000, 2000)]), 1.0, &views).is_none());
+    }
+
+    #[test]
+    fn test_format_preference_order() {
+        use wgpu::TextureFormat as Tf;
+        let available = [Tf::Bgra8UnormSrgb, Tf::Rgba8UnormSrgb, Tf::Rgba16Float];
+
+        let formats = OxrSessionConfig::default().formats.unwrap();
+        assert_eq!(
+            select_format(Some(&formats), &available),
+            Some(Tf::Rgba8UnormSrgb)
+        );
+        let formats = OxrSessionConfig::default().with_hdr().formats.unwrap();
+        assert_eq!(
+            select_format(Some(&formats), &available),
+            Some(Tf::Rgba16Float)
+        );
+        assert_eq!(
+            select_format(Some(&formats), &[Tf::Bgra8UnormSrgb]),
+            Some(Tf::Bgra8UnormSrgb)
+        );
+
+        assert_eq!(select_format(None, &available), Some(Tf::Bgra8UnormSrgb));
+        assert!(select_format(Some(&[Tf::Rgb10a2Unorm]), &available).is_none());
+    }
+
+    #[test]
+    fn test_swapchain_usage_validation() {
+        use crate::types::SwapchainUsageFlags as U;
+        use wgpu::TextureFormat as Tf;
+        let features = wgpu::Features::empty();
+
+        let usage = select_swapchain_usage(U::SAMPLED, Tf::Rgba8UnormSrgb, features, false);
+        assert_eq!(usage, U::COLOR_ATTACHMENT | U::SAMPLED);
+
+        // sRGB formats can't be storage textures
+        let wanted = U::COLOR_ATTACHMENT | U::UNORDERED_ACCESS;
+        let usage = select_swapchain_usage(wanted, Tf::Rgba8UnormSrgb, features, false);
+        assert_eq!(usage, U::COLOR_ATTACHMENT);
+        let usage = select_swapchain_usage(wanted, Tf::Rgba8Unorm, features, false);
+        assert_eq!(usage, wanted);
+
+        let wanted = U::COLOR_ATTACHMENT | U::INPUT_ATTACHMENT;
+        let usage = select_swapchain_usage(wanted, Tf::Rgba8UnormSrgb, features, false);
+        assert_eq!(usage, U::COLOR_ATTACHMENT);
+        let usage = select_swapchain_usage(wanted, Tf::Rgba8UnormSrgb, features, true);
+        assert_eq!(usage, wanted);
+    }
+
+    #[test]
+    fn test_default_swapchain_usage_is_copy_dst() {
+        let texture_usages = |usage_flags| {
+            SwapchainCreateInfo {
+                create_flags: SwapchainCreateFlags::EMPTY,
+                usage_flags,
+                format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                sample_count: 1,
+                width: 1,
+                height: 1,
+                face_count: 1,
+                array_size: 2,
+                mip_count: 1,
+            }
+            .texture_usages()
+        };
+        let usage = select_swapchain_usage(
+            OxrSessionConfig::default().swapchain_usage,
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            wgpu::Features::empty(),
+            false,
+        );
+        for usage in [usage, FALLBACK_SWAPCHAIN_USAGE] {
+            let (hal_usage, usage) = texture_usages(usage);
+            assert!(hal_usage.contains(wgpu::TextureUses::COPY_DST));
+            assert!(usage.contains(wgpu::TextureUsages::COPY_DST));
+        }
+    }
+}
@@ -1,15 +1,35 @@
-use std::mem;
+use std::{marker::PhantomData, mem};
 
+use bevy_camera::ManualTextureViewHandle;
 use bevy_ecs::world::World;
 use bevy_mod_xr::spaces::{XrPrimaryReferenceSpace, XrSpace};
 use openxr::{sys, CompositionLayerFlags, Fovf, Posef, Rect2Di};
 
 use crate::graphics::graphics_match;
+use crate::render::XR_TEXTURE_INDEX;
 use crate::resources::*;
 use crate::spaces::OxrSpaceExt as _;
 
 pub trait LayerProvider {
     fn get<'a>(&'a self, world: &'a World) -> Option<Box<dyn CompositionLayer<'a> + 'a>>;
+
+    /// Every layer of this provider for the current frame, in submission order.
+    ///
+    /// Providers submitting a varying number of layers override this, by default it submits [`get`](Self::get).
+    fn get_all<'a>(&'a self, world: &'a World) -> Vec<Box<dyn CompositionLayer<'a> + 'a>> {
+        self.get(world).into_iter().collect()
+    }
+}
+
+/// Extends the [`ProjectionLayer`] of the primary views before it is submitted, e.g. by chaining structs onto its views.
+///
+/// Extensions are registered in [`OxrProjectionLayerExtensions`] in the render world.
+pub trait ProjectionLayerExtension {
+    fn extend<'a>(
+        &'a self,
+        world: &'a World,
+        layer: CompositionLayerProjection<'a>,
+    ) -> CompositionLayerProjection<'a>;
 }
 
 pub struct ProjectionLayer;
@@ -22,43 +42,55 @@ impl LayerProvider for ProjectionLayer {
         let openxr_views = world.get_resource::<OxrViews>()?;
         let swapchain = world.get_resource::<OxrSwapchain>()?;
         let graphics_info = world.get_resource::<OxrCurrentSessionConfig>()?;
-        let rect = openxr::Rect2Di {
-            offset: openxr::Offset2Di { x: 0, y: 0 },
-            extent: openxr::Extent2Di {
-                width: graphics_info.resolution.x as _,
-                height: graphics_info.resolution.y as _,
-            },
+        let resolution_scale = world.get_resource::<OxrResolutionScale>();
+        let rect = |index: u32| {
+            let resolution = graphics_info.view_resolution(index);
+            let resolution = resolution_scale.map_or(resolution, |scale| scale.apply(resolution));
+            openxr::Rect2Di {
+                offset: openxr::Offset2Di { x: 0, y: 0 },
+                extent: openxr::Extent2Di {
+                    width: resolution.x as _,
+                    height: resolution.y as _,
+                },
+            }
         };
 
-        if openxr_views.len() < 2 {
+        if openxr_views.len() < graphics_info.view_count() as usize {
+            return None;
+        }
+        // the image was acquired, but no camera rendered to it this frame
+        let rendered = world.get_resource::<OxrRenderedTextureViews>()?;
+        if !(0..graphics_info.view_count())
+            .any(|index| rendered.contains(&ManualTextureViewHandle(XR_TEXTURE_INDEX + index)))
+        {
             return None;
         }
 
-        Some(Box::new(
-            CompositionLayerProjection::new()
-                .layer_flags(CompositionLayerFlags::BLEND_TEXTURE_SOURCE_ALPHA)
-                .space(stage)
-                .views(&[
-                    CompositionLayerProjectionView::new()
-                        .pose(openxr_views.0[0].pose)
-                        .fov(openxr_views.0[0].fov)
-                        .sub_image(
-                            SwapchainSubImage::new()
-                                .swapchain(swapchain)
-                                .image_array_index(0)
-                                .image_rect(rect),
-                        ),
-                    CompositionLayerProjectionView::new()
-                        .pose(openxr_views.0[1].pose)
-                        .fov(openxr_views.0[1].fov)
-                        .sub_image(
-                            SwapchainSubImage::new()
-                                .swapchain(swapchain)
-                                .image_array_index(1)
-                                .image_rect(rect),
-                        ),
-                ]),
-        ))
+        let views = (0..graphics_info.view_count())
+            .map(|index| {
+                let view = &openxr_views.0[index as usize];
+                CompositionLayerProjectionView::new()
+                    .pose(view.pose)
+                    .fov(view.fov)
+                    .sub_image(
+                        SwapchainSubImage::new()
+                            .swapchain(swapchain)
+                            .image_array_index(index)
+                            .image_rect(rect(index)),
+                    )
+            })
+            .collect::<Vec<_>>();
+
+        let mut layer = CompositionLayerProjection::new()
+            .layer_flags(CompositionLayerFlags::BLEND_TEXTURE_SOURCE_ALPHA)
+            .space(stage)
+            .views(&views);
+        if let Some(extensions) = world.get_resource::<OxrProjectionLayerExtensions>() {
+            for extension in extensions.iter() {
+                layer = extension.extend(world, layer);
+            }
+        }
+        Some(Box::new(layer))
     }
 }
 
@@ -183,6 +215,7 @@ pub struct CompositionLayerProjection<'a> {
     inner: sys::CompositionLayerProjection,
     swapchain: Option<&'a OxrSwapchain>,
     views: Vec<sys::CompositionLayerProjectionView>,
+    space_warp_infos: Vec<sys::CompositionLayerSpaceWarpInfoFB>,
 }
 impl<'a> CompositionLayerProjection<'a> {
     #[inline]
@@ -194,6 +227,7 @@ impl<'a> CompositionLayerProjection<'a> {
             },
             swapchain: None,
             views: Vec::new(),
+            space_warp_infos: Vec::new(),
         }
     }
     #[inline]
@@ -219,8 +253,21 @@ impl<'a> CompositionLayerProjection<'a> {
         self.views = value.iter().map(|view| view.inner).collect();
         self.inner.views = self.views.as_slice().as_ptr() as *const _ as _;
         self.inner.view_count = self.views.len() as u32;
+        self.link_space_warp_infos();
         self
     }
+    /// Chains one [`CompositionLayerSpaceWarpInfoFB`] onto each view, in the same order as the views.
+    #[inline]
+    pub fn space_warp_infos(mut self, value: &[CompositionLayerSpaceWarpInfoFB<'a>]) -> Self {
+        self.space_warp_infos = value.iter().map(|info| info.inner).collect();
+        self.link_space_warp_infos();
+        self
+    }
+    fn link_space_warp_infos(&mut self) {
+        for (view, info) in self.views.iter_mut().zip(&self.space_warp_infos) {
+            view.next = info as *const _ as _;
+        }
+    }
 }
 unsafe impl<'a> CompositionLayer<'a> for CompositionLayerProjection<'a> {
     fn swapchain(&self) -> Option<&'a OxrSwapchain> {
@@ -236,6 +283,76 @@ impl Default for CompositionLayerProjection<'_> {
         Self::new()
     }
 }
+#[derive(Clone)]
+pub struct CompositionLayerQuad<'a> {
+    inner: sys::CompositionLayerQuad,
+    swapchain: Option<&'a OxrSwapchain>,
+}
+impl<'a> CompositionLayerQuad<'a> {
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            inner: sys::CompositionLayerQuad {
+                ty: sys::StructureType::COMPOSITION_LAYER_QUAD,
+                ..unsafe { mem::zeroed() }
+            },
+            swapchain: None,
+        }
+    }
+    #[inline]
+    pub fn into_raw(self) -> sys::CompositionLayerQuad {
+        self.inner
+    }
+    #[inline]
+    pub fn as_raw(&self) -> &sys::CompositionLayerQuad {
+        &self.inner
+    }
+    #[inline]
+    pub fn layer_flags(mut self, value: CompositionLayerFlags) -> Self {
+        self.inner.layer_flags = value;
+        self
+    }
+    #[inline]
+    pub fn space(mut self, value: &XrSpace) -> Self {
+        self.inner.space = value.as_raw_openxr_space();
+        self
+    }
+    #[inline]
+    pub fn eye_visibility(mut self, value: sys::EyeVisibility) -> Self {
+        self.inner.eye_visibility = value;
+        self
+    }
+    #[inline]
+    pub fn sub_image(mut self, value: SwapchainSubImage<'a>) -> Self {
+        self.inner.sub_image = value.inner;
+        self.swapchain = value.swapchain;
+        self
+    }
+    #[inline]
+    pub fn pose(mut self, value: Posef) -> Self {
+        self.inner.pose = value;
+        self
+    }
+    #[inline]
+    pub fn size(mut self, value: sys::Extent2Df) -> Self {
+        self.inner.size = value;
+        self
+    }
+}
+unsafe impl<'a> CompositionLayer<'a> for CompositionLayerQuad<'a> {
+    fn swapchain(&self) -> Option<&'a OxrSwapchain> {
+        self.swapchain
+    }
+
+    fn header(&self) -> &sys::CompositionLayerBaseHeader {
+        unsafe { mem::transmute(&self.inner) }
+    }
+}
+impl Default for CompositionLayerQuad<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 pub struct CompositionLayerPassthroughFB {
     inner: sys::CompositionLayerPassthroughFB,
 }
@@ -275,3 +392,73 @@ unsafe impl<'a> CompositionLayer<'a> for CompositionLayerPassthroughFB {
         unsafe { mem::transmute(&self.inner) }
     }
 }
+#[derive(Copy, Clone)]
+pub struct CompositionLayerSpaceWarpInfoFB<'a> {
+    inner: sys::CompositionLayerSpaceWarpInfoFB,
+    _swapchains: PhantomData<&'a OxrSwapchain>,
+}
+impl<'a> CompositionLayerSpaceWarpInfoFB<'a> {
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            inner: sys::CompositionLayerSpaceWarpInfoFB {
+                ty: sys::CompositionLayerSpaceWarpInfoFB::TYPE,
+                ..unsafe { mem::zeroed() }
+            },
+            _swapchains: PhantomData,
+        }
+    }
+    #[inline]
+    pub fn into_raw(self) -> sys::CompositionLayerSpaceWarpInfoFB {
+        self.inner
+    }
+    #[inline]
+    pub fn as_raw(&self) -> &sys::CompositionLayerSpaceWarpInfoFB {
+        &self.inner
+    }
+    #[inline]
+    pub fn layer_flags(mut self, value: sys::CompositionLayerSpaceWarpInfoFlagsFB) -> Self {
+        self.inner.layer_flags = value;
+        self
+    }
+    #[inline]
+    pub fn motion_vector_sub_image(mut self, value: SwapchainSubImage<'a>) -> Self {
+        self.inner.motion_vector_sub_image = value.inner;
+        self
+    }
+    #[inline]
+    pub fn app_space_delta_pose(mut self, value: Posef) -> Self {
+        self.inner.app_space_delta_pose = value;
+        self
+    }
+    #[inline]
+    pub fn depth_sub_image(mut self, value: SwapchainSubImage<'a>) -> Self {
+        self.inner.depth_sub_image = value.inner;
+        self
+    }
+    #[inline]
+    pub fn min_depth(mut self, value: f32) -> Self {
+        self.inner.min_depth = value;
+        self
+    }
+    #[inline]
+    pub fn max_depth(mut self, value: f32) -> Self {
+        self.inner.max_depth = value;
+        self
+    }
+    #[inline]
+    pub fn near_z(mut self, value: f32) -> Self {
+        self.inner.near_z = value;
+        self
+    }
+    #[inline]
+    pub fn far_z(mut self, value: f32) -> Self {
+        self.inner.far_z = value;
+        self
+    }
+}
+impl Default for CompositionLayerSpaceWarpInfoFB<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -66,6 +66,9 @@ pub fn add_xr_plugins<G: PluginGroup>(plugins: G) -> PluginGroupBuilder {
         .add(action_binding::OxrActionBindingPlugin)
         .add(action_set_syncing::OxrActionSyncingPlugin)
         .add(features::overlay::OxrOverlayPlugin)
+        .add(features::late_latch::OxrLateLatchPlugin)
+        .add(features::screenshot::OxrScreenshotPlugin)
+        .add(features::quad_layer::OxrQuadLayerPlugin)
         .add(spaces::OxrSpatialPlugin)
         .add(spaces::OxrSpacePatchingPlugin);
     // we should probably handle the exiting ourselfs so that we can correctly end the
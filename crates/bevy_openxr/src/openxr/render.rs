@@ -1,33 +1,52 @@
 use bevy_app::{App, Plugin, PostUpdate};
-use bevy_camera::{Camera, ManualTextureViewHandle, Projection, RenderTarget};
+use bevy_camera::{
+    Camera, ManualTextureViewHandle, NormalizedRenderTarget, Projection, RenderTarget, Viewport,
+};
 use bevy_ecs::{
-    change_detection::DetectChanges as _,
+    change_detection::{DetectChanges as _, Ref},
+    component::Component,
     entity::Entity,
-    schedule::{IntoScheduleConfigs as _, SystemSet},
+    message::{Message, MessageReader},
+    query::{Added, With},
+    resource::Resource,
+    schedule::{
+        IntoScheduleConfigs as _, SystemSet,
+        common_conditions::{not, on_message, resource_exists},
+    },
     system::{Commands, Query, Res, ResMut},
     world::World,
 };
 use bevy_log::{debug_span, error, info, warn};
+use bevy_math::{UVec2, Vec3, Vec4};
 use bevy_render::{
-    Render, RenderApp,
+    ExtractSchedule, MainWorld, Render, RenderApp, RenderSystems,
+    camera::{SortedCameras, sort_cameras},
     extract_resource::ExtractResourcePlugin,
     pipelined_rendering::PipelinedRenderingPlugin,
+    renderer::RenderDevice,
     texture::{ManualTextureView, ManualTextureViews},
-    view::ExtractedView,
+    view::{ExtractedView, Hdr, Msaa},
 };
 
 use bevy_mod_xr::{
-    camera::{Fov, XrCamera, XrProjection, XrViewInit, calculate_projection},
+    camera::{
+        Fov, XrAdditionalCamera, XrCamera, XrClipPlanes, XrProjection, XrViewInit, XrViewUniform,
+        calculate_projection,
+    },
     session::{
         XrFirst, XrHandleEvents, XrPreDestroySession, XrRenderSystems, XrRootTransform,
-        XrSessionCreated,
+        XrSessionCreated, XrState,
     },
     spaces::XrPrimaryReferenceSpace,
 };
 use bevy_transform::{TransformSystems, components::Transform};
 use openxr::ViewStateFlags;
 
-use crate::{helper_traits::ToTransform as _, init::should_run_frame_loop, resources::*};
+use crate::{
+    helper_traits::ToTransform as _,
+    init::{create_swapchain, select_sample_count, should_run_frame_loop},
+    resources::*,
+};
 use crate::{layer_builder::ProjectionLayer, session::OxrSession};
 
 use super::environment_blend_mode::OxrEnvironmentBlendModes;
@@ -55,6 +74,10 @@ impl Default for OxrRenderPlugin {
 #[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone, Copy)]
 pub struct OxrWaitFrameSystem;
 
+/// Disables the default [`wait_frame`] system, for plugins that call `xrWaitFrame` on their own.
+#[derive(Resource, Clone, Copy, Debug, Default)]
+pub struct OxrExternalWaitFrame;
+
 impl Plugin for OxrRenderPlugin {
     fn build(&self, app: &mut App) {
         if app.is_plugin_added::<PipelinedRenderingPlugin>() {
@@ -66,6 +89,7 @@ impl Plugin for OxrRenderPlugin {
             ExtractResourcePlugin::<OxrCurrentSessionConfig>::default(),
             ExtractResourcePlugin::<OxrSwapchainImages>::default(),
             ExtractResourcePlugin::<OxrViews>::default(),
+            ExtractResourcePlugin::<OxrResolutionScale>::default(),
         ))
         .add_systems(XrPreDestroySession, clean_views);
         if self.default_wait_frame {
@@ -73,13 +97,24 @@ impl Plugin for OxrRenderPlugin {
                 XrFirst,
                 wait_frame
                     .run_if(should_run_frame_loop)
+                    .run_if(not(resource_exists::<OxrExternalWaitFrame>))
                     .in_set(OxrWaitFrameSystem)
                     .in_set(XrHandleEvents::FrameLoop),
             );
         }
+        app.add_message::<OxrRecreateSwapchainMessage>()
+            .add_systems(
+                XrFirst,
+                recreate_swapchain
+                    .run_if(should_run_frame_loop)
+                    .run_if(on_message::<OxrRecreateSwapchainMessage>)
+                    .before(OxrWaitFrameSystem)
+                    .in_set(XrHandleEvents::FrameLoop),
+            );
         app.add_systems(
             XrFirst,
-            update_cameras
+            (init_additional_cameras, update_cameras)
+                .chain()
                 .run_if(should_run_frame_loop)
                 .after(OxrWaitFrameSystem)
                 .in_set(XrHandleEvents::FrameLoop),
@@ -100,12 +135,17 @@ impl Plugin for OxrRenderPlugin {
                 .chain()
                 .run_if(should_run_frame_loop),
         )
-        .init_resource::<OxrViews>();
+        .init_resource::<OxrViews>()
+        .init_resource::<OxrResolutionScale>();
 
         let render_app = app.sub_app_mut(RenderApp);
 
         render_app
-            .add_systems(XrPreDestroySession, clean_views)
+            .init_resource::<OxrSwapchainImageIndex>()
+            .init_resource::<OxrRenderedTextureViews>()
+            .init_resource::<OxrRetiredSwapchains>()
+            .add_systems(XrPreDestroySession, (clean_views, clean_retired_swapchains))
+            .add_systems(ExtractSchedule, transfer_recreated_swapchain)
             .add_systems(
                 Render,
                 (
@@ -121,15 +161,23 @@ impl Plugin for OxrRenderPlugin {
             )
             .add_systems(
                 Render,
-                (release_image, end_frame)
+                skip_unacquired_cameras
+                    .after(sort_cameras)
+                    .in_set(RenderSystems::ManageViews),
+            )
+            .add_systems(
+                Render,
+                (release_image, end_frame, drop_retired_swapchains)
                     .chain()
                     .run_if(should_run_frame_loop)
                     .in_set(XrRenderSystems::PostRender),
             )
-            .insert_resource(OxrRenderLayers(vec![Box::new(ProjectionLayer)]));
+            .insert_resource(OxrRenderLayers(vec![Box::new(ProjectionLayer)]))
+            .init_resource::<OxrProjectionLayerExtensions>();
     }
 }
 
+/// First texture view handle of the XR swapchains, the handles from here on are reserved for them.
 pub const XR_TEXTURE_INDEX: u32 = 3383858418;
 
 pub fn clean_views(
@@ -150,39 +198,306 @@ pub fn init_views<const SPAWN_CAMERAS: bool>(
     mut commands: Commands,
 ) {
     let temp_tex = swapchain_images.first().unwrap();
-    // this for loop is to easily add support for quad or mono views in the future.
-    for index in 0..2 {
+    for index in 0..graphics_info.view_count() {
         let _span = debug_span!("xr_init_view").entered();
-        info!("XrCamera resolution: {}", graphics_info.resolution);
+        info!(
+            "XrCamera resolution: {}",
+            graphics_info.view_resolution(index)
+        );
         let view_handle =
             add_texture_view(&mut manual_texture_views, temp_tex, &graphics_info, index);
         if SPAWN_CAMERAS {
-            commands.spawn((
+            let mut camera = commands.spawn((
                 RenderTarget::TextureView(view_handle),
                 XrCamera(index),
                 Projection::custom(XrProjection::default()),
+                Msaa::from_samples(graphics_info.sample_count),
                 // NoFrustumCulling,
             ));
+            if graphics_info.hdr {
+                camera.insert(Hdr);
+            }
+        }
+    }
+}
+
+/// Message sent to recreate the swapchain of the current session without restarting the session.
+///
+/// Any field left as [`None`] keeps its current value.
+/// The new swapchain is handed to the render world during extraction, so the swap always happens between two frames.
+#[derive(Message, Clone, Copy, Debug, Default)]
+pub struct OxrRecreateSwapchainMessage {
+    pub resolution: Option<UVec2>,
+    pub format: Option<wgpu::TextureFormat>,
+    pub sample_count: Option<u32>,
+}
+
+impl OxrRecreateSwapchainMessage {
+    /// Combines two requests, the fields set in `later` take precedence.
+    fn then(self, later: &Self) -> Self {
+        Self {
+            resolution: later.resolution.or(self.resolution),
+            format: later.format.or(self.format),
+            sample_count: later.sample_count.or(self.sample_count),
         }
     }
 }
 
-pub fn wait_frame(mut frame_waiter: ResMut<OxrFrameWaiter>, mut commands: Commands) {
-    let state = frame_waiter.wait().expect("Failed to wait frame");
+/// This is used solely to transport a recreated [`OxrSwapchain`] from the main world to the render world,
+/// along with the images of the swapchain it replaces.
+#[derive(Resource)]
+struct OxrRecreatedSwapchain {
+    swapchain: OxrSwapchain,
+    old_images: OxrSwapchainImages,
+}
+
+/// Number of frames a replaced swapchain is kept alive, so frames still in flight on the GPU can finish with its images.
+const RETIRED_SWAPCHAIN_FRAMES: u32 = 3;
+
+/// Replaced or removed swapchains along with their images and the number of frames they are still kept alive.
+///
+/// The images are dropped before the swapchain they belong to.
+#[derive(Resource, Default)]
+pub(crate) struct OxrRetiredSwapchains(Vec<(OxrSwapchainImages, OxrSwapchain, u32)>);
+
+impl OxrRetiredSwapchains {
+    /// Keeps a swapchain and its images alive until the frames still in flight are done with them.
+    pub(crate) fn retire(&mut self, images: OxrSwapchainImages, swapchain: OxrSwapchain) {
+        self.0.push((images, swapchain, RETIRED_SWAPCHAIN_FRAMES));
+    }
+}
+
+/// Scales the resolution of every view by the change of the swapchain resolution, keeping the views in the new swapchain.
+fn scale_view_resolutions(view_resolutions: &[UVec2], old: UVec2, new: UVec2) -> Vec<UVec2> {
+    let scale = new.as_vec2() / old.max(UVec2::ONE).as_vec2();
+    view_resolutions
+        .iter()
+        .map(|resolution| {
+            (resolution.as_vec2() * scale)
+                .round()
+                .as_uvec2()
+                .clamp(UVec2::ONE, new.max(UVec2::ONE))
+        })
+        .collect()
+}
+
+pub fn recreate_swapchain(
+    mut messages: MessageReader<OxrRecreateSwapchainMessage>,
+    instance: Res<OxrInstance>,
+    system_id: Res<OxrSystemId>,
+    session: Res<OxrSession>,
+    device: Res<RenderDevice>,
+    mut graphics_info: ResMut<OxrCurrentSessionConfig>,
+    mut swapchain_images: ResMut<OxrSwapchainImages>,
+    mut manual_texture_views: ResMut<ManualTextureViews>,
+    mut cameras: Query<&mut Msaa, With<XrCamera>>,
+    mut commands: Commands,
+) {
+    // later requests override earlier ones
+    let request = messages.read().fold(
+        OxrRecreateSwapchainMessage::default(),
+        |request, message| request.then(message),
+    );
+    let resolution = request.resolution.unwrap_or(graphics_info.resolution);
+    let format = request.format.unwrap_or(graphics_info.format);
+
+    let views = match instance
+        .enumerate_view_configuration_views(**system_id, graphics_info.view_configuration)
+    {
+        Ok(views) => views,
+        Err(err) => {
+            error!("Failed to enumerate view configuration views: {err}");
+            return;
+        }
+    };
+    if views.iter().any(|view| {
+        resolution.x > view.max_image_rect_width || resolution.y > view.max_image_rect_height
+    }) {
+        error!("Swapchain resolution {resolution} exceeds the maximum supported by the runtime");
+        return;
+    }
+    match session.enumerate_swapchain_formats() {
+        Ok(formats) if formats.contains(&format) => {}
+        Ok(_) => {
+            error!("Swapchain format {format:?} is not supported by the runtime");
+            return;
+        }
+        Err(err) => {
+            error!("Failed to enumerate swapchain formats: {err}");
+            return;
+        }
+    }
+    let max_sample_count = views
+        .iter()
+        .map(|view| view.max_swapchain_sample_count)
+        .min()
+        .unwrap_or(1);
+    let sample_count = select_sample_count(
+        request.sample_count.unwrap_or(graphics_info.sample_count),
+        max_sample_count,
+    );
+
+    let (swapchain, images, swapchain_usage) = match create_swapchain(
+        &session,
+        device.wgpu_device(),
+        resolution,
+        format,
+        views.len() as u32,
+        graphics_info.swapchain_usage,
+    ) {
+        Ok(v) => v,
+        Err(err) => {
+            error!("Failed to recreate swapchain: {err}");
+            return;
+        }
+    };
+    info!("Recreated XR swapchain with resolution {resolution} and format {format:?}");
+
+    *graphics_info = OxrCurrentSessionConfig {
+        view_configuration: graphics_info.view_configuration,
+        resolution,
+        view_resolutions: scale_view_resolutions(
+            &graphics_info.view_resolutions,
+            graphics_info.resolution,
+            resolution,
+        ),
+        resolution_reason: OxrResolutionReason::Recreated,
+        format,
+        sample_count,
+        hdr: graphics_info.hdr,
+        swapchain_usage,
+    };
+    let temp_tex = images.first().unwrap();
+    for index in 0..graphics_info.view_count() {
+        add_texture_view(&mut manual_texture_views, temp_tex, &graphics_info, index);
+    }
+    for mut msaa in &mut cameras {
+        *msaa = Msaa::from_samples(sample_count);
+    }
+    let old_images = std::mem::replace(&mut *swapchain_images, images);
+    commands.insert_resource(OxrRecreatedSwapchain {
+        swapchain,
+        old_images,
+    });
+}
+
+/// This system transfers a recreated swapchain to the render world, where it replaces the old one.
+///
+/// The old swapchain is retired instead of dropped, as frames submitted before the swap may still use its images.
+fn transfer_recreated_swapchain(
+    mut world: ResMut<MainWorld>,
+    swapchain: Option<ResMut<OxrSwapchain>>,
+    mut retired: ResMut<OxrRetiredSwapchains>,
+    mut commands: Commands,
+) {
+    let Some(OxrRecreatedSwapchain {
+        swapchain: new,
+        old_images,
+    }) = world.remove_resource()
+    else {
+        return;
+    };
+    match swapchain {
+        Some(mut swapchain) => {
+            let old = std::mem::replace(&mut *swapchain, new);
+            retired.retire(old_images, old);
+        }
+        None => commands.insert_resource(new),
+    }
+}
+
+fn drop_retired_swapchains(mut retired: ResMut<OxrRetiredSwapchains>) {
+    retired.0.retain_mut(|(_, _, frames)| {
+        *frames = frames.saturating_sub(1);
+        *frames > 0
+    });
+}
+
+fn clean_retired_swapchains(mut retired: ResMut<OxrRetiredSwapchains>) {
+    retired.0.clear();
+}
+
+pub fn wait_frame(
+    mut frame_waiter: ResMut<OxrFrameWaiter>,
+    session: Res<OxrSession>,
+    secondary_states: Option<ResMut<OxrSecondaryViewStates>>,
+    mut commands: Commands,
+) {
+    let state = match secondary_states {
+        Some(mut secondary_states) if !secondary_states.is_empty() => {
+            let (state, states) = frame_waiter
+                .wait_secondary(&session, &secondary_states.view_configurations())
+                .expect("Failed to wait frame");
+            secondary_states.0 = states;
+            state
+        }
+        _ => frame_waiter.wait().expect("Failed to wait frame"),
+    };
     commands.insert_resource(OxrFrameState(state));
 }
 
+/// Size of the viewport [`update_cameras`] last gave an XR camera.
+///
+/// Cameras with any other viewport have a viewport set by the user, which is left untouched.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct OxrScaledViewport(pub UVec2);
+
 pub fn update_cameras(
     frame_state: Res<OxrFrameState>,
-    mut cameras: Query<(&mut Camera, &mut RenderTarget, &XrCamera)>,
+    graphics_info: Res<OxrCurrentSessionConfig>,
+    resolution_scale: Res<OxrResolutionScale>,
+    mut cameras: Query<(
+        Entity,
+        &mut Camera,
+        &mut RenderTarget,
+        &XrCamera,
+        Option<Ref<XrAdditionalCamera>>,
+        Option<&OxrScaledViewport>,
+    )>,
+    mut commands: Commands,
 ) {
-    for (_, mut target, xr_camera) in &mut cameras {
-        *target =
-            RenderTarget::TextureView(ManualTextureViewHandle(XR_TEXTURE_INDEX + xr_camera.0));
+    for (entity, mut camera, mut target, xr_camera, additional, scaled_viewport) in &mut cameras {
+        let handle = ManualTextureViewHandle(XR_TEXTURE_INDEX + xr_camera.0);
+        if !matches!(*target, RenderTarget::TextureView(current) if current == handle) {
+            *target = RenderTarget::TextureView(handle);
+        }
+        let size = resolution_scale.apply(graphics_info.view_resolution(xr_camera.0));
+        let user_viewport = camera.viewport.as_ref().is_some_and(|viewport| {
+            scaled_viewport.is_none_or(|scaled| {
+                viewport.physical_position != UVec2::ZERO || viewport.physical_size != scaled.0
+            })
+        });
+        let viewport_size = camera
+            .viewport
+            .as_ref()
+            .map_or(graphics_info.resolution, |viewport| viewport.physical_size);
+        if !user_viewport && viewport_size != size {
+            camera.viewport = Some(Viewport {
+                physical_size: size,
+                ..Default::default()
+            });
+            commands.entity(entity).insert(OxrScaledViewport(size));
+        }
+        if frame_state.is_changed() || additional.as_ref().is_some_and(Ref::is_changed) {
+            camera.is_active =
+                frame_state.should_render && additional.is_none_or(|additional| additional.enabled);
+        }
     }
-    if frame_state.is_changed() {
-        for (mut camera, _, _) in &mut cameras {
-            camera.is_active = frame_state.should_render
+}
+
+/// Matches the [`Msaa`] and [`Hdr`] of new [`XrAdditionalCamera`]s to the swapchain.
+pub fn init_additional_cameras(
+    graphics_info: Res<OxrCurrentSessionConfig>,
+    cameras: Query<Entity, Added<XrAdditionalCamera>>,
+    mut commands: Commands,
+) {
+    for entity in &cameras {
+        let mut camera = commands.entity(entity);
+        camera.insert(Msaa::from_samples(graphics_info.sample_count));
+        if graphics_info.hdr {
+            camera.insert(Hdr);
+        } else {
+            camera.remove::<Hdr>();
         }
     }
 }
@@ -190,6 +505,7 @@ pub fn update_cameras(
 pub fn locate_views(
     session: Res<OxrSession>,
     ref_space: Res<XrPrimaryReferenceSpace>,
+    graphics_info: Res<OxrCurrentSessionConfig>,
     frame_state: Res<OxrFrameState>,
     mut openxr_views: ResMut<OxrViews>,
     pipelined: Option<Res<Pipelined>>,
@@ -203,11 +519,7 @@ pub fn locate_views(
         frame_state.predicted_display_time
     };
     let Ok((flags, xr_views)) = session
-        .locate_views(
-            openxr::ViewConfigurationType::PRIMARY_STEREO,
-            time,
-            &ref_space,
-        )
+        .locate_views(graphics_info.view_configuration, time, &ref_space)
         .inspect_err(|err| warn!("failed to locate views: {err}"))
     else {
         return;
@@ -239,20 +551,42 @@ pub fn locate_views(
 }
 
 pub fn update_views(
-    mut query: Query<(&mut Transform, &mut Projection, &XrCamera)>,
+    mut query: Query<(
+        &mut Transform,
+        &mut Projection,
+        &mut XrViewUniform,
+        &XrClipPlanes,
+        &XrCamera,
+    )>,
     views: ResMut<OxrViews>,
+    frame_state: Res<OxrFrameState>,
+    pipelined: Option<Res<Pipelined>>,
 ) {
-    for (mut transform, mut projection, camera) in query.iter_mut() {
+    let time = if pipelined.is_some() {
+        openxr::Time::from_nanos(
+            frame_state.predicted_display_time.as_nanos()
+                + frame_state.predicted_display_period.as_nanos(),
+        )
+    } else {
+        frame_state.predicted_display_time
+    };
+    for (mut transform, mut projection, mut uniform, clip_planes, camera) in query.iter_mut() {
         let Some(view) = views.get(camera.0 as usize) else {
             continue;
         };
-        let projection = match projection.as_mut() {
-            Projection::Custom(custom) => custom.get_mut::<XrProjection>().unwrap(),
-            _ => unreachable!(),
+        *uniform = view_uniform(&views, camera.0, time);
+        let Projection::Custom(custom) = projection.as_mut() else {
+            continue;
+        };
+        let Some(projection) = custom.get_mut::<XrProjection>() else {
+            continue;
         };
+        projection.near = clip_planes.near;
+        projection.far = clip_planes.far;
 
         let projection_matrix = calculate_projection(
             projection.near,
+            projection.far,
             Fov {
                 angle_left: view.fov.angle_left,
                 angle_right: view.fov.angle_right,
@@ -269,13 +603,52 @@ pub fn update_views(
 pub fn update_views_render_world(
     views: Res<OxrViews>,
     root: Res<XrRootTransform>,
-    mut query: Query<(&mut ExtractedView, &XrCamera)>,
+    frame_state: Res<OxrFrameState>,
+    mut query: Query<(&mut ExtractedView, &mut XrViewUniform, &XrCamera)>,
 ) {
-    for (mut extracted_view, camera) in query.iter_mut() {
+    for (mut extracted_view, mut uniform, camera) in query.iter_mut() {
         let Some(view) = views.get(camera.0 as usize) else {
             continue;
         };
         extracted_view.world_from_view = root.0.mul_transform(view.pose.to_transform());
+        *uniform = view_uniform(&views, camera.0, frame_state.predicted_display_time);
+    }
+}
+
+/// Hour after which [`XrViewUniform::predicted_display_time`] wraps around.
+const VIEW_TIME_WRAP_NANOS: i64 = 3_600_000_000_000;
+
+fn view_uniform(views: &[openxr::View], index: u32, time: openxr::Time) -> XrViewUniform {
+    let view = &views[index as usize];
+    let position = |view: &openxr::View| {
+        Vec3::new(
+            view.pose.position.x,
+            view.pose.position.y,
+            view.pose.position.z,
+        )
+    };
+    let ipd = match views {
+        [left, right, ..] => position(left).distance(position(right)),
+        _ => 0.0,
+    };
+    XrViewUniform {
+        fov: Vec4::new(
+            view.fov.angle_left,
+            view.fov.angle_right,
+            view.fov.angle_down,
+            view.fov.angle_up,
+        ),
+        orientation: Vec4::new(
+            view.pose.orientation.x,
+            view.pose.orientation.y,
+            view.pose.orientation.z,
+            view.pose.orientation.w,
+        ),
+        position: position(view),
+        index,
+        ipd,
+        predicted_display_time: (time.as_nanos().rem_euclid(VIEW_TIME_WRAP_NANOS) as f64 / 1e9)
+            as f32,
     }
 }
 
@@ -284,6 +657,7 @@ pub fn update_views_render_world(
 pub fn insert_texture_views(
     swapchain_images: Res<OxrSwapchainImages>,
     mut swapchain: ResMut<OxrSwapchain>,
+    mut image_index: ResMut<OxrSwapchainImageIndex>,
     mut manual_texture_views: ResMut<ManualTextureViews>,
     graphics_info: Res<OxrCurrentSessionConfig>,
     frame_state: Res<OxrFrameState>,
@@ -292,14 +666,43 @@ pub fn insert_texture_views(
         return;
     }
     let index = swapchain.acquire_image().expect("Failed to acquire image");
+    image_index.0 = index;
     let image = &swapchain_images[index as usize];
 
-    for i in 0..2 {
+    for i in 0..graphics_info.view_count() {
         let _span = debug_span!("xr_insert_texture_view").entered();
         add_texture_view(&mut manual_texture_views, image, &graphics_info, i);
     }
 }
 
+/// Keeps cameras from rendering to XR texture views while no XR images are acquired.
+///
+/// The main world toggles the XR cameras before the actual frame state is known,
+/// this uses the actual state so nothing renders to an image that wasn't acquired this frame.
+fn skip_unacquired_cameras(
+    frame_state: Option<Res<OxrFrameState>>,
+    mut sorted_cameras: ResMut<SortedCameras>,
+    mut rendered: ResMut<OxrRenderedTextureViews>,
+    started: Option<Res<OxrSessionStarted>>,
+    state: Option<Res<XrState>>,
+) {
+    rendered.0.clear();
+    let acquired = should_run_frame_loop(started, state)
+        && frame_state.is_some_and(|frame_state| frame_state.should_render);
+    sorted_cameras.0.retain(|camera| {
+        let Some(NormalizedRenderTarget::TextureView(handle)) = camera.target else {
+            return true;
+        };
+        if handle.0 < XR_TEXTURE_INDEX {
+            return true;
+        }
+        if acquired {
+            rendered.0.insert(handle);
+        }
+        acquired
+    });
+}
+
 pub fn wait_image(mut swapchain: ResMut<OxrSwapchain>, state: Res<OxrFrameState>) {
     if state.should_render {
         swapchain
@@ -361,20 +764,93 @@ pub fn end_frame(world: &mut World) {
         let _span = debug_span!("get layers").entered();
         if frame_state.should_render {
             for layer in world.resource::<OxrRenderLayers>().iter() {
-                if let Some(layer) = layer.get(world) {
-                    layers.push(layer);
-                }
+                layers.extend(layer.get_all(world));
+            }
+        }
+        // every active secondary view configuration is part of the frame, without layers if nothing rendered
+        let mut secondary_layers = vec![];
+        if let Some(secondary_states) = world.get_resource::<OxrSecondaryViewStates>() {
+            let secondary_providers = world.get_resource::<OxrSecondaryRenderLayers>();
+            for state in secondary_states.iter().filter(|state| state.active) {
+                let layers = match secondary_providers {
+                    Some(providers) if frame_state.should_render => providers
+                        .iter()
+                        .filter(|(ty, _)| *ty == state.view_configuration)
+                        .flat_map(|(_, layer)| layer.get_all(world))
+                        .collect::<Vec<_>>(),
+                    _ => vec![],
+                };
+                secondary_layers.push((state.view_configuration, layers));
             }
         }
         drop(_span);
         let layers: Vec<_> = layers.iter().map(Box::as_ref).collect();
+        let secondary_layers: Vec<(_, Vec<_>)> = secondary_layers
+            .iter()
+            .map(|(ty, layers)| (*ty, layers.iter().map(Box::as_ref).collect()))
+            .collect();
         let _span = debug_span!("xr_end_frame").entered();
-        if let Err(e) = frame_stream.end(
-            frame_state.predicted_display_time,
-            world.resource::<OxrEnvironmentBlendModes>().blend_mode(),
-            &layers,
-        ) {
+        let blend_mode = world.resource::<OxrEnvironmentBlendModes>().blend_mode();
+        let result = if secondary_layers.is_empty() {
+            frame_stream.end(frame_state.predicted_display_time, blend_mode, &layers)
+        } else {
+            let secondary_layers: Vec<_> = secondary_layers
+                .iter()
+                .map(|(ty, layers)| (*ty, layers.as_slice()))
+                .collect();
+            frame_stream.end_secondary(
+                world.resource::<OxrSession>(),
+                frame_state.predicted_display_time,
+                blend_mode,
+                &layers,
+                &secondary_layers,
+            )
+        };
+        if let Err(e) = result {
             error!("Failed to end frame stream: {e}");
         }
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy_math::UVec2;
+
+    use super::{OxrRecreateSwapchainMessage, scale_view_resolutions};
+
+    #[test]
+    fn test_scale_view_resolutions() {
+        let views = [UVec2::new(1000, 1000), UVec2::new(800, 600)];
+
+        let scaled = scale_view_resolutions(&views, UVec2::new(1000, 1000), UVec2::new(500, 2000));
+        assert_eq!(scaled, vec![UVec2::new(500, 2000), UVec2::new(400, 1200)]);
+
+        let unchanged =
+            scale_view_resolutions(&views, UVec2::new(1000, 1000), UVec2::new(1000, 1000));
+        assert_eq!(unchanged, views.to_vec());
+
+        let tiny = scale_view_resolutions(&views, UVec2::new(1000, 1000), UVec2::new(1, 1));
+        assert_eq!(tiny, vec![UVec2::ONE; 2]);
+    }
+
+    #[test]
+    fn test_later_recreate_requests_take_precedence() {
+        let first = OxrRecreateSwapchainMessage {
+            resolution: Some(UVec2::new(1000, 1000)),
+            format: Some(wgpu::TextureFormat::Rgba8UnormSrgb),
+            sample_count: None,
+        };
+        let second = OxrRecreateSwapchainMessage {
+            resolution: Some(UVec2::new(500, 500)),
+            format: None,
+            sample_count: Some(4),
+        };
+
+        let request = OxrRecreateSwapchainMessage::default()
+            .then(&first)
+            .then(&second);
+        assert_eq!(request.resolution, Some(UVec2::new(500, 500)));
+        assert_eq!(request.format, Some(wgpu::TextureFormat::Rgba8UnormSrgb));
+        assert_eq!(request.sample_count, Some(4));
+    }
+}
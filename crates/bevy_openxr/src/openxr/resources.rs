@@ -1,12 +1,16 @@
+use std::sync::Arc;
+
+use bevy_camera::ManualTextureViewHandle;
 use bevy_derive::{Deref, DerefMut};
 use bevy_ecs::resource::Resource;
 use bevy_log::error;
 use bevy_math::UVec2;
+use bevy_platform::collections::HashSet;
 use bevy_render::extract_resource::ExtractResource;
 
 use crate::error::OxrError;
 use crate::graphics::*;
-use crate::layer_builder::{CompositionLayer, LayerProvider};
+use crate::layer_builder::{CompositionLayer, LayerProvider, ProjectionLayerExtension};
 use crate::session::{OxrSession, OxrSessionCreateNextChain};
 use crate::types::Result as OxrResult;
 use crate::types::*;
@@ -211,6 +215,77 @@ impl OxrFrameStream {
             }
         )
     }
+
+    /// Indicate that all graphics work for the frame has been submitted, including the layers of any secondary view configurations.
+    ///
+    /// `secondary_layers` must contain an entry for every secondary view configuration that was active for this frame.
+    /// Requires [`XR_MSFT_secondary_view_configuration`](https://www.khronos.org/registry/OpenXR/specs/1.0/html/xrspec.html#XR_MSFT_secondary_view_configuration).
+    pub fn end_secondary(
+        &mut self,
+        session: &OxrSession,
+        display_time: openxr::Time,
+        environment_blend_mode: openxr::EnvironmentBlendMode,
+        layers: &[&dyn CompositionLayer],
+        secondary_layers: &[(openxr::ViewConfigurationType, &[&dyn CompositionLayer])],
+    ) -> OxrResult<()> {
+        graphics_match!(
+            &mut self.0;
+            _stream => {
+                let headers = |layers: &[&dyn CompositionLayer]| {
+                    let mut headers = vec![];
+                    for (i, layer) in layers.iter().enumerate() {
+                        if let Some(swapchain) = layer.swapchain()
+                            && !swapchain.0.using_graphics::<Api>() {
+                            error!(
+                                "Composition layer {i} is using graphics api '{}', expected graphics api '{}'. Excluding layer from frame submission.",
+                                swapchain.0.graphics_name(),
+                                std::any::type_name::<Api>(),
+                            );
+                            continue;
+                        }
+                        headers.push(layer.header() as *const openxr::sys::CompositionLayerBaseHeader);
+                    }
+                    headers
+                };
+                let primary_headers = headers(layers);
+                let secondary_headers = secondary_layers
+                    .iter()
+                    .map(|(_, layers)| headers(layers))
+                    .collect::<Vec<_>>();
+                let secondary_infos = secondary_layers
+                    .iter()
+                    .zip(&secondary_headers)
+                    .map(|((ty, _), headers)| openxr::sys::SecondaryViewConfigurationLayerInfoMSFT {
+                        ty: openxr::sys::SecondaryViewConfigurationLayerInfoMSFT::TYPE,
+                        next: std::ptr::null(),
+                        view_configuration_type: *ty,
+                        environment_blend_mode,
+                        layer_count: headers.len() as u32,
+                        layers: headers.as_ptr(),
+                    })
+                    .collect::<Vec<_>>();
+                let secondary_info = openxr::sys::SecondaryViewConfigurationFrameEndInfoMSFT {
+                    ty: openxr::sys::SecondaryViewConfigurationFrameEndInfoMSFT::TYPE,
+                    next: std::ptr::null(),
+                    view_configuration_count: secondary_infos.len() as u32,
+                    view_configuration_layers_info: secondary_infos.as_ptr(),
+                };
+                let info = openxr::sys::FrameEndInfo {
+                    ty: openxr::sys::FrameEndInfo::TYPE,
+                    next: &secondary_info as *const _ as *const _,
+                    display_time,
+                    environment_blend_mode,
+                    layer_count: primary_headers.len() as u32,
+                    layers: primary_headers.as_ptr(),
+                };
+                let result = unsafe { (session.instance().fp().end_frame)(session.as_raw(), &info) };
+                if result.into_raw() < 0 {
+                    return Err(result.into());
+                }
+                Ok(())
+            }
+        )
+    }
 }
 
 /// Handle for waiting to render a frame.
@@ -219,6 +294,64 @@ impl OxrFrameStream {
 #[derive(Resource, Deref, DerefMut)]
 pub struct OxrFrameWaiter(pub openxr::FrameWaiter);
 
+impl OxrFrameWaiter {
+    /// Waits for the next frame and returns the state of each secondary view configuration in `secondary_tys`.
+    ///
+    /// Requires [`XR_MSFT_secondary_view_configuration`](https://www.khronos.org/registry/OpenXR/specs/1.0/html/xrspec.html#XR_MSFT_secondary_view_configuration).
+    /// Calls [`xrWaitFrame`](https://www.khronos.org/registry/OpenXR/specs/1.0/html/xrspec.html#xrWaitFrame) internally.
+    pub fn wait_secondary(
+        &mut self,
+        session: &OxrSession,
+        secondary_tys: &[openxr::ViewConfigurationType],
+    ) -> openxr::Result<(openxr::FrameState, Vec<OxrSecondaryViewState>)> {
+        let mut states = secondary_tys
+            .iter()
+            .map(|ty| openxr::sys::SecondaryViewConfigurationStateMSFT {
+                ty: openxr::sys::SecondaryViewConfigurationStateMSFT::TYPE,
+                next: std::ptr::null_mut(),
+                view_configuration_type: *ty,
+                active: false.into(),
+            })
+            .collect::<Vec<_>>();
+        let mut secondary_state = openxr::sys::SecondaryViewConfigurationFrameStateMSFT {
+            ty: openxr::sys::SecondaryViewConfigurationFrameStateMSFT::TYPE,
+            next: std::ptr::null_mut(),
+            view_configuration_count: states.len() as u32,
+            view_configuration_states: states.as_mut_ptr(),
+        };
+        let mut state = openxr::sys::FrameState {
+            ty: openxr::sys::FrameState::TYPE,
+            next: &mut secondary_state as *mut _ as *mut _,
+            predicted_display_time: openxr::Time::from_nanos(0),
+            predicted_display_period: openxr::Duration::from_nanos(0),
+            should_render: false.into(),
+        };
+        let info = openxr::sys::FrameWaitInfo {
+            ty: openxr::sys::FrameWaitInfo::TYPE,
+            next: std::ptr::null(),
+        };
+        let result =
+            unsafe { (session.instance().fp().wait_frame)(session.as_raw(), &info, &mut state) };
+        if result.into_raw() < 0 {
+            return Err(result);
+        }
+        Ok((
+            openxr::FrameState {
+                predicted_display_time: state.predicted_display_time,
+                predicted_display_period: state.predicted_display_period,
+                should_render: state.should_render.into(),
+            },
+            states
+                .iter()
+                .map(|state| OxrSecondaryViewState {
+                    view_configuration: state.view_configuration_type,
+                    active: state.active.into(),
+                })
+                .collect(),
+        ))
+    }
+}
+
 /// Graphics agnostic wrapper around [openxr::Swapchain]
 #[derive(Resource)]
 pub struct OxrSwapchain(pub GraphicsWrap<Self>);
@@ -234,6 +367,14 @@ impl OxrSwapchain {
         Self(G::wrap(swapchain))
     }
 
+    /// Returns the raw [`openxr::sys::Swapchain`] handle.
+    pub fn as_raw(&self) -> openxr::sys::Swapchain {
+        graphics_match!(
+            &self.0;
+            swap => swap.as_raw()
+        )
+    }
+
     /// Determine the index of the next image to render to in the swapchain image array.
     ///
     /// Calls [`acquire_image`](openxr::Swapchain::acquire_image) internally.
@@ -270,8 +411,7 @@ impl OxrSwapchain {
     pub fn enumerate_images(
         &self,
         device: &wgpu::Device,
-        format: wgpu::TextureFormat,
-        resolution: UVec2,
+        info: SwapchainCreateInfo,
     ) -> OxrResult<OxrSwapchainImages> {
         graphics_match!(
             &self.0;
@@ -279,18 +419,31 @@ impl OxrSwapchain {
                 let mut images = vec![];
                 for image in swap.enumerate_images()? {
                     unsafe {
-                        images.push(Api::to_wgpu_img(image, device, format, resolution)?);
+                        images.push(Api::to_wgpu_img(image, device, info)?);
                     }
                 }
-                Ok(OxrSwapchainImages(images.leak()))
+                Ok(OxrSwapchainImages(images.into()))
             }
         )
     }
 }
 
 /// Stores the generated swapchain images.
-#[derive(Debug, Deref, Resource, Clone, Copy, ExtractResource)]
-pub struct OxrSwapchainImages(pub &'static [wgpu::Texture]);
+///
+/// The images belong to the [`OxrSwapchain`] they were enumerated from, they must not be used after it was dropped.
+#[derive(Debug, Deref, Resource, Clone, ExtractResource)]
+pub struct OxrSwapchainImages(pub Arc<[wgpu::Texture]>);
+
+/// Index of the swapchain image acquired for the current frame, only present in the render world.
+#[derive(Debug, Deref, Resource, Clone, Copy, Default)]
+pub struct OxrSwapchainImageIndex(pub u32);
+
+/// XR texture views a camera renders to this frame, only present in the render world.
+///
+/// Layers use this to skip images that were acquired but not rendered to,
+/// e.g. when the main world predicted that the frame doesn't render.
+#[derive(Debug, Deref, Resource, Clone, Default)]
+pub struct OxrRenderedTextureViews(pub HashSet<ManualTextureViewHandle>);
 
 /// Stores the latest generated [OxrViews]
 #[derive(Clone, Resource, ExtractResource, Deref, DerefMut, Default)]
@@ -331,11 +484,120 @@ pub struct OxrPassthroughLayerFB(pub openxr::PassthroughLayerFB);
 #[derive(Resource, Deref, DerefMut, Default)]
 pub struct OxrRenderLayers(pub Vec<Box<dyn LayerProvider + Send + Sync>>);
 
+/// Extensions applied to the [`ProjectionLayer`](crate::layer_builder::ProjectionLayer) of the primary views, in order.
+#[derive(Resource, Deref, DerefMut, Default)]
+pub struct OxrProjectionLayerExtensions(pub Vec<Box<dyn ProjectionLayerExtension + Send + Sync>>);
+
+/// Layers submitted for secondary view configurations, keyed by the view configuration they belong to.
+///
+/// Layers are only submitted while their view configuration is active in [`OxrSecondaryViewStates`].
+#[derive(Resource, Deref, DerefMut, Default)]
+pub struct OxrSecondaryRenderLayers(
+    pub Vec<(
+        openxr::ViewConfigurationType,
+        Box<dyn LayerProvider + Send + Sync>,
+    )>,
+);
+
+/// The secondary view configurations enabled for the current session, along with their state for the current frame.
+///
+/// When this resource exists, the session is begun with these secondary view configurations enabled,
+/// see [`OxrSecondaryViewPlugin`](crate::features::secondary_view::OxrSecondaryViewPlugin).
+#[derive(Clone, Debug, Default, Resource, ExtractResource, Deref, DerefMut)]
+pub struct OxrSecondaryViewStates(pub Vec<OxrSecondaryViewState>);
+
+impl OxrSecondaryViewStates {
+    /// Returns the view configuration types of all enabled secondary view configurations.
+    pub fn view_configurations(&self) -> Vec<openxr::ViewConfigurationType> {
+        self.iter().map(|state| state.view_configuration).collect()
+    }
+
+    /// Returns `true` if the runtime wants `view_configuration` rendered this frame.
+    pub fn is_active(&self, view_configuration: openxr::ViewConfigurationType) -> bool {
+        self.iter()
+            .any(|state| state.view_configuration == view_configuration && state.active)
+    }
+}
+
+/// State of a single secondary view configuration.
+#[derive(Clone, Copy, Debug)]
+pub struct OxrSecondaryViewState {
+    pub view_configuration: openxr::ViewConfigurationType,
+    pub active: bool,
+}
+
 /// Resource storing graphics info for the currently running session.
-#[derive(Clone, Copy, Resource, ExtractResource)]
+#[derive(Clone, Resource, ExtractResource)]
 pub struct OxrCurrentSessionConfig {
+    /// The view configuration used by the session. There is one [`XrCamera`](bevy_mod_xr::camera::XrCamera) for each of its views.
+    pub view_configuration: openxr::ViewConfigurationType,
+    /// Resolution of the swapchain, large enough to fit every view.
     pub resolution: UVec2,
+    /// Resolution rendered for each view, in view order.
+    pub view_resolutions: Vec<UVec2>,
+    /// Why [`resolution`](Self::resolution) was picked.
+    pub resolution_reason: OxrResolutionReason,
     pub format: wgpu::TextureFormat,
+    /// The MSAA sample count used by the XR cameras.
+    pub sample_count: u32,
+    /// Whether the XR cameras render into an HDR intermediate target.
+    pub hdr: bool,
+    /// The usage flags the swapchain was created with.
+    pub swapchain_usage: SwapchainUsageFlags,
+}
+
+impl OxrCurrentSessionConfig {
+    /// Returns the number of views in the current view configuration.
+    pub fn view_count(&self) -> u32 {
+        self.view_resolutions.len() as u32
+    }
+
+    /// Returns the resolution rendered for the view with the given index.
+    pub fn view_resolution(&self, index: u32) -> UVec2 {
+        self.view_resolutions
+            .get(index as usize)
+            .copied()
+            .unwrap_or(self.resolution)
+    }
+}
+
+/// Describes why the swapchain resolution in [`OxrCurrentSessionConfig`] was picked.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OxrResolutionReason {
+    /// A resolution from [`OxrSessionConfig::resolutions`] matched the recommended resolution.
+    MatchedRecommended,
+    /// A resolution from [`OxrSessionConfig::resolutions`] fit within the maximum resolution.
+    FitsMax,
+    /// The recommended resolution was scaled by [`OxrSessionConfig::render_scale`].
+    RenderScale(f32),
+    /// The scaled recommended resolution was larger than the maximum resolution, so it was clamped.
+    ClampedToMax,
+    /// The resolution was requested with an [`OxrRecreateSwapchainMessage`](crate::render::OxrRecreateSwapchainMessage).
+    Recreated,
+}
+
+/// Scale applied to the swapchain resolution to get the area of the swapchain image the XR cameras render to.
+///
+/// This allows lowering the rendered resolution without recreating the swapchain.
+/// A scale of `1.0` renders to the full swapchain image.
+/// XR cameras with a viewport set by the user keep it, see [`OxrScaledViewport`](crate::render::OxrScaledViewport).
+#[derive(Clone, Copy, Debug, Deref, DerefMut, Resource, ExtractResource)]
+pub struct OxrResolutionScale(pub f32);
+
+impl Default for OxrResolutionScale {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+impl OxrResolutionScale {
+    /// Returns the size of the rendered area for a swapchain with the given resolution.
+    pub fn apply(&self, resolution: UVec2) -> UVec2 {
+        (resolution.as_vec2() * self.0.clamp(0.0, 1.0))
+            .round()
+            .as_uvec2()
+            .max(UVec2::ONE)
+    }
 }
 
 #[derive(Clone, Resource, Debug)]
@@ -343,17 +605,101 @@ pub struct OxrCurrentSessionConfig {
 pub struct OxrSessionConfig {
     /// List of blend modes the openxr session can use. If [None], pick the first available blend mode.
     pub blend_mode_preference: Vec<EnvironmentBlendMode>,
-    /// List of formats the openxr session can use. If [None], pick the first available format
+    /// List of view configurations the openxr session can use, in order of preference.
+    ///
+    /// Use [`PRIMARY_MONO`](openxr::ViewConfigurationType::PRIMARY_MONO) for handheld AR,
+    /// or [`PRIMARY_QUAD_VARJO`](openxr::ViewConfigurationType::PRIMARY_QUAD_VARJO) for quad view headsets, which requires the `XR_VARJO_quad_views` extension.
+    pub view_configuration_preference: Vec<openxr::ViewConfigurationType>,
+    /// List of formats the openxr session can use, in order of preference. If [None], pick the first available format
+    ///
+    /// Bevy writes the final image into the swapchain with a blit, so sRGB formats are encoded by the GPU
+    /// and every other format (UNORM, 10-bit, FP16) receives linear color, which is how the runtime interprets them.
     pub formats: Option<Vec<wgpu::TextureFormat>>,
-    /// List of resolutions that the openxr swapchain can use. If [None] pick the first available resolution.
+    /// List of resolutions that the openxr swapchain can use, in order of preference. If [None] the resolution is picked using [`render_scale`](Self::render_scale).
     pub resolutions: Option<Vec<UVec2>>,
+    /// Supersampling factor applied to the recommended resolution of every view. The result is clamped to the maximum resolution.
+    pub render_scale: f32,
+    /// The MSAA sample count wanted for the XR cameras.
+    ///
+    /// This is clamped to the runtime's `max_swapchain_sample_count` and rounded down to a sample count supported by [`Msaa`](bevy_render::view::Msaa).
+    /// Bevy renders into its own multisampled target and resolves into the single sampled swapchain image.
+    pub sample_count: u32,
+    /// Renders the XR cameras into an HDR intermediate target, which is tonemapped before it is written to the swapchain.
+    ///
+    /// Required for bloom and other HDR post processing, and to make use of 10-bit and FP16 swapchains.
+    pub hdr: bool,
+    /// Usage flags of the swapchain used by the XR views.
+    ///
+    /// `COLOR_ATTACHMENT` is always added. Flags that wgpu can't support for the selected format are removed,
+    /// and the swapchain falls back to `COLOR_ATTACHMENT | SAMPLED | TRANSFER_DST` if the runtime rejects the remaining flags.
+    /// `TRANSFER_DST` lets bevy copy into the swapchain, `TRANSFER_SRC` is needed by the spectator window and screenshots, `UNORDERED_ACCESS` by compute passes
+    /// writing to the swapchain and `INPUT_ATTACHMENT` requires `XR_KHR_swapchain_usage_input_attachment_bit`.
+    pub swapchain_usage: SwapchainUsageFlags,
 }
 impl Default for OxrSessionConfig {
     fn default() -> Self {
         Self {
             blend_mode_preference: vec![openxr::EnvironmentBlendMode::OPAQUE],
-            formats: Some(vec![wgpu::TextureFormat::Rgba8UnormSrgb]),
+            view_configuration_preference: vec![openxr::ViewConfigurationType::PRIMARY_STEREO],
+            formats: Some(vec![
+                wgpu::TextureFormat::Rgba8UnormSrgb,
+                wgpu::TextureFormat::Bgra8UnormSrgb,
+            ]),
             resolutions: None,
+            render_scale: 1.0,
+            sample_count: 4,
+            hdr: false,
+            swapchain_usage: SwapchainUsageFlags::COLOR_ATTACHMENT
+                | SwapchainUsageFlags::SAMPLED
+                | SwapchainUsageFlags::TRANSFER_SRC
+                | SwapchainUsageFlags::TRANSFER_DST,
+        }
+    }
+}
+
+impl OxrSessionConfig {
+    /// Preset for standalone headsets, where the GPU is usually the bottleneck.
+    ///
+    /// Renders below the recommended resolution and keeps 4x MSAA, which tile based mobile GPUs resolve on chip.
+    pub fn standalone() -> Self {
+        Self {
+            render_scale: 0.85,
+            sample_count: 4,
+            ..Default::default()
+        }
+    }
+
+    /// Preset for headsets driven by a desktop GPU, which supersamples the recommended resolution.
+    pub fn desktop() -> Self {
+        Self {
+            render_scale: 1.2,
+            sample_count: 4,
+            ..Default::default()
+        }
+    }
+
+    /// Enables [`hdr`](Self::hdr) and prefers FP16 and 10-bit swapchain formats over the current [`formats`](Self::formats).
+    pub fn with_hdr(mut self) -> Self {
+        let mut formats = vec![
+            wgpu::TextureFormat::Rgba16Float,
+            wgpu::TextureFormat::Rgb10a2Unorm,
+        ];
+        for format in self.formats.unwrap_or_default() {
+            if !formats.contains(&format) {
+                formats.push(format);
+            }
+        }
+        self.formats = Some(formats);
+        self.hdr = true;
+        self
+    }
+
+    /// Picks the [`standalone`](Self::standalone) preset on android and the [`desktop`](Self::desktop) preset everywhere else.
+    pub fn platform_preset() -> Self {
+        if cfg!(target_os = "android") {
+            Self::standalone()
+        } else {
+            Self::desktop()
         }
     }
 }
@@ -374,6 +720,14 @@ pub struct OxrSessionStarted(pub bool);
 #[derive(Clone, Deref, DerefMut, Resource, ExtractResource)]
 pub struct OxrFrameState(pub openxr::FrameState);
 
+impl OxrFrameState {
+    /// Returns whether at least one display period was skipped since the frame displayed at `last_display_time`.
+    pub fn missed_frame_since(&self, last_display_time: openxr::Time) -> bool {
+        let period = self.predicted_display_period.as_nanos();
+        self.predicted_display_time.as_nanos() - last_display_time.as_nanos() > period * 3 / 2
+    }
+}
+
 /// Instructs systems to add display period
 #[derive(Clone, Copy, Default, Resource)]
 pub struct Pipelined;
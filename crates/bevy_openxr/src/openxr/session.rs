@@ -1,11 +1,12 @@
 use std::ffi::c_void;
+use std::ptr;
 
 use crate::next_chain::{OxrNextChain, OxrNextChainStructBase, OxrNextChainStructProvider};
 use crate::resources::{OxrPassthrough, OxrPassthroughLayerFB, OxrSwapchain};
 use crate::types::{Result, SwapchainCreateInfo};
 use bevy_derive::Deref;
 use bevy_ecs::resource::Resource;
-use openxr::AnyGraphics;
+use openxr::{AnyGraphics, sys};
 
 use crate::graphics::{graphics_match, GraphicsExt, GraphicsType, GraphicsWrap};
 
@@ -67,6 +68,71 @@ impl OxrSession {
         )))
     }
 
+    /// Creates an [OxrSwapchain] with `XrSwapchainCreateInfoFoveationFB` chained, so foveation profiles can be applied to it.
+    ///
+    /// Requires [`XR_FB_foveation`](https://www.khronos.org/registry/OpenXR/specs/1.0/html/xrspec.html#XR_FB_foveation).
+    ///
+    /// Calls [`xrCreateSwapchain`](https://www.khronos.org/registry/OpenXR/specs/1.0/html/xrspec.html#xrCreateSwapchain) internally.
+    pub fn create_foveated_swapchain(
+        &self,
+        info: SwapchainCreateInfo,
+        flags: sys::SwapchainCreateFoveationFlagsFB,
+    ) -> Result<OxrSwapchain> {
+        Ok(OxrSwapchain(graphics_match!(
+            &self.1;
+            session => {
+                let info: openxr::SwapchainCreateInfo<Api> = info.try_into()?;
+                let foveation_info = sys::SwapchainCreateInfoFoveationFB {
+                    ty: sys::SwapchainCreateInfoFoveationFB::TYPE,
+                    next: ptr::null_mut(),
+                    flags,
+                };
+                let raw_info = sys::SwapchainCreateInfo {
+                    ty: sys::SwapchainCreateInfo::TYPE,
+                    next: &foveation_info as *const _ as *const c_void,
+                    create_flags: info.create_flags,
+                    usage_flags: info.usage_flags,
+                    format: <Api as openxr::Graphics>::lower_format(info.format),
+                    sample_count: info.sample_count,
+                    width: info.width,
+                    height: info.height,
+                    face_count: info.face_count,
+                    array_size: info.array_size,
+                    mip_count: info.mip_count,
+                };
+                let mut handle = sys::Swapchain::NULL;
+                unsafe {
+                    cvt((self.instance().fp().create_swapchain)(self.as_raw(), &raw_info, &mut handle))?;
+                    openxr::Swapchain::<Api>::from_raw(session.clone(), handle)
+                }
+            } => OxrSwapchain
+        )))
+    }
+
+    /// Begins the session with secondary view configurations enabled.
+    ///
+    /// Requires [`XR_MSFT_secondary_view_configuration`](https://www.khronos.org/registry/OpenXR/specs/1.0/html/xrspec.html#XR_MSFT_secondary_view_configuration).
+    ///
+    /// Calls [`xrBeginSession`](https://www.khronos.org/registry/OpenXR/specs/1.0/html/xrspec.html#xrBeginSession) internally.
+    pub fn begin_with_secondary(
+        &self,
+        ty: openxr::ViewConfigurationType,
+        secondary_tys: &[openxr::ViewConfigurationType],
+    ) -> openxr::Result<sys::Result> {
+        let secondary_info = sys::SecondaryViewConfigurationSessionBeginInfoMSFT {
+            ty: sys::SecondaryViewConfigurationSessionBeginInfoMSFT::TYPE,
+            next: ptr::null(),
+            view_configuration_count: secondary_tys.len() as u32,
+            enabled_view_configuration_types: secondary_tys.as_ptr(),
+        };
+        let info = sys::SessionBeginInfo {
+            ty: sys::SessionBeginInfo::TYPE,
+            next: &secondary_info as *const _ as *const _,
+            primary_view_configuration_type: ty,
+        };
+        unsafe { cvt((self.instance().fp().begin_session)(self.as_raw(), &info)) }
+    }
+
     /// Creates a passthrough.
     ///
     /// Requires [`XR_FB_passthrough`](https://www.khronos.org/registry/OpenXR/specs/1.0/html/xrspec.html#XR_FB_passthrough).
@@ -116,3 +182,7 @@ impl OxrSessionCreateNextChain {
         self.0.chain_pointer()
     }
 }
+
+fn cvt(x: sys::Result) -> openxr::Result<sys::Result> {
+    if x.into_raw() >= 0 { Ok(x) } else { Err(x) }
+}
@@ -306,6 +306,15 @@ pub fn locate_hand_joints(
     tracker: &openxr::HandTracker,
     base: &XrSpace,
     time: openxr::Time,
+) -> openxr::Result<Option<HandJointLocations>> {
+    locate_raw_hand_joints(instance, tracker.as_raw(), base, time)
+}
+/// Same as [`locate_hand_joints`], for a hand tracker owned by someone else, e.g. in the main world.
+pub fn locate_raw_hand_joints(
+    instance: &openxr::Instance,
+    tracker: sys::HandTrackerEXT,
+    base: &XrSpace,
+    time: openxr::Time,
 ) -> openxr::Result<Option<HandJointLocations>> {
     unsafe {
         let locate_info = sys::HandJointsLocateInfoEXT {
@@ -329,7 +338,7 @@ pub fn locate_hand_joints(
             .as_ref()
             .expect("Somehow created HandTracker without XR_EXT_hand_tracking being enabled")
             .locate_hand_joints)(
-            tracker.as_raw(),
+            tracker,
             &locate_info,
             &mut location_info,
         ))?;
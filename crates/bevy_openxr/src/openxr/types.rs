@@ -69,6 +69,45 @@ pub struct SwapchainCreateInfo {
     pub mip_count: u32,
 }
 
+impl SwapchainCreateInfo {
+    /// The usages of the swapchain images, for the wgpu-hal and the wgpu texture descriptors.
+    ///
+    /// `INPUT_ATTACHMENT` and `MUTABLE_FORMAT` have no wgpu equivalent and are only passed to the runtime.
+    pub fn texture_usages(&self) -> (wgpu::TextureUses, wgpu::TextureUsages) {
+        let mut hal_usage = wgpu::TextureUses::empty();
+        let mut usage = wgpu::TextureUsages::empty();
+        let flags = self.usage_flags;
+        if flags.contains(SwapchainUsageFlags::COLOR_ATTACHMENT) {
+            hal_usage |= wgpu::TextureUses::COLOR_TARGET;
+            usage |= wgpu::TextureUsages::RENDER_ATTACHMENT;
+        }
+        if flags.contains(SwapchainUsageFlags::DEPTH_STENCIL_ATTACHMENT) {
+            hal_usage |=
+                wgpu::TextureUses::DEPTH_STENCIL_READ | wgpu::TextureUses::DEPTH_STENCIL_WRITE;
+            usage |= wgpu::TextureUsages::RENDER_ATTACHMENT;
+        }
+        if flags.contains(SwapchainUsageFlags::SAMPLED) {
+            hal_usage |= wgpu::TextureUses::RESOURCE;
+            usage |= wgpu::TextureUsages::TEXTURE_BINDING;
+        }
+        if flags.contains(SwapchainUsageFlags::UNORDERED_ACCESS) {
+            hal_usage |= wgpu::TextureUses::STORAGE_READ_ONLY
+                | wgpu::TextureUses::STORAGE_WRITE_ONLY
+                | wgpu::TextureUses::STORAGE_READ_WRITE;
+            usage |= wgpu::TextureUsages::STORAGE_BINDING;
+        }
+        if flags.contains(SwapchainUsageFlags::TRANSFER_SRC) {
+            hal_usage |= wgpu::TextureUses::COPY_SRC;
+            usage |= wgpu::TextureUsages::COPY_SRC;
+        }
+        if flags.contains(SwapchainUsageFlags::TRANSFER_DST) {
+            hal_usage |= wgpu::TextureUses::COPY_DST;
+            usage |= wgpu::TextureUsages::COPY_DST;
+        }
+        (hal_usage, usage)
+    }
+}
+
 impl<G: GraphicsExt> TryFrom<SwapchainCreateInfo> for openxr::SwapchainCreateInfo<G> {
     type Error = OxrError;
 
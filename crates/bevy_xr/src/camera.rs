@@ -1,15 +1,45 @@
-use core::panic;
-
-use bevy_app::{App, Plugin};
-use bevy_camera::{Camera3d, CameraProjection};
-use bevy_ecs::{component::Component, schedule::SystemSet};
-use bevy_math::{Mat4, Vec3A, Vec4};
+use core::{ops::Range, panic};
+
+use bevy_app::{App, Plugin, PostUpdate};
+use bevy_camera::{
+    primitives::{Frustum, HalfSpace},
+    visibility::{RenderLayers, VisibilitySystems},
+    Camera3d, CameraProjection, Projection,
+};
+use bevy_ecs::{
+    component::Component,
+    entity::Entity,
+    lifecycle::HookContext,
+    query::ROQueryItem,
+    resource::Resource,
+    schedule::{IntoScheduleConfigs as _, SystemSet},
+    system::{Commands, Query, Res, SystemParamItem, lifetimeless::Read},
+    world::DeferredWorld,
+};
+use bevy_log::warn;
+use bevy_math::{Mat4, Vec3, Vec3A, Vec4};
 // use bevy::prelude::SystemSet;
 #[cfg(feature = "reflect")]
 use bevy_reflect::std_traits::ReflectDefault;
 #[cfg(feature = "reflect")]
 use bevy_reflect::Reflect;
-use bevy_render::extract_component::{ExtractComponent, ExtractComponentPlugin};
+use bevy_render::{
+    Render, RenderApp, RenderSystems,
+    extract_component::{
+        ComponentUniforms, DynamicUniformIndex, ExtractComponent, ExtractComponentPlugin,
+        UniformComponentPlugin,
+    },
+    render_phase::{PhaseItem, RenderCommand, RenderCommandResult, TrackedRenderPass},
+    render_resource::{
+        BindGroup, BindGroupEntries, BindGroupLayoutDescriptor, BindGroupLayoutEntries,
+        BufferBinding, BufferId, PipelineCache, ShaderStages, ShaderType,
+        binding_types::uniform_buffer,
+    },
+    renderer::RenderDevice,
+};
+use bevy_shader::load_shader_library;
+
+use bevy_transform::components::GlobalTransform;
 
 use crate::session::XrTracker;
 
@@ -17,7 +47,25 @@ pub struct XrCameraPlugin;
 
 impl Plugin for XrCameraPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(ExtractComponentPlugin::<XrCamera>::default());
+        load_shader_library!(app, "view_uniform.wgsl");
+        app.add_plugins((
+            ExtractComponentPlugin::<XrCamera>::default(),
+            ExtractComponentPlugin::<XrClipPlanes>::default(),
+            ExtractComponentPlugin::<XrViewUniform>::default(),
+            UniformComponentPlugin::<XrViewUniform>::default(),
+        ))
+            .add_systems(
+                PostUpdate,
+                add_view_render_layers.before(VisibilitySystems::CheckVisibility),
+            );
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app
+                .insert_resource(XrViewBindGroupLayout(xr_view_bind_group_layout()))
+                .add_systems(
+                    Render,
+                    prepare_xr_view_bind_groups.in_set(RenderSystems::PrepareBindGroups),
+                );
+        }
     }
 }
 
@@ -30,28 +78,319 @@ pub struct XrViewInit;
 pub struct XrProjection {
     pub projection_matrix: Mat4,
     pub near: f32,
+    /// The far plane of the projection, [`None`] for an infinite projection.
+    pub far: Option<f32>,
 }
 
 impl Default for XrProjection {
     fn default() -> Self {
         Self {
             near: 0.1,
+            far: None,
             projection_matrix: Mat4::IDENTITY,
         }
     }
 }
 
+/// The near and far plane of an [`XrCamera`].
+///
+/// The backend builds the [`XrProjection`] of the camera from these every frame,
+/// they are also used for frustum culling and when submitting depth to the runtime.
+#[derive(Clone, Copy, Component, ExtractComponent, Debug, PartialEq)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+#[cfg_attr(feature = "reflect", reflect(Default))]
+pub struct XrClipPlanes {
+    pub near: f32,
+    /// The far plane, [`None`] uses an infinite projection.
+    pub far: Option<f32>,
+}
+
+impl Default for XrClipPlanes {
+    fn default() -> Self {
+        Self {
+            near: 0.1,
+            far: None,
+        }
+    }
+}
+
+impl XrClipPlanes {
+    /// Clip planes with a far plane at infinity.
+    pub const fn infinite(near: f32) -> Self {
+        Self { near, far: None }
+    }
+
+    /// Clip planes with a finite far plane, nothing beyond it is rendered.
+    pub const fn finite(near: f32, far: f32) -> Self {
+        Self {
+            near,
+            far: Some(far),
+        }
+    }
+}
+
 /// Marker component for an XR view. It is the backends responsibility to update this.
+///
+/// The backend spawns one camera per view, more cameras following a view can be added with [`XrAdditionalCamera`].
 #[derive(Clone, Copy, Component, ExtractComponent, Debug, Default)]
-#[require(Camera3d, XrTracker)]
+#[require(
+    Camera3d,
+    XrTracker,
+    XrViewUniform,
+    XrClipPlanes,
+    Projection = Projection::custom(XrProjection::default())
+)]
 pub struct XrCamera(pub u32);
 
+/// Marks an [`XrCamera`] as an additional camera of its view, spawned next to the camera of the backend.
+///
+/// It follows the view pose and renders into the same swapchain layer, the backend sets its render target,
+/// viewport, [`Msaa`](bevy_render::view::Msaa) and HDR. Everything else is up to the user,
+/// for example a higher [`Camera::order`](bevy_camera::Camera::order) with
+/// [`ClearColorConfig::None`](bevy_camera::ClearColorConfig::None) to draw on top of the view,
+/// its own [`RenderLayers`] or [`XrClipPlanes`] with a different near plane.
+/// Spawn one for every view to get a stereo rig.
+#[derive(Clone, Copy, Component, Debug, PartialEq, Eq)]
+pub struct XrAdditionalCamera {
+    /// Whether the camera renders, it never renders when the backend skips a frame.
+    pub enabled: bool,
+}
+
+impl Default for XrAdditionalCamera {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+/// Per view data of an [`XrCamera`], updated by the backend every frame.
+///
+/// In the render world this is uploaded into [`ComponentUniforms<XrViewUniform>`],
+/// every view entity gets an [`XrViewBindGroup`] with its uniform, matching [`XrViewBindGroupLayout`].
+/// The matching WGSL struct can be imported from `bevy_mod_xr::view_uniform`.
+#[derive(Clone, Copy, Component, ExtractComponent, ShaderType, Debug, Default, PartialEq)]
+pub struct XrViewUniform {
+    /// The left, right, down and up angles of the field of view in radians.
+    pub fov: Vec4,
+    /// Orientation of the view relative to the tracking root, as a quaternion.
+    pub orientation: Vec4,
+    /// Position of the view relative to the tracking root.
+    pub position: Vec3,
+    /// Index of the view, the left eye is `0` in stereo view configurations.
+    pub index: u32,
+    /// Distance between the first two views in meters, `0` if there is only one view.
+    pub ipd: f32,
+    /// Predicted display time of the frame in seconds.
+    ///
+    /// Wraps every hour to keep the precision, like the time in bevy's globals uniform.
+    pub predicted_display_time: f32,
+}
+
+/// Layout of the [`XrViewBindGroup`] of every view, a single [`XrViewUniform`] at binding `0`.
+///
+/// In WGSL this is `@group(N) @binding(0) var<uniform> xr_view: XrViewUniform;`.
+#[derive(Resource, Clone, Debug)]
+pub struct XrViewBindGroupLayout(pub BindGroupLayoutDescriptor);
+
+/// The layout of [`XrViewBindGroupLayout`], for pipelines that are specialized without access to the render world.
+pub fn xr_view_bind_group_layout() -> BindGroupLayoutDescriptor {
+    BindGroupLayoutDescriptor::new(
+        "xr_view_bind_group_layout",
+        &BindGroupLayoutEntries::single(
+            ShaderStages::VERTEX_FRAGMENT,
+            uniform_buffer::<XrViewUniform>(false),
+        ),
+    )
+}
+
+/// Bind group with the [`XrViewUniform`] of a view, on the view entities in the render world.
+///
+/// Post processing and other per view passes can bind it directly, draw functions can use [`SetXrViewBindGroup`].
+#[derive(Component, Clone, Debug)]
+pub struct XrViewBindGroup {
+    pub bind_group: BindGroup,
+    /// The uniform buffer and offset the bind group was created for.
+    binding: (BufferId, u32),
+}
+
+fn prepare_xr_view_bind_groups(
+    render_device: Res<RenderDevice>,
+    pipeline_cache: Res<PipelineCache>,
+    layout: Res<XrViewBindGroupLayout>,
+    uniforms: Res<ComponentUniforms<XrViewUniform>>,
+    views: Query<(
+        Entity,
+        &DynamicUniformIndex<XrViewUniform>,
+        Option<&XrViewBindGroup>,
+    )>,
+    mut commands: Commands,
+) {
+    let Some(buffer) = uniforms.uniforms().buffer() else {
+        return;
+    };
+    for (entity, index, bind_group) in &views {
+        let binding = (buffer.id(), index.index());
+        if bind_group.is_some_and(|bind_group| bind_group.binding == binding) {
+            continue;
+        }
+        let bind_group = render_device.create_bind_group(
+            "xr_view_bind_group",
+            &pipeline_cache.get_bind_group_layout(&layout.0),
+            &BindGroupEntries::single(BufferBinding {
+                buffer,
+                offset: index.index() as u64,
+                size: Some(XrViewUniform::min_size()),
+            }),
+        );
+        commands.entity(entity).insert(XrViewBindGroup {
+            bind_group,
+            binding,
+        });
+    }
+}
+
+/// Sets the [`XrViewBindGroup`] of the view at bind group `I`.
+///
+/// Items are skipped in views that aren't XR views, e.g. shadow views.
+pub struct SetXrViewBindGroup<const I: usize>;
+
+impl<P: PhaseItem, const I: usize> RenderCommand<P> for SetXrViewBindGroup<I> {
+    type Param = ();
+    type ViewQuery = Option<Read<XrViewBindGroup>>;
+    type ItemQuery = ();
+
+    #[inline]
+    fn render<'w>(
+        _item: &P,
+        bind_group: ROQueryItem<'w, '_, Self::ViewQuery>,
+        _entity: Option<()>,
+        _: SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let Some(bind_group) = bind_group else {
+            return RenderCommandResult::Skip;
+        };
+        pass.set_bind_group(I, &bind_group.bind_group, &[]);
+        RenderCommandResult::Success
+    }
+}
+
+/// The most views the reserved render layers have room for.
+///
+/// OpenXR view configurations have at most four views, the rest is left for future view configurations.
+pub const XR_MAX_VIEWS: u32 = 8;
+
+/// The render layer reserved for the first XR view, view `n` uses `XR_VIEW_RENDER_LAYER + n`.
+///
+/// Every [`XrCamera`] renders its reserved layer in addition to its own [`RenderLayers`].
+pub const XR_VIEW_RENDER_LAYER: usize = 24;
+
+/// The render layers reserved for the XR views, don't use these for anything else.
+pub const XR_VIEW_RENDER_LAYERS: Range<usize> =
+    XR_VIEW_RENDER_LAYER..XR_VIEW_RENDER_LAYER + XR_MAX_VIEWS as usize;
+
+/// Makes an entity only visible to the [`XrCamera`] with the same view index.
+///
+/// This replaces the [`RenderLayers`] of the entity with the layer reserved for that view,
+/// removing the component restores the previous [`RenderLayers`].
+/// Useful for eye specific debug content, materials can also read the view index from the [`XrViewUniform`].
+#[derive(Clone, Copy, Component, Debug, PartialEq, Eq, Hash)]
+#[component(on_insert = on_view_visibility_insert, on_remove = on_view_visibility_remove)]
+pub struct XrViewVisibility(pub u32);
+
+impl XrViewVisibility {
+    /// The left eye in a stereo view configuration.
+    pub const LEFT: Self = Self(0);
+    /// The right eye in a stereo view configuration.
+    pub const RIGHT: Self = Self(1);
+
+    /// The render layer reserved for this view, `None` if the index is not below [`XR_MAX_VIEWS`].
+    pub const fn render_layer(&self) -> Option<usize> {
+        if self.0 < XR_MAX_VIEWS {
+            Some(XR_VIEW_RENDER_LAYER + self.0 as usize)
+        } else {
+            None
+        }
+    }
+}
+
+/// The [`RenderLayers`] an entity had before [`XrViewVisibility`] was inserted.
+#[derive(Clone, Component, Debug)]
+struct XrViewVisibilityPreviousLayers(Option<RenderLayers>);
+
+fn on_view_visibility_insert(mut world: DeferredWorld, HookContext { entity, .. }: HookContext) {
+    let Some(visibility) = world.get::<XrViewVisibility>(entity).copied() else {
+        return;
+    };
+    let Some(layer) = visibility.render_layer() else {
+        warn!(
+            "XrViewVisibility({}) is out of range, there are only {XR_MAX_VIEWS} reserved view layers",
+            visibility.0
+        );
+        return;
+    };
+    // replacing the component keeps the layers from before the first insert
+    let previous = if world.entity(entity).contains::<XrViewVisibilityPreviousLayers>() {
+        None
+    } else {
+        Some(XrViewVisibilityPreviousLayers(
+            world.get::<RenderLayers>(entity).cloned(),
+        ))
+    };
+    let mut commands = world.commands();
+    let mut entity = commands.entity(entity);
+    if let Some(previous) = previous {
+        entity.try_insert(previous);
+    }
+    entity.try_insert(RenderLayers::layer(layer));
+}
+
+fn on_view_visibility_remove(mut world: DeferredWorld, HookContext { entity, .. }: HookContext) {
+    let Some(XrViewVisibilityPreviousLayers(previous)) =
+        world.get::<XrViewVisibilityPreviousLayers>(entity).cloned()
+    else {
+        return;
+    };
+    let mut commands = world.commands();
+    let mut entity = commands.entity(entity);
+    entity.try_remove::<XrViewVisibilityPreviousLayers>();
+    match previous {
+        Some(layers) => entity.try_insert(layers),
+        None => entity.try_remove::<RenderLayers>(),
+    };
+}
+
+/// Adds the reserved view layer to every [`XrCamera`], keeping any layers set by the user.
+fn add_view_render_layers(
+    cameras: Query<(Entity, &XrCamera, Option<&RenderLayers>)>,
+    mut commands: Commands,
+) {
+    for (entity, camera, layers) in &cameras {
+        let Some(layer) = XrViewVisibility(camera.0).render_layer() else {
+            continue;
+        };
+        let layers = layers.cloned().unwrap_or_default();
+        if layers.iter().any(|l| l == layer) {
+            continue;
+        }
+        commands.entity(entity).insert(layers.with(layer));
+    }
+}
+
 impl CameraProjection for XrProjection {
     fn update(&mut self, _width: f32, _height: f32) {}
 
     fn far(&self) -> f32 {
-        self.projection_matrix.to_cols_array()[14]
-            / (self.projection_matrix.to_cols_array()[10] + 1.0)
+        self.far.unwrap_or(f32::INFINITY)
+    }
+
+    fn compute_frustum(&self, camera_transform: &GlobalTransform) -> Frustum {
+        let clip_from_world = self.get_clip_from_view() * camera_transform.affine().inverse();
+        let mut frustum = Frustum::from_clip_from_world(&clip_from_world);
+        if self.far.is_none() {
+            // an infinite projection has no far plane, use one that contains everything
+            frustum.half_spaces[5] = HalfSpace::new(Vec4::new(0.0, 0.0, 1.0, f32::INFINITY));
+        }
+        frustum
     }
 
     fn get_frustum_corners(&self, z_near: f32, z_far: f32) -> [Vec3A; 8] {
@@ -99,16 +438,17 @@ pub struct Fov {
     pub angle_up: f32,
 }
 
-/// Calculates an asymmetrical perspective projection matrix for XR rendering. This API is for internal use only.
+/// Calculates an asymmetrical reverse-Z perspective projection matrix for XR rendering,
+/// `far_z` of [`None`] places the far plane at infinity. This API is for internal use only.
 #[doc(hidden)]
-pub fn calculate_projection(near_z: f32, fov: Fov) -> Mat4 {
+pub fn calculate_projection(near_z: f32, far_z: Option<f32>, fov: Fov) -> Mat4 {
     //  symmetric perspective for debugging
     // let x_fov = (self.fov.angle_left.abs() + self.fov.angle_right.abs());
     // let y_fov = (self.fov.angle_up.abs() + self.fov.angle_down.abs());
     // return Mat4::perspective_infinite_reverse_rh(y_fov, x_fov / y_fov, self.near);
 
-    let far_z = -1.; //   use infinite proj
-                     // let far_z = self.far;
+    //  a far plane in front of the near plane means an infinite projection
+    let far_z = far_z.unwrap_or(-1.);
 
     let tan_angle_left = fov.angle_left.tan();
     let tan_angle_right = fov.angle_right.tan();
@@ -155,17 +495,6 @@ pub fn calculate_projection(near_z: f32, fov: Fov) -> Mat4 {
         cols[7] = 0.;
         cols[11] = -1.;
         cols[15] = 0.;
-
-        //  bevy uses the _reverse_ infinite projection
-        //  https://dev.theomader.com/depth-precision/
-        let z_reversal = Mat4::from_cols_array_2d(&[
-            [1f32, 0., 0., 0.],
-            [0., 1., 0., 0.],
-            [0., 0., -1., 0.],
-            [0., 0., 1., 1.],
-        ]);
-
-        return z_reversal * Mat4::from_cols_array(&cols);
     } else {
         // normal projection
         cols[0] = 2. / tan_angle_width;
@@ -189,15 +518,25 @@ pub fn calculate_projection(near_z: f32, fov: Fov) -> Mat4 {
         cols[15] = 0.;
     }
 
-    Mat4::from_cols_array(&cols)
+    //  bevy uses a _reverse_ projection, the near plane maps to 1 and the far plane to 0
+    //  https://dev.theomader.com/depth-precision/
+    let z_reversal = Mat4::from_cols_array_2d(&[
+        [1f32, 0., 0., 0.],
+        [0., 1., 0., 0.],
+        [0., 0., -1., 0.],
+        [0., 0., 1., 1.],
+    ]);
+
+    z_reversal * Mat4::from_cols_array(&cols)
 }
 
 #[cfg(test)]
 mod tests {
     use std::f32::{self, consts::PI};
 
-    use bevy_math::{Mat4,Vec3A};
-    use bevy_camera::{CameraProjection, PerspectiveProjection};
+    use bevy_math::{Mat4, Vec2, Vec3, Vec3A};
+    use bevy_camera::{primitives::Sphere, CameraProjection, PerspectiveProjection};
+    use bevy_transform::components::GlobalTransform;
 
     const TEST_VALUES: &[(f32, f32)] = &[(0.5, 100.0), (50.0, 200.0)];
 
@@ -217,12 +556,104 @@ mod tests {
 
         let near = 0.1;
 
-        let matrix = super::calculate_projection(near, fov);
+        let matrix = super::calculate_projection(near, None, fov);
         let control = Mat4::perspective_infinite_reverse_rh(2. * half_fov_y, aspect, near);
 
         assert_eq!(matrix, control);
     }
 
+    /// Test that calculate_projection works correctly for symmetrical FOV parameters with a finite far plane,
+    /// by comparing against glam.
+    #[test]
+    fn test_calculate_symmetrical_far_plane() {
+        let half_fov_y = PI * 0.25;
+        let aspect = 1.;
+        let fov = super::Fov {
+            angle_left: -half_fov_y * aspect,
+            angle_right: half_fov_y * aspect,
+            angle_down: -half_fov_y,
+            angle_up: half_fov_y,
+        };
+
+        for (near, far) in TEST_VALUES {
+            let matrix = super::calculate_projection(*near, Some(*far), fov);
+            // Invert far and near plane to create reverse-Z far-plane perspective matrix.
+            let control = Mat4::perspective_rh(2. * half_fov_y, aspect, *far, *near);
+
+            assert!(matrix.abs_diff_eq(control, TOLERANCE));
+        }
+    }
+
+    /// Test that calculate_projection maps the edges of an asymmetrical FOV to the edges of clip space,
+    /// and the near and far plane to a reverse-Z depth of 1 and 0.
+    #[test]
+    fn test_calculate_asymmetrical_clip_planes() {
+        let fov = super::Fov {
+            angle_left: -PI * 0.33,
+            angle_right: PI * 0.25,
+            angle_down: -PI * 0.3,
+            angle_up: PI * 0.2,
+        };
+
+        for (near, far) in TEST_VALUES {
+            let matrix = super::calculate_projection(*near, Some(*far), fov);
+            let infinite = super::calculate_projection(*near, None, fov);
+
+            for z in [*near, *far] {
+                let bottom_left = Vec3::new(fov.angle_left.tan() * z, fov.angle_down.tan() * z, -z);
+                let top_right = Vec3::new(fov.angle_right.tan() * z, fov.angle_up.tan() * z, -z);
+                let depth = if z == *near { 1. } else { 0. };
+
+                let ndc = matrix.project_point3(bottom_left);
+                assert!(ndc.abs_diff_eq(Vec3::new(-1., -1., depth), TOLERANCE));
+                let ndc = matrix.project_point3(top_right);
+                assert!(ndc.abs_diff_eq(Vec3::new(1., 1., depth), TOLERANCE));
+
+                // the infinite projection only reaches a depth of 0 at infinity
+                let ndc = infinite.project_point3(top_right);
+                assert!(ndc.truncate().abs_diff_eq(Vec2::ONE, TOLERANCE));
+                assert!((ndc.z - near / z).abs() < TOLERANCE);
+            }
+        }
+    }
+
+    /// Test that the frustum of an XrProjection only culls beyond the far plane when there is one.
+    #[test]
+    fn test_compute_frustum_far_plane() {
+        let fov = super::Fov {
+            angle_left: -PI * 0.33,
+            angle_right: PI * 0.25,
+            angle_down: -PI * 0.25,
+            angle_up: PI * 0.25,
+        };
+        let (near, far) = (0.1, 100.);
+        let sphere = |z: f32| Sphere {
+            center: Vec3A::new(0., 0., -z),
+            radius: 0.5,
+        };
+
+        let finite = XrProjection {
+            near,
+            far: Some(far),
+            projection_matrix: super::calculate_projection(near, Some(far), fov),
+        };
+        let frustum = finite.compute_frustum(&GlobalTransform::IDENTITY);
+        assert_eq!(finite.far(), far);
+        assert!(frustum.intersects_sphere(&sphere(far * 0.5), true));
+        assert!(!frustum.intersects_sphere(&sphere(far * 2.), true));
+
+        let infinite = XrProjection {
+            near,
+            far: None,
+            projection_matrix: super::calculate_projection(near, None, fov),
+        };
+        let frustum = infinite.compute_frustum(&GlobalTransform::IDENTITY);
+        assert_eq!(infinite.far(), f32::INFINITY);
+        assert!(frustum.intersects_sphere(&sphere(far * 0.5), true));
+        assert!(frustum.intersects_sphere(&sphere(far * 1000.), true));
+        assert!(!frustum.intersects_sphere(&sphere(-far), true));
+    }
+
     /// Test that XrProjection::get_frustum_corners works correctly for a symmetrical projection matrix,
     /// by comparing against Bevy's PerspectiveProjection.
     #[test]
@@ -234,6 +665,7 @@ mod tests {
 
         let projection = XrProjection {
             near: control_proj.near,
+            far: None,
             projection_matrix: control_proj.get_clip_from_view(),
         };
 
@@ -256,6 +688,7 @@ mod tests {
 
         let projection = XrProjection {
             near: control_proj.near,
+            far: Some(control_proj.far),
             // Invert far and near plane to create reverse-Z far-plane perspective matrix.
             projection_matrix: Mat4::perspective_rh(
                 control_proj.fov,
@@ -288,7 +721,35 @@ mod tests {
 
         let projection = XrProjection {
             near,
-            projection_matrix: super::calculate_projection(near, fov),
+            far: None,
+            projection_matrix: super::calculate_projection(near, None, fov),
+        };
+
+        for (near, far) in TEST_VALUES {
+            let corners = projection.get_frustum_corners(*near, *far);
+            let control_corners = get_frustum_corners_asymmetrical_control(fov, *near, *far);
+
+            assert!(equals_in_tolerance(&corners, &control_corners));
+        }
+    }
+
+    /// Test that XrProjection::get_frustum_corners works correctly for an asymmetrical projection matrix
+    /// with a non-infinite far plane, by comparing against an implementation similar to that of Bevy's PerspectiveProjection.
+    #[test]
+    fn test_get_frustum_corners_asymmetrical_far_plane() {
+        let fov = super::Fov {
+            angle_left: -PI * 0.3,
+            angle_right: PI * 0.2,
+            angle_down: -PI * 0.28,
+            angle_up: PI * 0.22,
+        };
+
+        let (near, far) = (0.1, 1000.);
+
+        let projection = XrProjection {
+            near,
+            far: Some(far),
+            projection_matrix: super::calculate_projection(near, Some(far), fov),
         };
 
         for (near, far) in TEST_VALUES {
@@ -332,4 +793,59 @@ mod tests {
             Vec3A::new(tan_left * far, tan_down * far, z_far),     // Bottom-left
         ]
     }
+
+    /// Test that XrViewVisibility replaces the render layers and restores them when it is removed.
+    #[test]
+    fn test_view_visibility_restores_render_layers() {
+        use bevy_camera::visibility::RenderLayers;
+        use bevy_ecs::world::World;
+
+        use super::{XR_VIEW_RENDER_LAYER, XrViewVisibility};
+
+        let mut world = World::new();
+        let with_layers = world
+            .spawn((RenderLayers::layer(3), XrViewVisibility::RIGHT))
+            .id();
+        let without_layers = world.spawn(XrViewVisibility::LEFT).id();
+        world.flush();
+        assert_eq!(
+            world.get::<RenderLayers>(with_layers),
+            Some(&RenderLayers::layer(XR_VIEW_RENDER_LAYER + 1))
+        );
+        assert_eq!(
+            world.get::<RenderLayers>(without_layers),
+            Some(&RenderLayers::layer(XR_VIEW_RENDER_LAYER))
+        );
+
+        // replacing the view keeps the layers from before the first insert
+        world.entity_mut(with_layers).insert(XrViewVisibility::LEFT);
+        world.flush();
+        assert_eq!(
+            world.get::<RenderLayers>(with_layers),
+            Some(&RenderLayers::layer(XR_VIEW_RENDER_LAYER))
+        );
+
+        world.entity_mut(with_layers).remove::<XrViewVisibility>();
+        world
+            .entity_mut(without_layers)
+            .remove::<XrViewVisibility>();
+        world.flush();
+        assert_eq!(
+            world.get::<RenderLayers>(with_layers),
+            Some(&RenderLayers::layer(3))
+        );
+        assert_eq!(world.get::<RenderLayers>(without_layers), None);
+    }
+
+    /// Test that the reserved view layers have room for every view.
+    #[test]
+    fn test_view_render_layers() {
+        use super::{XR_MAX_VIEWS, XR_VIEW_RENDER_LAYERS, XrViewVisibility};
+
+        for view in 0..XR_MAX_VIEWS {
+            let layer = XrViewVisibility(view).render_layer().unwrap();
+            assert!(XR_VIEW_RENDER_LAYERS.contains(&layer));
+        }
+        assert_eq!(XrViewVisibility(XR_MAX_VIEWS).render_layer(), None);
+    }
 }
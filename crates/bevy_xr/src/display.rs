@@ -0,0 +1,41 @@
+use bevy_ecs::{message::Message, resource::Resource};
+
+/// The refresh rate of the XR display, in Hz.
+///
+/// The backend fills in [`available`](Self::available) and [`current`](Self::current) once a session exists.
+/// Use [`request`](Self::request) to ask the backend for a different rate,
+/// an [`XrDisplayRefreshRateChanged`] message is sent once the runtime actually switched.
+#[derive(Resource, Clone, Debug, Default, PartialEq)]
+pub struct XrDisplayRefreshRate {
+    /// All refresh rates supported by the display.
+    pub available: Vec<f32>,
+    /// The refresh rate the display is currently running at.
+    pub current: Option<f32>,
+    /// A refresh rate requested by the app that hasn't been handled by the backend yet.
+    pub requested: Option<f32>,
+}
+
+impl XrDisplayRefreshRate {
+    /// Requests that the display switches to `rate`.
+    /// Pass `0.0` to let the runtime pick the refresh rate again.
+    pub fn request(&mut self, rate: f32) {
+        self.requested = Some(rate);
+    }
+
+    /// Returns the highest available refresh rate.
+    pub fn max(&self) -> Option<f32> {
+        self.available.iter().copied().reduce(f32::max)
+    }
+
+    /// Returns the lowest available refresh rate.
+    pub fn min(&self) -> Option<f32> {
+        self.available.iter().copied().reduce(f32::min)
+    }
+}
+
+/// Message sent by the backend when the display refresh rate changed.
+#[derive(Message, Clone, Copy, Debug, PartialEq)]
+pub struct XrDisplayRefreshRateChanged {
+    pub from: f32,
+    pub to: f32,
+}
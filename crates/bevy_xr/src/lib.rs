@@ -1,6 +1,8 @@
 pub mod camera;
+pub mod display;
 #[cfg(feature = "gizmos")]
 pub mod hand_debug_gizmos;
 pub mod hands;
+pub mod screenshot;
 pub mod session;
 pub mod spaces;
@@ -0,0 +1,45 @@
+use std::path::PathBuf;
+
+use bevy_ecs::message::Message;
+
+/// Message to capture the XR views of the next rendered frame.
+///
+/// The backend triggers bevy's [`ScreenshotCaptured`](bevy_render::view::screenshot::ScreenshotCaptured)
+/// event once the image was read back, observe it to get the captured image.
+/// If [`path`](Self::path) is set, the image is also saved there, the format is picked from the file extension.
+#[derive(Message, Clone, Debug, PartialEq)]
+pub struct XrScreenshot {
+    pub views: XrScreenshotViews,
+    pub path: Option<PathBuf>,
+}
+
+impl XrScreenshot {
+    /// Captures a single view, `0` is the left eye for stereo view configurations.
+    pub fn view(view: u32) -> Self {
+        Self {
+            views: XrScreenshotViews::View(view),
+            path: None,
+        }
+    }
+
+    /// Captures all views next to each other, left to right.
+    pub fn side_by_side() -> Self {
+        Self {
+            views: XrScreenshotViews::SideBySide,
+            path: None,
+        }
+    }
+
+    /// Saves the captured image to `path`.
+    pub fn save_to(mut self, path: impl Into<PathBuf>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+}
+
+/// The views captured by an [`XrScreenshot`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum XrScreenshotViews {
+    View(u32),
+    SideBySide,
+}
@@ -40,6 +40,15 @@ impl XrVelocity {
     }
 }
 
+/// Marks an [`XrSpace`] or a hand tracker that is located again in the render world right before rendering.
+///
+/// The space, or every bone of the hand, and all of their descendants are drawn with the newer pose,
+/// so held items and hand meshes don't lag behind the head.
+/// This only changes what is rendered, the [`Transform`] in the main world is left as is.
+#[derive(Component, Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+pub struct XrLateLatch;
+
 #[derive(Message, Clone, Copy, Deref, DerefMut)]
 pub struct XrDestroySpace(pub XrSpace);
 
@@ -0,0 +1,17 @@
+#define_import_path bevy_mod_xr::view_uniform
+
+// Matches `XrViewUniform` in `camera.rs`.
+struct XrViewUniform {
+    // tangents aren't stored, these are the left, right, down and up angles in radians
+    fov: vec4<f32>,
+    // quaternion relative to the tracking root
+    orientation: vec4<f32>,
+    // position relative to the tracking root
+    position: vec3<f32>,
+    // 0 is the left eye in stereo view configurations
+    index: u32,
+    // distance between the first two views in meters
+    ipd: f32,
+    // seconds, wraps every hour
+    predicted_display_time: f32,
+}
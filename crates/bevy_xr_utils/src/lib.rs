@@ -5,5 +5,10 @@ pub mod transform_utils;
 #[cfg(not(target_family = "wasm"))]
 pub mod actions;
 pub mod generic_tracker;
+pub mod mixed_reality_capture;
+pub mod stereo_image;
+pub mod view_material;
+#[cfg(not(target_family = "wasm"))]
+pub mod ui_panel;
 #[cfg(not(target_family = "wasm"))]
 pub mod mndx_xdev_spaces_trackers;
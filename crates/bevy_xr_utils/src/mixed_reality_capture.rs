@@ -0,0 +1,564 @@
+use std::{fmt::Write as _, path::Path};
+
+use bevy_app::{App, Plugin, PostUpdate};
+use bevy_camera::{
+    Camera, Camera3d, CameraProjection, CameraUpdateSystems, ClearColorConfig, Projection,
+    RenderTarget, SubCameraView, Viewport,
+};
+use bevy_color::Color;
+use bevy_ecs::{
+    component::Component,
+    entity::Entity,
+    hierarchy::{ChildOf, Children},
+    query::{Changed, With},
+    schedule::IntoScheduleConfigs as _,
+    system::{Commands, Query},
+};
+use bevy_log::warn;
+use bevy_math::{EulerRot, Mat4, Quat, UVec2, Vec3, Vec3A, Vec4};
+use bevy_mod_xr::{camera::XrCamera, session::XrTrackingRoot};
+use bevy_transform::{
+    TransformSystems,
+    components::{GlobalTransform, Transform},
+};
+
+/// Renders the scene from an external, calibrated camera for mixed reality capture.
+///
+/// Every [`MixedRealityCapture`] spawns its own capture cameras, which are split at the depth of the
+/// player's head: the foreground contains everything between the capture camera and the player,
+/// the background everything behind. Composite the real camera feed between the two, e.g. in OBS.
+pub struct MixedRealityCapturePlugin;
+
+impl Plugin for MixedRealityCapturePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            (
+                spawn_capture_cameras.before(TransformSystems::Propagate),
+                split_at_head_depth
+                    .after(TransformSystems::Propagate)
+                    .before(CameraUpdateSystems),
+            ),
+        );
+    }
+}
+
+/// An external camera used for mixed reality capture.
+///
+/// The capture cameras are placed at the calibrated pose relative to the `tracker`, or relative to
+/// the [`XrTrackingRoot`] if there is none. Changing this component respawns the capture cameras.
+#[derive(Component, Clone, Debug)]
+#[require(Transform)]
+pub struct MixedRealityCapture {
+    pub calibration: MixedRealityCalibration,
+    /// Entity tracking the physical camera, for example an `XrSpace` of a tracker attached to the webcam.
+    pub tracker: Option<Entity>,
+    pub output: MixedRealityCaptureOutput,
+    /// Clear color of the foreground, use a chroma key color if the output doesn't keep the alpha channel.
+    pub foreground_clear_color: Color,
+}
+
+impl MixedRealityCapture {
+    pub fn new(calibration: MixedRealityCalibration, output: MixedRealityCaptureOutput) -> Self {
+        Self {
+            calibration,
+            tracker: None,
+            output,
+            foreground_clear_color: Color::NONE,
+        }
+    }
+
+    pub fn with_tracker(mut self, tracker: Entity) -> Self {
+        self.tracker = Some(tracker);
+        self
+    }
+}
+
+/// Where the capture cameras render to.
+#[derive(Clone, Debug)]
+pub enum MixedRealityCaptureOutput {
+    /// Foreground and background are rendered to their own targets,
+    /// for example two windows or two images shared with an encoder.
+    Separate {
+        foreground: RenderTarget,
+        background: RenderTarget,
+    },
+    /// The quadrant layout used by LIV and the SteamVR mixed reality setups, in a single target of
+    /// twice the calibrated resolution.
+    ///
+    /// The foreground is rendered to the top left, the background to the bottom left and the whole
+    /// scene to the bottom right. The top right quadrant (the foreground alpha) is not written, so the
+    /// foreground is separated by keying out [`MixedRealityCapture::foreground_clear_color`], which
+    /// also fills every quadrant where nothing was drawn.
+    Quadrant(RenderTarget),
+}
+
+/// The part of the scene rendered by a capture camera.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MixedRealityCaptureLayer {
+    /// Everything between the capture camera and the player's head.
+    Foreground,
+    /// Everything behind the player's head.
+    Background,
+    /// The whole scene.
+    Full,
+}
+
+/// A camera spawned as a child of a [`MixedRealityCapture`].
+#[derive(Component, Clone, Copy, Debug)]
+pub struct MixedRealityCaptureCamera(pub MixedRealityCaptureLayer);
+
+/// Calibration of an external camera, compatible with the `externalcamera.cfg` files written by
+/// LIV and the SteamVR mixed reality tools.
+///
+/// Those files use Unity's left handed coordinates with the rotation in degrees,
+/// they are converted to bevy's coordinates when parsing.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MixedRealityCalibration {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    /// Vertical field of view in radians.
+    pub fov: f32,
+    pub near: f32,
+    pub far: f32,
+    /// Moves the split between foreground and background along the view direction, in meters.
+    pub head_offset: f32,
+    /// Resolution of a single layer.
+    pub resolution: UVec2,
+}
+
+impl Default for MixedRealityCalibration {
+    fn default() -> Self {
+        Self {
+            translation: Vec3::ZERO,
+            rotation: Quat::IDENTITY,
+            fov: 60f32.to_radians(),
+            near: 0.01,
+            far: 1000.0,
+            head_offset: 0.0,
+            resolution: UVec2::new(1920, 1080),
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum MixedRealityCalibrationError {
+    #[error("failed to read calibration file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("line {0} is not a `key=value` pair")]
+    InvalidLine(usize),
+    #[error("invalid value `{value}` for `{key}`")]
+    InvalidValue { key: String, value: String },
+}
+
+impl MixedRealityCalibration {
+    /// Loads a calibration from an `externalcamera.cfg` file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, MixedRealityCalibrationError> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Parses the contents of an `externalcamera.cfg` file. Unknown keys are ignored.
+    pub fn parse(config: &str) -> Result<Self, MixedRealityCalibrationError> {
+        let mut calibration = Self::default();
+        let (mut rx, mut ry, mut rz) = (0.0, 0.0, 0.0);
+        for (index, line) in config.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with("//") || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(MixedRealityCalibrationError::InvalidLine(index + 1));
+            };
+            let (key, value) = (key.trim(), value.trim());
+            let invalid = || MixedRealityCalibrationError::InvalidValue {
+                key: key.to_string(),
+                value: value.to_string(),
+            };
+            let float = || value.parse::<f32>().map_err(|_| invalid());
+            let int = || value.parse::<u32>().map_err(|_| invalid());
+            match key {
+                "x" => calibration.translation.x = float()?,
+                "y" => calibration.translation.y = float()?,
+                // unity's z axis points forward
+                "z" => calibration.translation.z = -float()?,
+                "rx" => rx = float()?,
+                "ry" => ry = float()?,
+                "rz" => rz = float()?,
+                "fov" => calibration.fov = float()?.to_radians(),
+                "near" => calibration.near = float()?,
+                "far" => calibration.far = float()?,
+                "hmdOffset" => calibration.head_offset = float()?,
+                "sceneResolutionX" => calibration.resolution.x = int()?,
+                "sceneResolutionY" => calibration.resolution.y = int()?,
+                _ => {}
+            }
+        }
+        // unity applies the euler angles in z, x, y order, mirroring the z axis flips the x and y rotations
+        calibration.rotation = Quat::from_euler(
+            EulerRot::YXZ,
+            -ry.to_radians(),
+            -rx.to_radians(),
+            rz.to_radians(),
+        );
+        Ok(calibration)
+    }
+
+    /// Writes the calibration in the `externalcamera.cfg` format.
+    pub fn to_config(&self) -> String {
+        let (ry, rx, rz) = self.rotation.to_euler(EulerRot::YXZ);
+        let mut config = String::new();
+        for (key, value) in [
+            ("x", self.translation.x),
+            ("y", self.translation.y),
+            ("z", -self.translation.z),
+            ("rx", -rx.to_degrees()),
+            ("ry", -ry.to_degrees()),
+            ("rz", rz.to_degrees()),
+            ("fov", self.fov.to_degrees()),
+            ("near", self.near),
+            ("far", self.far),
+            ("hmdOffset", self.head_offset),
+        ] {
+            let _ = writeln!(config, "{key}={value}");
+        }
+        let _ = writeln!(config, "sceneResolutionX={}", self.resolution.x);
+        let _ = writeln!(config, "sceneResolutionY={}", self.resolution.y);
+        config
+    }
+
+    /// The pose of the camera relative to the tracker or tracking root.
+    pub fn transform(&self) -> Transform {
+        Transform::from_translation(self.translation).with_rotation(self.rotation)
+    }
+}
+
+/// A perspective projection with a finite far plane, so that geometry behind it is clipped.
+#[derive(Clone, Copy, Debug)]
+pub struct MixedRealityCaptureProjection {
+    /// Vertical field of view in radians.
+    pub fov: f32,
+    pub aspect_ratio: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl MixedRealityCaptureProjection {
+    fn frustum_matrix(&self, left: f32, right: f32, bottom: f32, top: f32) -> Mat4 {
+        let (near, far) = (self.near, self.far);
+        // reverse z, depth is 1 at the near plane and 0 at the far plane
+        Mat4::from_cols(
+            Vec4::new(2.0 * near / (right - left), 0.0, 0.0, 0.0),
+            Vec4::new(0.0, 2.0 * near / (top - bottom), 0.0, 0.0),
+            Vec4::new(
+                (right + left) / (right - left),
+                (top + bottom) / (top - bottom),
+                near / (far - near),
+                -1.0,
+            ),
+            Vec4::new(0.0, 0.0, near * far / (far - near), 0.0),
+        )
+    }
+}
+
+impl CameraProjection for MixedRealityCaptureProjection {
+    fn get_clip_from_view(&self) -> Mat4 {
+        let top = self.near * (self.fov * 0.5).tan();
+        let right = top * self.aspect_ratio;
+        self.frustum_matrix(-right, right, -top, top)
+    }
+
+    fn get_clip_from_view_for_sub(&self, sub_view: &SubCameraView) -> Mat4 {
+        let full_size = sub_view.full_size.as_vec2();
+        let size = sub_view.size.as_vec2();
+        // Y-axis increases from top to bottom
+        let offset_y = full_size.y - (sub_view.offset.y + size.y);
+
+        let top = self.near * (self.fov * 0.5).tan();
+        let right = top * full_size.x / full_size.y;
+        let (width, height) = (2.0 * right, 2.0 * top);
+        self.frustum_matrix(
+            -right + width * sub_view.offset.x / full_size.x,
+            -right + width * (sub_view.offset.x + size.x) / full_size.x,
+            -top + height * offset_y / full_size.y,
+            -top + height * (offset_y + size.y) / full_size.y,
+        )
+    }
+
+    fn update(&mut self, width: f32, height: f32) {
+        if width > 0.0 && height > 0.0 {
+            self.aspect_ratio = width / height;
+        }
+    }
+
+    fn far(&self) -> f32 {
+        self.far
+    }
+
+    fn get_frustum_corners(&self, z_near: f32, z_far: f32) -> [Vec3A; 8] {
+        let tan_half_fov = (self.fov / 2.).tan();
+        let a = z_near.abs() * tan_half_fov;
+        let b = z_far.abs() * tan_half_fov;
+        let aspect_ratio = self.aspect_ratio;
+        [
+            Vec3A::new(a * aspect_ratio, -a, z_near),  // bottom right
+            Vec3A::new(a * aspect_ratio, a, z_near),   // top right
+            Vec3A::new(-a * aspect_ratio, a, z_near),  // top left
+            Vec3A::new(-a * aspect_ratio, -a, z_near), // bottom left
+            Vec3A::new(b * aspect_ratio, -b, z_far),   // bottom right
+            Vec3A::new(b * aspect_ratio, b, z_far),    // top right
+            Vec3A::new(-b * aspect_ratio, b, z_far),   // top left
+            Vec3A::new(-b * aspect_ratio, -b, z_far),  // bottom left
+        ]
+    }
+}
+
+fn spawn_capture_cameras(
+    mut captures: Query<
+        (
+            Entity,
+            &MixedRealityCapture,
+            &mut Transform,
+            Option<&Children>,
+        ),
+        Changed<MixedRealityCapture>,
+    >,
+    capture_cameras: Query<(), With<MixedRealityCaptureCamera>>,
+    root: Query<Entity, With<XrTrackingRoot>>,
+    mut cmds: Commands,
+) {
+    for (entity, capture, mut transform, children) in &mut captures {
+        for child in children.into_iter().flatten() {
+            if capture_cameras.contains(*child) {
+                cmds.entity(*child).despawn();
+            }
+        }
+
+        let calibration = &capture.calibration;
+        *transform = calibration.transform();
+        match capture.tracker.or_else(|| root.single().ok()) {
+            Some(parent) => {
+                cmds.entity(entity).insert(ChildOf(parent));
+            }
+            None => warn!("no tracking root for mixed reality capture, using world space"),
+        }
+
+        let layers = match &capture.output {
+            MixedRealityCaptureOutput::Separate {
+                foreground,
+                background,
+            } => vec![
+                (
+                    MixedRealityCaptureLayer::Foreground,
+                    foreground.clone(),
+                    None,
+                ),
+                (
+                    MixedRealityCaptureLayer::Background,
+                    background.clone(),
+                    None,
+                ),
+            ],
+            MixedRealityCaptureOutput::Quadrant(target) => {
+                let size = calibration.resolution;
+                [
+                    (MixedRealityCaptureLayer::Foreground, UVec2::ZERO),
+                    (MixedRealityCaptureLayer::Background, UVec2::new(0, size.y)),
+                    (MixedRealityCaptureLayer::Full, size),
+                ]
+                .into_iter()
+                .map(|(layer, position)| {
+                    let viewport = Viewport {
+                        physical_position: position,
+                        physical_size: size,
+                        ..Default::default()
+                    };
+                    (layer, target.clone(), Some(viewport))
+                })
+                .collect()
+            }
+        };
+
+        let quadrant = matches!(capture.output, MixedRealityCaptureOutput::Quadrant(_));
+        for (order, (layer, target, viewport)) in layers.into_iter().enumerate() {
+            let clear_color = match layer {
+                MixedRealityCaptureLayer::Foreground => {
+                    ClearColorConfig::Custom(capture.foreground_clear_color)
+                }
+                // every camera clears the whole target, so only the first one may clear the quadrants
+                _ if quadrant => ClearColorConfig::None,
+                _ => ClearColorConfig::Default,
+            };
+            cmds.spawn((
+                Camera3d::default(),
+                Camera {
+                    order: order as isize,
+                    viewport,
+                    clear_color,
+                    ..Default::default()
+                },
+                target,
+                Projection::custom(MixedRealityCaptureProjection {
+                    fov: calibration.fov,
+                    aspect_ratio: calibration.resolution.x as f32
+                        / calibration.resolution.y.max(1) as f32,
+                    near: calibration.near,
+                    far: calibration.far,
+                }),
+                MixedRealityCaptureCamera(layer),
+                ChildOf(entity),
+            ));
+        }
+    }
+}
+
+fn split_at_head_depth(
+    captures: Query<&MixedRealityCapture>,
+    heads: Query<&GlobalTransform, With<XrCamera>>,
+    mut cameras: Query<(
+        &MixedRealityCaptureCamera,
+        &ChildOf,
+        &GlobalTransform,
+        &mut Projection,
+    )>,
+) {
+    let (sum, count) = heads
+        .iter()
+        .fold((Vec3::ZERO, 0), |(sum, count), transform| {
+            (sum + transform.translation(), count + 1)
+        });
+    if count == 0 {
+        return;
+    }
+    let head = sum / count as f32;
+
+    for (camera, child_of, transform, mut projection) in &mut cameras {
+        let Ok(capture) = captures.get(child_of.parent()) else {
+            continue;
+        };
+        let Projection::Custom(projection) = projection.as_mut() else {
+            continue;
+        };
+        let Some(projection) = projection.get_mut::<MixedRealityCaptureProjection>() else {
+            continue;
+        };
+        let calibration = &capture.calibration;
+        let depth =
+            (head - transform.translation()).dot(*transform.forward()) + calibration.head_offset;
+        let split = depth.clamp(calibration.near, calibration.far);
+        match camera.0 {
+            MixedRealityCaptureLayer::Foreground => projection.far = split,
+            MixedRealityCaptureLayer::Background => projection.near = split,
+            MixedRealityCaptureLayer::Full => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = "
+// written by LIV
+x=0.1
+y=1.5
+z=-2
+rx=0
+ry=0
+rz=0
+fov=40
+near=0.05
+far=100
+hmdOffset=0.2
+sceneResolutionX=1280
+sceneResolutionY=720
+disableStandardAssets=0
+";
+
+    fn calibration_with_rotation(rx: f32, ry: f32, rz: f32) -> MixedRealityCalibration {
+        MixedRealityCalibration::parse(&format!("rx={rx}\nry={ry}\nrz={rz}")).unwrap()
+    }
+
+    fn assert_vec3(actual: Vec3, expected: Vec3) {
+        assert!(actual.abs_diff_eq(expected, 1e-4), "{actual} != {expected}");
+    }
+
+    #[test]
+    fn test_parse() {
+        let calibration = MixedRealityCalibration::parse(CONFIG).unwrap();
+        assert_vec3(calibration.translation, Vec3::new(0.1, 1.5, 2.0));
+        assert!(calibration.rotation.abs_diff_eq(Quat::IDENTITY, 1e-6));
+        assert!((calibration.fov - 40f32.to_radians()).abs() < 1e-6);
+        assert_eq!(calibration.near, 0.05);
+        assert_eq!(calibration.far, 100.0);
+        assert_eq!(calibration.head_offset, 0.2);
+        assert_eq!(calibration.resolution, UVec2::new(1280, 720));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(matches!(
+            MixedRealityCalibration::parse("x=1\nfov"),
+            Err(MixedRealityCalibrationError::InvalidLine(2))
+        ));
+        assert!(matches!(
+            MixedRealityCalibration::parse("fov=wide"),
+            Err(MixedRealityCalibrationError::InvalidValue { key, value })
+                if key == "fov" && value == "wide"
+        ));
+    }
+
+    #[test]
+    fn test_rotation_yaw() {
+        // facing along unity's +x, which is bevy's +x as well
+        let rotation = calibration_with_rotation(0.0, 90.0, 0.0).rotation;
+        assert_vec3(rotation * Vec3::NEG_Z, Vec3::X);
+        assert_vec3(rotation * Vec3::Y, Vec3::Y);
+    }
+
+    #[test]
+    fn test_rotation_pitch() {
+        // a positive pitch looks down in unity
+        let rotation = calibration_with_rotation(90.0, 0.0, 0.0).rotation;
+        assert_vec3(rotation * Vec3::NEG_Z, Vec3::NEG_Y);
+    }
+
+    #[test]
+    fn test_rotation_roll() {
+        // a positive roll turns the camera's up towards -x in unity
+        let rotation = calibration_with_rotation(0.0, 0.0, 90.0).rotation;
+        assert_vec3(rotation * Vec3::NEG_Z, Vec3::NEG_Z);
+        assert_vec3(rotation * Vec3::Y, Vec3::NEG_X);
+    }
+
+    #[test]
+    fn test_rotation_combined() {
+        // unity's forward after `Quaternion.Euler(30, 45, 0)` is (0.612, -0.5, 0.612)
+        let rotation = calibration_with_rotation(30.0, 45.0, 0.0).rotation;
+        let horizontal = 45f32.to_radians().sin() * 30f32.to_radians().cos();
+        assert_vec3(
+            rotation * Vec3::NEG_Z,
+            Vec3::new(horizontal, -0.5, -horizontal),
+        );
+    }
+
+    #[test]
+    fn test_to_config_round_trip() {
+        let calibration = MixedRealityCalibration {
+            rotation: calibration_with_rotation(-20.0, 135.0, 10.0).rotation,
+            ..MixedRealityCalibration::parse(CONFIG).unwrap()
+        };
+        let parsed = MixedRealityCalibration::parse(&calibration.to_config()).unwrap();
+        assert_vec3(parsed.translation, calibration.translation);
+        assert!(
+            parsed.rotation.angle_between(calibration.rotation) < 1e-3,
+            "{} != {}",
+            parsed.rotation,
+            calibration.rotation
+        );
+        assert!((parsed.fov - calibration.fov).abs() < 1e-6);
+        assert_eq!(parsed.near, calibration.near);
+        assert_eq!(parsed.far, calibration.far);
+        assert_eq!(parsed.head_offset, calibration.head_offset);
+        assert_eq!(parsed.resolution, calibration.resolution);
+    }
+}
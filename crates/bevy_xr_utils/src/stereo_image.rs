@@ -0,0 +1,168 @@
+use bevy_app::{App, Plugin, PostUpdate};
+use bevy_asset::{Asset, AssetPath, Assets, Handle, embedded_asset, embedded_path};
+use bevy_camera::visibility::Visibility;
+use bevy_ecs::{
+    component::Component,
+    entity::Entity,
+    hierarchy::{ChildOf, Children},
+    query::{Changed, With},
+    schedule::IntoScheduleConfigs as _,
+    system::{Commands, Query, ResMut},
+};
+use bevy_image::Image;
+use bevy_math::Vec4;
+use bevy_mesh::{Mesh, Mesh3d, MeshVertexBufferLayoutRef};
+use bevy_pbr::{Material, MaterialPipeline, MaterialPipelineKey, MeshMaterial3d};
+use bevy_reflect::TypePath;
+use bevy_render::{
+    alpha::AlphaMode,
+    render_resource::{AsBindGroup, RenderPipelineDescriptor, SpecializedMeshPipelineError},
+};
+use bevy_shader::ShaderRef;
+use bevy_transform::{TransformSystems, components::Transform};
+
+use crate::view_material::{XrViewMaterialPlugin, add_xr_view_bind_group};
+
+/// Displays stereo photos and video with [`StereoImage`].
+pub struct StereoImagePlugin;
+
+impl Plugin for StereoImagePlugin {
+    fn build(&self, app: &mut App) {
+        embedded_asset!(app, "stereo_image.wgsl");
+        app.add_plugins(XrViewMaterialPlugin::<StereoImageMaterial>::default())
+            .add_systems(
+                PostUpdate,
+                spawn_stereo_views.before(TransformSystems::Propagate),
+            );
+    }
+}
+
+/// How the views are packed into a stereo image.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum StereoLayout {
+    /// The left eye in the left half, the right eye in the right half.
+    #[default]
+    SideBySide,
+    /// The left eye in the top half, the right eye in the bottom half.
+    TopBottom,
+}
+
+impl StereoLayout {
+    /// Offset and size of the part of the image belonging to `view`, in uv coordinates.
+    pub fn uv_rect(&self, view: u32) -> Vec4 {
+        let half = view.min(1) as f32 * 0.5;
+        match self {
+            StereoLayout::SideBySide => Vec4::new(half, 0.0, 0.5, 1.0),
+            StereoLayout::TopBottom => Vec4::new(0.0, half, 1.0, 0.5),
+        }
+    }
+}
+
+/// Shows each eye its own half of a stereo image on a mesh.
+///
+/// A child is spawned with a [`StereoImageMaterial`], which picks the half of the image from the view index
+/// in the [`XrViewUniform`](bevy_mod_xr::camera::XrViewUniform). Only
+/// [`XrCamera`](bevy_mod_xr::camera::XrCamera)s render the image. Changing this component respawns the child.
+#[derive(Component, Clone, Debug)]
+#[require(Transform, Visibility)]
+pub struct StereoImage {
+    pub mesh: Handle<Mesh>,
+    /// The stereo image, for video update the pixels of this image every frame.
+    pub image: Handle<Image>,
+    pub layout: StereoLayout,
+    pub alpha_mode: AlphaMode,
+}
+
+impl StereoImage {
+    pub fn new(mesh: Handle<Mesh>, image: Handle<Image>, layout: StereoLayout) -> Self {
+        Self {
+            mesh,
+            image,
+            layout,
+            alpha_mode: AlphaMode::Opaque,
+        }
+    }
+}
+
+/// Marker for the child spawned for a [`StereoImage`].
+#[derive(Component, Clone, Copy, Debug)]
+pub struct StereoImageView;
+
+/// Unlit material sampling the part of a stereo image that belongs to the view it is drawn in.
+#[derive(Asset, TypePath, AsBindGroup, Clone, Debug)]
+pub struct StereoImageMaterial {
+    /// Offset and size of the part of the image for the left and right view, in uv coordinates.
+    #[uniform(0)]
+    pub uv_rects: [Vec4; 2],
+    #[texture(1)]
+    #[sampler(2)]
+    pub image: Handle<Image>,
+    pub alpha_mode: AlphaMode,
+}
+
+impl StereoImageMaterial {
+    /// Material sampling `image` packed with `layout`.
+    pub fn new(image: Handle<Image>, layout: StereoLayout) -> Self {
+        Self {
+            uv_rects: [layout.uv_rect(0), layout.uv_rect(1)],
+            image,
+            alpha_mode: AlphaMode::Opaque,
+        }
+    }
+}
+
+impl Material for StereoImageMaterial {
+    fn fragment_shader() -> ShaderRef {
+        ShaderRef::Path(
+            AssetPath::from_path_buf(embedded_path!("stereo_image.wgsl")).with_source("embedded"),
+        )
+    }
+
+    fn alpha_mode(&self) -> AlphaMode {
+        self.alpha_mode
+    }
+
+    fn enable_prepass() -> bool {
+        false
+    }
+
+    fn enable_shadows() -> bool {
+        false
+    }
+
+    fn specialize(
+        _pipeline: &MaterialPipeline,
+        descriptor: &mut RenderPipelineDescriptor,
+        _layout: &MeshVertexBufferLayoutRef,
+        _key: MaterialPipelineKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        add_xr_view_bind_group(descriptor);
+        Ok(())
+    }
+}
+
+fn spawn_stereo_views(
+    images: Query<(Entity, &StereoImage, Option<&Children>), Changed<StereoImage>>,
+    views: Query<(), With<StereoImageView>>,
+    mut materials: ResMut<Assets<StereoImageMaterial>>,
+    mut cmds: Commands,
+) {
+    for (entity, image, children) in &images {
+        for child in children.into_iter().flatten() {
+            if views.contains(*child) {
+                cmds.entity(*child).despawn();
+            }
+        }
+
+        let material = StereoImageMaterial {
+            alpha_mode: image.alpha_mode,
+            ..StereoImageMaterial::new(image.image.clone(), image.layout)
+        };
+        cmds.spawn((
+            StereoImageView,
+            Mesh3d(image.mesh.clone()),
+            MeshMaterial3d(materials.add(material)),
+            ChildOf(entity),
+        ));
+    }
+}
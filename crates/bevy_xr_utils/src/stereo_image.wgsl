@@ -0,0 +1,17 @@
+#import bevy_pbr::forward_io::VertexOutput
+#import bevy_mod_xr::view_uniform::XrViewUniform
+
+@group(#{MATERIAL_BIND_GROUP}) @binding(0) var<uniform> uv_rects: array<vec4<f32>, 2>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(1) var image_texture: texture_2d<f32>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(2) var image_sampler: sampler;
+
+@group(#{XR_VIEW_BIND_GROUP}) @binding(0) var<uniform> xr_view: XrViewUniform;
+
+@fragment
+fn fragment(mesh: VertexOutput) -> @location(0) vec4<f32> {
+    // only sample the part of the stereo image that belongs to this view,
+    // the inner views of quad view configurations use the same eye as the outer ones
+    let uv_rect = uv_rects[xr_view.index % 2u];
+    let uv = uv_rect.xy + mesh.uv * uv_rect.zw;
+    return textureSample(image_texture, image_sampler, uv);
+}
@@ -0,0 +1,683 @@
+use bevy_app::{App, First, Plugin, PostUpdate, PreUpdate};
+use bevy_asset::Assets;
+use bevy_camera::{
+    Camera, Camera2d, ClearColorConfig, ImageRenderTarget, RenderTarget, visibility::Visibility,
+};
+use bevy_color::Color;
+use bevy_ecs::{
+    component::Component,
+    entity::Entity,
+    hierarchy::{ChildOf, Children},
+    lifecycle::HookContext,
+    message::MessageWriter,
+    query::{Changed, Or, With, Without},
+    schedule::IntoScheduleConfigs as _,
+    system::{Commands, Local, Query, Res, ResMut},
+    world::DeferredWorld,
+};
+use bevy_image::Image;
+use bevy_math::{Dir3, Quat, UVec2, Vec2, Vec3, primitives::Rectangle};
+use bevy_mesh::{Mesh, Mesh3d};
+use bevy_mod_openxr::{
+    features::quad_layer::{OxrQuadLayer, OxrQuadLayerTarget},
+    render::update_views,
+};
+use bevy_mod_xr::{
+    camera::{XrAdditionalCamera, XrCamera},
+    session::XrTrackingRoot,
+};
+use bevy_pbr::{MeshMaterial3d, StandardMaterial};
+use bevy_picking::{
+    PickingSystems,
+    hover::HoverMap,
+    pointer::{Location, PointerAction, PointerButton, PointerId, PointerInput, PointerLocation},
+};
+use bevy_platform::collections::HashMap;
+use bevy_render::{alpha::AlphaMode, render_resource::TextureFormat};
+use bevy_time::Time;
+use bevy_transform::{
+    TransformSystems,
+    components::{GlobalTransform, Transform},
+};
+use bevy_ui::{Interaction, UiSystems, UiTargetCamera};
+use uuid::Uuid;
+
+/// Renders [`XrUiPanel`]s into textures shown in the world and lets [`XrUiPointer`]s interact with them.
+///
+/// Needs bevy's UI picking backend for the pointers to reach the UI nodes.
+pub struct XrUiPanelPlugin;
+
+impl Plugin for XrUiPanelPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(First, update_pointers.in_set(PickingSystems::Input))
+            .add_systems(
+                PreUpdate,
+                update_interactions
+                    .after(UiSystems::Focus)
+                    .after(PickingSystems::Hover),
+            )
+            .add_systems(
+                PostUpdate,
+                (
+                    spawn_panels,
+                    update_quad_layer_cameras,
+                    place_panels.after(update_views),
+                )
+                    .chain()
+                    .before(TransformSystems::Propagate),
+            );
+    }
+}
+
+/// Shows the `bevy_ui` tree of `root` on a quad in the world.
+///
+/// The UI is laid out in pixels, the texture has `size * pixels_per_meter` pixels so
+/// `pixels_per_meter` controls how large the UI appears. The quad faces the +Z axis of the entity.
+/// Panels that aren't [`XrUiPanelPlacement::WorldAnchored`] overwrite their [`Transform`] every frame
+/// and shouldn't have a parent. Changing this component respawns the camera and texture of the panel.
+#[derive(Component, Clone, Debug)]
+#[require(Transform, Visibility)]
+pub struct XrUiPanel {
+    /// Root node of the UI tree, gets a [`UiTargetCamera`] pointing at the camera of the panel.
+    pub root: Entity,
+    /// Size of the panel in meters.
+    pub size: Vec2,
+    pub pixels_per_meter: f32,
+    pub placement: XrUiPanelPlacement,
+    pub display: XrUiPanelDisplay,
+}
+
+impl XrUiPanel {
+    pub fn new(root: Entity, size: Vec2) -> Self {
+        Self {
+            root,
+            size,
+            pixels_per_meter: 1000.0,
+            placement: XrUiPanelPlacement::WorldAnchored,
+            display: XrUiPanelDisplay::Mesh,
+        }
+    }
+
+    pub fn with_placement(mut self, placement: XrUiPanelPlacement) -> Self {
+        self.placement = placement;
+        self
+    }
+
+    pub fn with_display(mut self, display: XrUiPanelDisplay) -> Self {
+        self.display = display;
+        self
+    }
+
+    /// Size of the texture the UI is rendered to.
+    pub fn resolution(&self) -> UVec2 {
+        (self.size * self.pixels_per_meter)
+            .round()
+            .as_uvec2()
+            .max(UVec2::ONE)
+    }
+}
+
+/// How an [`XrUiPanel`] is placed relative to the user's head.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum XrUiPanelPlacement {
+    /// The panel stays at its [`Transform`].
+    #[default]
+    WorldAnchored,
+    /// The panel is placed at `offset` relative to the head every frame.
+    HeadLocked { offset: Transform },
+    /// The panel is placed at `offset` relative to the heading of the head, ignoring pitch and roll.
+    ///
+    /// It only starts following once it is more than `max_angle` radians or `max_distance` meters
+    /// away from where it should be, it then moves there with `speed`.
+    LazyFollow {
+        offset: Transform,
+        max_angle: f32,
+        max_distance: f32,
+        speed: f32,
+    },
+}
+
+impl XrUiPanelPlacement {
+    /// Head-locked one meter in front of the user.
+    pub fn head_locked() -> Self {
+        Self::HeadLocked {
+            offset: Transform::from_xyz(0.0, 0.0, -1.0),
+        }
+    }
+
+    /// Lazily follows the user one meter in front of them.
+    pub fn lazy_follow() -> Self {
+        Self::LazyFollow {
+            offset: Transform::from_xyz(0.0, 0.0, -1.0),
+            max_angle: 30f32.to_radians(),
+            max_distance: 0.3,
+            speed: 4.0,
+        }
+    }
+}
+
+/// How the texture of an [`XrUiPanel`] is shown.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum XrUiPanelDisplay {
+    /// A mesh with an unlit material, depth tested against the rest of the scene.
+    #[default]
+    Mesh,
+    /// An [`OxrQuadLayer`] composited by the runtime. Sharper than a mesh but always drawn
+    /// on top of the scene, the panel is only shown while the session is running.
+    QuadLayer,
+}
+
+/// Camera rendering the UI of an [`XrUiPanel`], spawned as a child of the panel.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct XrUiPanelCamera;
+
+/// Quad showing the texture of an [`XrUiPanel`], spawned as a child of the panel.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct XrUiPanelSurface {
+    pub camera: Entity,
+}
+
+#[derive(Component, Clone, Copy, Debug)]
+struct XrUiPanelState {
+    camera: Entity,
+    resolution: UVec2,
+    /// Whether a lazy follow panel is currently moving towards its target.
+    following: bool,
+}
+
+/// Turns the entity into a picking pointer that interacts with [`XrUiPanel`]s along its forward ray.
+///
+/// Set `pressed` from an action, e.g. a trigger. The UI receives the usual picking events like
+/// `Pointer<Click>`, [`Interaction`] is updated for nodes hovered by XR pointers as well.
+#[derive(Component, Clone, Copy, Debug, Default)]
+#[component(on_add = on_pointer_add)]
+#[require(Transform, XrUiPointerState)]
+pub struct XrUiPointer {
+    pub pressed: bool,
+}
+
+/// Namespace of the uuids of the [`PointerId`]s of [`XrUiPointer`]s.
+const XR_UI_POINTER_ID: u64 = 0x5872_5569_506f_696e;
+
+fn on_pointer_add(mut world: DeferredWorld, HookContext { entity, .. }: HookContext) {
+    world
+        .commands()
+        .entity(entity)
+        .try_insert(PointerId::Custom(Uuid::from_u64_pair(
+            XR_UI_POINTER_ID,
+            entity.to_bits(),
+        )));
+}
+
+#[derive(Component, Clone, Debug, Default)]
+struct XrUiPointerState {
+    location: Option<Location>,
+    /// Whether a press was sent to the picking pointer.
+    pressed: bool,
+    /// [`XrUiPointer::pressed`] of the last frame, presses only start on panels.
+    was_pressed: bool,
+}
+
+fn spawn_panels(
+    panels: Query<(Entity, &XrUiPanel, Option<&Children>), Changed<XrUiPanel>>,
+    children: Query<(), Or<(With<XrUiPanelCamera>, With<XrUiPanelSurface>)>>,
+    mut images: ResMut<Assets<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut cmds: Commands,
+) {
+    for (entity, panel, panel_children) in &panels {
+        for child in panel_children.into_iter().flatten() {
+            if children.contains(*child) {
+                cmds.entity(*child).despawn();
+            }
+        }
+
+        let resolution = panel.resolution();
+        let camera = Camera {
+            order: -1,
+            clear_color: ClearColorConfig::Custom(Color::NONE),
+            // quad layer cameras are activated once the swapchain of the quad exists
+            is_active: panel.display == XrUiPanelDisplay::Mesh,
+            ..Default::default()
+        };
+        let camera = cmds
+            .spawn((XrUiPanelCamera, Camera2d, camera, ChildOf(entity)))
+            .id();
+        let surface = XrUiPanelSurface { camera };
+        match panel.display {
+            XrUiPanelDisplay::Mesh => {
+                let image = images.add(Image::new_target_texture(
+                    resolution.x,
+                    resolution.y,
+                    TextureFormat::Rgba8UnormSrgb,
+                    None,
+                ));
+                cmds.entity(camera)
+                    .insert(RenderTarget::Image(ImageRenderTarget {
+                        handle: image.clone(),
+                        scale_factor: 1.0,
+                    }));
+                cmds.spawn((
+                    surface,
+                    Mesh3d(meshes.add(Rectangle::from_size(panel.size))),
+                    MeshMaterial3d(materials.add(StandardMaterial {
+                        base_color_texture: Some(image),
+                        unlit: true,
+                        alpha_mode: AlphaMode::Blend,
+                        ..Default::default()
+                    })),
+                    ChildOf(entity),
+                ));
+            }
+            XrUiPanelDisplay::QuadLayer => {
+                cmds.spawn((
+                    surface,
+                    OxrQuadLayer {
+                        resolution,
+                        size: panel.size,
+                    },
+                    ChildOf(entity),
+                ));
+            }
+        }
+        cmds.entity(panel.root).insert(UiTargetCamera(camera));
+        cmds.entity(entity).insert(XrUiPanelState {
+            camera,
+            resolution,
+            following: true,
+        });
+    }
+}
+
+fn update_quad_layer_cameras(
+    added: Query<(&XrUiPanelSurface, &OxrQuadLayerTarget), Changed<OxrQuadLayerTarget>>,
+    removed: Query<&XrUiPanelSurface, (With<OxrQuadLayer>, Without<OxrQuadLayerTarget>)>,
+    mut cameras: Query<&mut Camera, With<XrUiPanelCamera>>,
+    mut cmds: Commands,
+) {
+    for (surface, target) in &added {
+        cmds.entity(surface.camera)
+            .insert(RenderTarget::TextureView(target.0));
+        if let Ok(mut camera) = cameras.get_mut(surface.camera) {
+            camera.is_active = true;
+        }
+    }
+    for surface in &removed {
+        if let Ok(mut camera) = cameras.get_mut(surface.camera)
+            && camera.is_active
+        {
+            camera.is_active = false;
+        }
+    }
+}
+
+/// Places head-locked and lazy follow panels at the average position of the [`XrCamera`]s,
+/// facing the same way as the first view.
+fn place_panels(
+    mut panels: Query<(&XrUiPanel, &mut XrUiPanelState, &mut Transform), Without<XrCamera>>,
+    views: Query<(&XrCamera, &Transform), Without<XrAdditionalCamera>>,
+    root: Query<&GlobalTransform, With<XrTrackingRoot>>,
+    time: Res<Time>,
+) {
+    let mut view_count = 0;
+    let mut translation = Vec3::ZERO;
+    let mut rotation = None;
+    for (camera, view) in &views {
+        view_count += 1;
+        translation += view.translation;
+        if camera.0 == 0 {
+            rotation = Some(view.rotation);
+        }
+    }
+    let Some(rotation) = rotation else {
+        return;
+    };
+    // views are placed relative to the tracking root
+    let root = root.single().copied().unwrap_or_default();
+    let head = root.mul_transform(Transform {
+        translation: translation / view_count as f32,
+        rotation,
+        ..Default::default()
+    });
+    let head = head.compute_transform();
+
+    for (panel, mut state, mut transform) in &mut panels {
+        match panel.placement {
+            XrUiPanelPlacement::WorldAnchored => {}
+            XrUiPanelPlacement::HeadLocked { offset } => {
+                *transform = head.mul_transform(offset);
+            }
+            XrUiPanelPlacement::LazyFollow {
+                offset,
+                max_angle,
+                max_distance,
+                speed,
+            } => {
+                state.following = lazy_follow(
+                    &mut transform,
+                    &head,
+                    offset,
+                    max_angle,
+                    max_distance,
+                    speed,
+                    state.following,
+                    time.delta_secs(),
+                );
+            }
+        }
+    }
+}
+
+/// Moves a [`XrUiPanelPlacement::LazyFollow`] panel towards `offset` relative to the heading of `head`,
+/// returns whether the panel is still following.
+fn lazy_follow(
+    transform: &mut Transform,
+    head: &Transform,
+    offset: Transform,
+    max_angle: f32,
+    max_distance: f32,
+    speed: f32,
+    mut following: bool,
+    delta_secs: f32,
+) -> bool {
+    let forward = (head.rotation * Vec3::NEG_Z).with_y(0.0);
+    let heading = match Dir3::new(forward) {
+        Ok(forward) => Quat::from_rotation_arc(Vec3::NEG_Z, *forward),
+        // looking straight up or down, keep the panel where it is
+        Err(_) => return following,
+    };
+    let target = Transform::from_translation(head.translation)
+        .with_rotation(heading)
+        .mul_transform(offset);
+
+    let to_panel = transform.translation - head.translation;
+    let to_target = target.translation - head.translation;
+    let angle = to_panel.angle_between(to_target);
+    let distance = transform.translation.distance(target.translation);
+    if angle > max_angle || distance > max_distance {
+        following = true;
+    }
+    if !following {
+        return false;
+    }
+    let t = 1.0 - (-speed * delta_secs).exp();
+    transform.translation = transform.translation.lerp(target.translation, t);
+    transform.rotation = transform.rotation.slerp(target.rotation, t);
+    transform.scale = target.scale;
+    !(distance < 0.01 && angle < 1f32.to_radians())
+}
+
+/// Casts the ray of every [`XrUiPointer`] against the panels and moves its picking pointer to the hit.
+fn update_pointers(
+    mut pointers: Query<(
+        &PointerId,
+        &XrUiPointer,
+        &mut XrUiPointerState,
+        &mut PointerLocation,
+        &GlobalTransform,
+    )>,
+    panels: Query<(&XrUiPanel, &XrUiPanelState, &GlobalTransform)>,
+    cameras: Query<&RenderTarget, With<XrUiPanelCamera>>,
+    mut inputs: MessageWriter<PointerInput>,
+) {
+    for (id, pointer, mut state, mut pointer_location, transform) in &mut pointers {
+        let origin = transform.translation();
+        let direction = transform.forward();
+        let hit = panels
+            .iter()
+            .filter_map(|(panel, panel_state, panel_transform)| {
+                let (distance, uv) = ray_panel_uv(origin, direction, panel.size, panel_transform)?;
+                let target = cameras.get(panel_state.camera).ok()?.normalize(None)?;
+                let location = Location {
+                    target,
+                    position: uv * panel_state.resolution.as_vec2(),
+                };
+                Some((distance, location))
+            })
+            .min_by(|(a, _), (b, _)| a.total_cmp(b))
+            .map(|(_, location)| location);
+
+        let just_pressed = pointer.pressed && !state.was_pressed;
+        state.was_pressed = pointer.pressed;
+        let on_panel = hit.is_some();
+        let previous = state.location.take();
+        // a pressed pointer that left the panels keeps its last location until it is released
+        let Some(location) = hit.or_else(|| previous.clone().filter(|_| state.pressed)) else {
+            if previous.is_some() {
+                pointer_location.location = None;
+            }
+            continue;
+        };
+        if previous.as_ref() != Some(&location) {
+            let delta = previous
+                .as_ref()
+                .filter(|previous| previous.target == location.target)
+                .map_or(Vec2::ZERO, |previous| location.position - previous.position);
+            inputs.write(PointerInput::new(
+                *id,
+                location.clone(),
+                PointerAction::Move { delta },
+            ));
+        }
+        if just_pressed && !state.pressed {
+            inputs.write(PointerInput::new(
+                *id,
+                location.clone(),
+                PointerAction::Press(PointerButton::Primary),
+            ));
+            state.pressed = true;
+        } else if !pointer.pressed && state.pressed {
+            inputs.write(PointerInput::new(
+                *id,
+                location.clone(),
+                PointerAction::Release(PointerButton::Primary),
+            ));
+            state.pressed = false;
+        }
+        if on_panel || state.pressed {
+            state.location = Some(location);
+        } else {
+            pointer_location.location = None;
+        }
+    }
+}
+
+/// Intersects a ray with the front of a panel of `size` meters,
+/// returns the distance along the ray and the uv of the hit with the origin in the top left corner.
+fn ray_panel_uv(
+    origin: Vec3,
+    direction: Dir3,
+    size: Vec2,
+    panel_transform: &GlobalTransform,
+) -> Option<(f32, Vec2)> {
+    let normal = panel_transform.back();
+    let facing = direction.dot(*normal);
+    // only hit the front of the panel
+    if facing >= 0.0 {
+        return None;
+    }
+    let distance = (panel_transform.translation() - origin).dot(*normal) / facing;
+    if distance < 0.0 {
+        return None;
+    }
+    let local = panel_transform
+        .affine()
+        .inverse()
+        .transform_point3(origin + direction * distance)
+        .truncate();
+    let uv = Vec2::new(local.x / size.x + 0.5, 0.5 - local.y / size.y);
+    if !(0.0..=1.0).contains(&uv.x) || !(0.0..=1.0).contains(&uv.y) {
+        return None;
+    }
+    Some((distance, uv))
+}
+
+/// Sets [`Interaction`] of the nodes hovered by [`XrUiPointer`]s, as bevy_ui only does that for windows.
+fn update_interactions(
+    hover_map: Res<HoverMap>,
+    pointers: Query<(&PointerId, &XrUiPointer)>,
+    mut nodes: Query<&mut Interaction>,
+    mut interacted: Local<HashMap<Entity, Interaction>>,
+) {
+    let mut current = HashMap::<Entity, Interaction>::default();
+    for (id, pointer) in &pointers {
+        let Some(hovered) = hover_map.get(id) else {
+            continue;
+        };
+        let interaction = if pointer.pressed {
+            Interaction::Pressed
+        } else {
+            Interaction::Hovered
+        };
+        for entity in hovered.keys() {
+            let entry = current.entry(*entity).or_insert(interaction);
+            if interaction == Interaction::Pressed {
+                *entry = interaction;
+            }
+        }
+    }
+    for (entity, _) in interacted.drain() {
+        if !current.contains_key(&entity)
+            && let Ok(mut node) = nodes.get_mut(entity)
+        {
+            node.set_if_neq(Interaction::None);
+        }
+    }
+    for (entity, interaction) in &current {
+        if let Ok(mut node) = nodes.get_mut(*entity) {
+            node.set_if_neq(*interaction);
+        }
+    }
+    *interacted = current;
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_math::{Dir3, Quat, Vec2, Vec3};
+    use bevy_transform::components::{GlobalTransform, Transform};
+
+    use super::{XrUiPanelPlacement, lazy_follow, ray_panel_uv};
+
+    const SIZE: Vec2 = Vec2::new(2.0, 1.0);
+
+    fn panel() -> GlobalTransform {
+        GlobalTransform::from(Transform::from_xyz(0.0, 1.0, -2.0))
+    }
+
+    fn follow(transform: &mut Transform, head: &Transform, following: bool, delta: f32) -> bool {
+        let XrUiPanelPlacement::LazyFollow {
+            offset,
+            max_angle,
+            max_distance,
+            speed,
+        } = XrUiPanelPlacement::lazy_follow()
+        else {
+            unreachable!()
+        };
+        lazy_follow(
+            transform,
+            head,
+            offset,
+            max_angle,
+            max_distance,
+            speed,
+            following,
+            delta,
+        )
+    }
+
+    /// Test that a ray through the center of a panel hits the center of the texture.
+    #[test]
+    fn test_ray_panel_uv_center() {
+        let (distance, uv) =
+            ray_panel_uv(Vec3::new(0.0, 1.0, 0.0), Dir3::NEG_Z, SIZE, &panel()).unwrap();
+        assert!((distance - 2.0).abs() < 1e-5);
+        assert!(uv.abs_diff_eq(Vec2::splat(0.5), 1e-5));
+    }
+
+    /// Test that the uv origin is in the top left corner of the panel, like the UI.
+    #[test]
+    fn test_ray_panel_uv_top_left() {
+        let origin = Vec3::new(-0.5, 1.25, 0.0);
+        let (_, uv) = ray_panel_uv(origin, Dir3::NEG_Z, SIZE, &panel()).unwrap();
+        assert!(uv.abs_diff_eq(Vec2::new(0.25, 0.25), 1e-5));
+
+        // rotating the panel moves the hit with it
+        let rotated = GlobalTransform::from(
+            Transform::from_xyz(0.0, 1.0, -2.0).with_rotation(Quat::from_rotation_y(0.3)),
+        );
+        let hit = rotated.transform_point(Vec3::new(-0.5, 0.25, 0.0));
+        let origin = hit + *rotated.back();
+        let direction = Dir3::new(hit - origin).unwrap();
+        let (distance, uv) = ray_panel_uv(origin, direction, SIZE, &rotated).unwrap();
+        assert!((distance - 1.0).abs() < 1e-5);
+        assert!(uv.abs_diff_eq(Vec2::new(0.25, 0.25), 1e-5));
+    }
+
+    /// Test that rays missing the panel, behind it or hitting its back don't hit.
+    #[test]
+    fn test_ray_panel_uv_misses() {
+        // beside the panel
+        assert!(ray_panel_uv(Vec3::new(1.5, 1.0, 0.0), Dir3::NEG_Z, SIZE, &panel()).is_none());
+        // pointing away from the panel
+        assert!(ray_panel_uv(Vec3::new(0.0, 1.0, 0.0), Dir3::Z, SIZE, &panel()).is_none());
+        // hitting the back of the panel
+        assert!(ray_panel_uv(Vec3::new(0.0, 1.0, -4.0), Dir3::Z, SIZE, &panel()).is_none());
+        // the panel is behind the ray
+        assert!(ray_panel_uv(Vec3::new(0.0, 1.0, -3.0), Dir3::NEG_Z, SIZE, &panel()).is_none());
+    }
+
+    /// Test that a lazy follow panel stays put until it is too far away, then catches up and stops.
+    #[test]
+    fn test_lazy_follow() {
+        let mut head = Transform::from_xyz(0.0, 1.5, 0.0);
+        let mut transform = Transform::from_xyz(0.0, 1.5, -1.0);
+        assert!(!follow(&mut transform, &head, false, 0.1));
+        assert_eq!(transform.translation, Vec3::new(0.0, 1.5, -1.0));
+
+        // turning a bit doesn't move the panel
+        head.rotate_y(15f32.to_radians());
+        assert!(!follow(&mut transform, &head, false, 0.1));
+        assert_eq!(transform.translation, Vec3::new(0.0, 1.5, -1.0));
+
+        // turning further makes it follow
+        head.rotate_y(75f32.to_radians());
+        let mut following = follow(&mut transform, &head, false, 0.1);
+        assert!(following);
+        assert!(transform.translation.x < 0.0);
+
+        for _ in 0..100 {
+            following = follow(&mut transform, &head, following, 0.1);
+        }
+        assert!(!following);
+        assert!(
+            transform
+                .translation
+                .abs_diff_eq(Vec3::new(-1.0, 1.5, 0.0), 0.01)
+        );
+        assert!(transform.rotation.angle_between(head.rotation) < 1f32.to_radians());
+    }
+
+    /// Test that lazy follow only uses the heading of the head, ignoring pitch and roll.
+    #[test]
+    fn test_lazy_follow_heading() {
+        let head = Transform::from_xyz(0.0, 1.5, 0.0).with_rotation(
+            Quat::from_rotation_y(0.5) * Quat::from_rotation_x(-0.6) * Quat::from_rotation_z(0.2),
+        );
+        let mut transform = Transform::from_xyz(0.0, 0.0, 5.0);
+        let mut following = false;
+        for _ in 0..100 {
+            following = follow(&mut transform, &head, following, 0.1);
+        }
+        let expected = Transform::from_xyz(0.0, 1.5, 0.0)
+            .with_rotation(Quat::from_rotation_y(0.5))
+            .mul_transform(Transform::from_xyz(0.0, 0.0, -1.0));
+        assert!(
+            transform
+                .translation
+                .abs_diff_eq(expected.translation, 0.01)
+        );
+        assert!(transform.rotation.angle_between(expected.rotation) < 1f32.to_radians());
+    }
+}
@@ -0,0 +1,294 @@
+use std::{
+    any::{TypeId, type_name},
+    hash::Hash,
+    marker::PhantomData,
+    sync::Arc,
+};
+
+use bevy_app::{App, Plugin, PostUpdate};
+use bevy_asset::{AssetApp as _, AssetEventSystems, AssetId, prelude::AssetChanged};
+use bevy_camera::visibility::ViewVisibility;
+use bevy_core_pipeline::core_3d::{AlphaMask3d, Opaque3d, Transmissive3d, Transparent3d};
+use bevy_ecs::{
+    entity::Entity,
+    lifecycle::RemovedComponents,
+    query::{Changed, Or},
+    schedule::IntoScheduleConfigs as _,
+    system::{Query, Res, ResMut, SystemParamItem, lifetimeless::SRes},
+};
+use bevy_log::error;
+use bevy_mesh::{Mesh3d, mark_3d_meshes_as_changed_if_their_assets_changed};
+use bevy_mod_xr::camera::{SetXrViewBindGroup, xr_view_bind_group_layout};
+use bevy_pbr::{
+    DrawMesh, EntitiesNeedingSpecialization, MATERIAL_BIND_GROUP_INDEX,
+    MainPassAlphaMaskDrawFunction, MainPassOpaqueDrawFunction, MainPassTransmissiveDrawFunction,
+    MainPassTransparentDrawFunction, Material, MaterialBindGroupAllocator,
+    MaterialBindGroupAllocators, MaterialExtractEntitiesNeedingSpecializationSystems,
+    MaterialExtractionSystems, MeshMaterial3d, PreparedMaterial, RenderMaterialInstance,
+    RenderMaterialInstances, SetMaterialBindGroup, SetMeshBindGroup, SetMeshViewBindGroup,
+    SetMeshViewBindingArrayBindGroup, check_entities_needing_specialization,
+    extract_entities_needs_specialization, late_sweep_material_instances,
+    material_uses_bindless_resources, sweep_entities_needing_specialization,
+};
+use bevy_platform::collections::hash_map::Entry;
+use bevy_render::{
+    Extract, ExtractSchedule, RenderApp, RenderStartup,
+    camera::extract_cameras,
+    erased_render_asset::{ErasedRenderAsset, ErasedRenderAssetPlugin, PrepareAssetError},
+    render_phase::{AddRenderCommand as _, DrawFunctionLabel as _, DrawFunctions, SetItemPipeline},
+    render_resource::{BindGroupLayoutDescriptor, RenderPipelineDescriptor},
+    renderer::RenderDevice,
+    sync_world::MainEntity,
+};
+use bevy_shader::ShaderDefVal;
+
+/// Bind group of the [`XrViewUniform`](bevy_mod_xr::camera::XrViewUniform) in materials using the [`XrViewMaterialPlugin`].
+pub const XR_VIEW_MATERIAL_BIND_GROUP: usize = MATERIAL_BIND_GROUP_INDEX + 1;
+
+/// Renders [`MeshMaterial3d<M>`] like bevy's [`MaterialPlugin`](bevy_pbr::MaterialPlugin), with the
+/// [`XrViewUniform`](bevy_mod_xr::camera::XrViewUniform) of the view bound in the main passes,
+/// e.g. to pick the eye in the fragment shader. Use it instead of the `MaterialPlugin` of `M`.
+///
+/// `M` calls [`add_xr_view_bind_group`] from [`Material::specialize`], the shader binds the uniform with
+/// `@group(#{XR_VIEW_BIND_GROUP}) @binding(0) var<uniform> xr_view: XrViewUniform;`.
+///
+/// Only XR views draw the material. Prepasses and shadows don't bind the uniform, so `M` has to disable both.
+pub struct XrViewMaterialPlugin<M: Material>(PhantomData<M>);
+
+impl<M: Material> Default for XrViewMaterialPlugin<M> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<M: Material> Plugin for XrViewMaterialPlugin<M>
+where
+    M::Data: PartialEq + Eq + Hash + Clone,
+{
+    fn build(&self, app: &mut App) {
+        if M::enable_prepass() || M::enable_shadows() {
+            error!(
+                "{} has to disable prepasses and shadows to read the XR view uniform",
+                type_name::<M>()
+            );
+            return;
+        }
+        app.init_asset::<M>()
+            .init_resource::<EntitiesNeedingSpecialization<M>>()
+            .add_plugins(ErasedRenderAssetPlugin::<XrViewMaterialAsset<M>>::default())
+            .add_systems(
+                PostUpdate,
+                (
+                    mark_meshes_as_changed_if_their_materials_changed::<M>.ambiguous_with_all(),
+                    check_entities_needing_specialization::<M>.after(AssetEventSystems),
+                )
+                    .after(mark_3d_meshes_as_changed_if_their_assets_changed),
+            );
+
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app
+                .add_systems(RenderStartup, add_material_bind_group_allocator::<M>)
+                .add_systems(
+                    ExtractSchedule,
+                    (
+                        extract_mesh_materials::<M>.in_set(MaterialExtractionSystems),
+                        early_sweep_material_instances::<M>
+                            .after(MaterialExtractionSystems)
+                            .before(late_sweep_material_instances),
+                        extract_entities_needs_specialization::<M>
+                            .in_set(MaterialExtractEntitiesNeedingSpecializationSystems),
+                        sweep_entities_needing_specialization::<M>
+                            .after(MaterialExtractEntitiesNeedingSpecializationSystems)
+                            .after(MaterialExtractionSystems)
+                            .after(extract_cameras)
+                            .before(late_sweep_material_instances),
+                    ),
+                );
+        }
+    }
+
+    fn finish(&self, app: &mut App) {
+        if M::enable_prepass() || M::enable_shadows() {
+            return;
+        }
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .add_render_command::<Opaque3d, DrawXrViewMaterial>()
+            .add_render_command::<AlphaMask3d, DrawXrViewMaterial>()
+            .add_render_command::<Transmissive3d, DrawXrViewMaterial>()
+            .add_render_command::<Transparent3d, DrawXrViewMaterial>();
+    }
+}
+
+/// Adds the layout of the XR view bind group to a pipeline of a material using the [`XrViewMaterialPlugin`].
+///
+/// Also defines `XR_VIEW_BIND_GROUP` in the shaders, like bevy's `MATERIAL_BIND_GROUP`.
+pub fn add_xr_view_bind_group(descriptor: &mut RenderPipelineDescriptor) {
+    let layout = &mut descriptor.layout;
+    layout.resize(
+        layout.len().max(XR_VIEW_MATERIAL_BIND_GROUP + 1),
+        BindGroupLayoutDescriptor::default(),
+    );
+    layout[XR_VIEW_MATERIAL_BIND_GROUP] = xr_view_bind_group_layout();
+
+    let shader_def = ShaderDefVal::UInt(
+        "XR_VIEW_BIND_GROUP".into(),
+        XR_VIEW_MATERIAL_BIND_GROUP as u32,
+    );
+    descriptor.vertex.shader_defs.push(shader_def.clone());
+    if let Some(fragment) = descriptor.fragment.as_mut() {
+        fragment.shader_defs.push(shader_def);
+    }
+}
+
+/// The draw function of bevy's materials, with the XR view bind group.
+type DrawXrViewMaterial = (
+    SetItemPipeline,
+    SetMeshViewBindGroup<0>,
+    SetMeshViewBindingArrayBindGroup<1>,
+    SetMeshBindGroup<2>,
+    SetMaterialBindGroup<MATERIAL_BIND_GROUP_INDEX>,
+    SetXrViewBindGroup<XR_VIEW_MATERIAL_BIND_GROUP>,
+    DrawMesh,
+);
+
+/// Prepares `M` like bevy's materials, with [`DrawXrViewMaterial`] as the main pass draw functions.
+struct XrViewMaterialAsset<M>(PhantomData<fn() -> M>);
+
+impl<M: Material> ErasedRenderAsset for XrViewMaterialAsset<M>
+where
+    M::Data: PartialEq + Eq + Hash + Clone,
+{
+    type SourceAsset = M;
+    type ErasedAsset = PreparedMaterial;
+    type Param = (
+        <MeshMaterial3d<M> as ErasedRenderAsset>::Param,
+        SRes<DrawFunctions<Opaque3d>>,
+        SRes<DrawFunctions<AlphaMask3d>>,
+        SRes<DrawFunctions<Transmissive3d>>,
+        SRes<DrawFunctions<Transparent3d>>,
+    );
+
+    fn prepare_asset(
+        material: Self::SourceAsset,
+        material_id: AssetId<Self::SourceAsset>,
+        (material_param, opaque, alpha_mask, transmissive, transparent): &mut SystemParamItem<
+            Self::Param,
+        >,
+    ) -> Result<Self::ErasedAsset, PrepareAssetError<Self::SourceAsset>> {
+        let mut prepared = <MeshMaterial3d<M> as ErasedRenderAsset>::prepare_asset(
+            material,
+            material_id,
+            material_param,
+        )?;
+        let draw_functions = [
+            (
+                MainPassOpaqueDrawFunction.intern(),
+                opaque.read().id::<DrawXrViewMaterial>(),
+            ),
+            (
+                MainPassAlphaMaskDrawFunction.intern(),
+                alpha_mask.read().id::<DrawXrViewMaterial>(),
+            ),
+            (
+                MainPassTransmissiveDrawFunction.intern(),
+                transmissive.read().id::<DrawXrViewMaterial>(),
+            ),
+            (
+                MainPassTransparentDrawFunction.intern(),
+                transparent.read().id::<DrawXrViewMaterial>(),
+            ),
+        ];
+        // the properties were just created, nothing else holds them yet
+        if let Some(properties) = Arc::get_mut(&mut prepared.properties) {
+            for (label, draw_function) in &mut properties.draw_functions {
+                if let Some((_, xr_draw_function)) = draw_functions.iter().find(|(l, _)| l == label)
+                {
+                    *draw_function = *xr_draw_function;
+                }
+            }
+        }
+        Ok(prepared)
+    }
+
+    fn unload_asset(
+        material_id: AssetId<Self::SourceAsset>,
+        (material_param, ..): &mut SystemParamItem<Self::Param>,
+    ) {
+        <MeshMaterial3d<M> as ErasedRenderAsset>::unload_asset(material_id, material_param);
+    }
+}
+
+fn add_material_bind_group_allocator<M: Material>(
+    render_device: Res<RenderDevice>,
+    mut bind_group_allocators: ResMut<MaterialBindGroupAllocators>,
+) {
+    bind_group_allocators.insert(
+        TypeId::of::<M>(),
+        MaterialBindGroupAllocator::new(
+            &render_device,
+            M::label(),
+            material_uses_bindless_resources::<M>(&render_device)
+                .then(|| M::bindless_descriptor())
+                .flatten(),
+            M::bind_group_layout_descriptor(&render_device),
+            M::bindless_slot_count(),
+        ),
+    );
+}
+
+/// Makes bevy re-extract meshes whose material changed, like [`MaterialPlugin`](bevy_pbr::MaterialPlugin) does.
+fn mark_meshes_as_changed_if_their_materials_changed<M: Material>(
+    mut meshes: Query<
+        &mut Mesh3d,
+        Or<(Changed<MeshMaterial3d<M>>, AssetChanged<MeshMaterial3d<M>>)>,
+    >,
+) {
+    for mut mesh in &mut meshes {
+        mesh.set_changed();
+    }
+}
+
+fn extract_mesh_materials<M: Material>(
+    mut material_instances: ResMut<RenderMaterialInstances>,
+    meshes: Extract<
+        Query<
+            (Entity, &ViewVisibility, &MeshMaterial3d<M>),
+            Or<(Changed<ViewVisibility>, Changed<MeshMaterial3d<M>>)>,
+        >,
+    >,
+) {
+    let last_change_tick = material_instances.current_change_tick;
+    for (entity, view_visibility, material) in &meshes {
+        if view_visibility.get() {
+            material_instances.instances.insert(
+                entity.into(),
+                RenderMaterialInstance {
+                    asset_id: material.id().untyped(),
+                    last_change_tick,
+                },
+            );
+        } else {
+            material_instances
+                .instances
+                .remove(&MainEntity::from(entity));
+        }
+    }
+}
+
+/// Removes the instances of meshes whose material was removed, unless another material replaced it this frame.
+fn early_sweep_material_instances<M: Material>(
+    mut material_instances: ResMut<RenderMaterialInstances>,
+    mut removed: Extract<RemovedComponents<MeshMaterial3d<M>>>,
+) {
+    let last_change_tick = material_instances.current_change_tick;
+    for entity in removed.read() {
+        if let Entry::Occupied(entry) = material_instances.instances.entry(entity.into())
+            && entry.get().last_change_tick != last_change_tick
+        {
+            entry.remove();
+        }
+    }
+}